@@ -1,3 +1,5 @@
+use std::str::FromStr;
+
 use dotenvy::{ dotenv, var };
 use lazy_static::lazy_static;
 
@@ -18,10 +20,86 @@ pub struct Configuration {
     pub migration_location: String,
     pub migration_version: String,
     pub file_storage_location: String,
+    pub avatar_max_size: usize,
     pub jwt_ttl: u64,
     pub jwt_secret: String,
+    pub jwt_algorithm: String,
+    pub jwt_private_key_path: String,
+    pub jwt_key_id: String,
+    pub jwt_retired_secrets: Vec<String>,
+    pub jwt_retired_key_paths: Vec<String>,
+    pub oauth_clients: Vec<(String, String)>,
+    pub api_token_max_lifetime_days: u32,
+    pub impersonation_ttl: u64,
+    pub password_reset_ttl: u64,
+    pub password_reset_url: String,
+    pub password_reset_max_requests_per_identifier: i32,
+    pub password_reset_max_requests_per_ip: i32,
+    pub notifier: String,
+    pub notifier_file_path: String,
+    pub refresh_token_ttl: u64,
+    pub session_purge_interval: u64,
+    pub auth_cookie_enabled: bool,
+    pub auth_cookie_secure: bool,
+    pub auth_cookie_same_site: String,
+    pub auth_cookie_allowed_origins: Vec<String>,
+    pub auth_providers: Vec<String>,
+    pub local_registration_enabled: bool,
+    pub bcrypt_cost: u32,
+    pub totp_encryption_key: String,
+    pub totp_issuer: String,
+    pub totp_challenge_ttl: u64,
+    pub totp_challenge_max_attempts: i32,
+    pub totp_recovery_codes: usize,
+    pub login_max_failures_per_identifier: i32,
+    pub login_max_failures_per_ip: i32,
+    pub login_failure_window: u64,
+    pub login_lockout_duration: u64,
+    pub login_delay_initial: u64,
+    pub login_delay_max: u64,
+    pub login_trust_forwarded_for: bool,
+    pub login_trusted_proxy_hops: usize,
     pub ldap_url: String,
     pub ldap_auth_base_dn: String,
+    pub ldap_login_attribute: String,
+    pub ldap_user_filter: String,
+    pub ldap_users_filter: String,
+    pub ldap_attr_uid: String,
+    pub ldap_attr_name: String,
+    pub ldap_attr_email: String,
+    pub ldap_attr_display_name: String,
+    pub ldap_attr_given_name: String,
+    pub ldap_attr_title: String,
+    pub ldap_attr_telephone_number: String,
+    pub ldap_attr_department: String,
+    pub ldap_group_membership: String,
+    pub ldap_group_base_dn: String,
+    pub ldap_group_filter: String,
+    pub ldap_group_name_attribute: String,
+    pub admin_role: String,
+    pub ldap_registration_enabled: bool,
+    pub ldap_registration_rdn_attribute: String,
+    pub ldap_registration_template: Vec<(String, String)>,
+    pub ldap_registration_verify_email: bool,
+    pub ldap_registration_verify_ttl: u64,
+    pub ldap_registration_verify_url: String,
+    pub ldap_starttls: bool,
+    pub ldap_ca_cert_path: String,
+    pub ldap_client_cert_path: String,
+    pub ldap_client_key_path: String,
+    pub ldap_allow_insecure_bind: bool,
+    pub ldap_bind_dn: String,
+    pub ldap_bind_password: String,
+    pub ldap_pool_max_size: u32,
+    pub ldap_pool_min_idle: u32,
+    pub ldap_pool_idle_timeout: u64,
+    pub ldap_pool_connection_timeout: u64,
+    pub ldap_pool_health_check: bool,
+    pub ldap_connect_timeout: u64,
+    pub ldap_operation_timeout: u64,
+    pub ldap_reconnect_backoff_initial: u64,
+    pub ldap_reconnect_backoff_max: u64,
+    pub ldap_server_cooldown: u64,
 }
 
 fn get_configuration() -> Configuration {
@@ -38,12 +116,177 @@ fn get_configuration() -> Configuration {
         // latest - for running migration to the last one in migrations folder.
         migration_version: get_var_or_default("MIGRATE_TO", "latest"),
         file_storage_location: get_var_or_default("FILE_STORAGE_LOCATION", "file_storage"),
-        jwt_ttl: 72 * 3600,
+        // Largest accepted avatar upload in bytes.
+        avatar_max_size: get_parsed_var_or_default("AVATAR_MAX_SIZE", 2 * 1024 * 1024),
+        // Access tokens are short-lived, sessions are extended with refresh tokens.
+        jwt_ttl: get_parsed_var_or_default("JWT_TTL", 15 * 60),
         jwt_secret: get_var_or_default("JWT_SECRET", "1234567890"),
+        // Asymmetric algorithms sign with a PKCS#8 PEM private key and publish the public
+        // key at /.well-known/jwks.json, HS256 signs with JWT_SECRET.
+        jwt_algorithm: get_one_of_var_or_default(
+            "JWT_ALGORITHM",
+            "HS256",
+            &["HS256", "RS256", "ES256", "EdDSA"]
+        ),
+        jwt_private_key_path: get_var_or_default("JWT_PRIVATE_KEY_PATH", ""),
+        // Value of the kid header, derived from the key when empty.
+        jwt_key_id: get_var_or_default("JWT_KEY_ID", ""),
+        // Previous keys keep verifying tokens for JWT_TTL after a restart with a new key.
+        jwt_retired_secrets: get_comma_separated_var_or_default("JWT_RETIRED_SECRETS", ""),
+        jwt_retired_key_paths: get_comma_separated_var_or_default("JWT_RETIRED_KEY_PATHS", ""),
+        // Clients of the introspection and revocation endpoints as client_id:sha256(secret).
+        oauth_clients: get_client_credentials_var_or_default("OAUTH_CLIENTS", ""),
+        // Personal access tokens can not be created for longer than this.
+        api_token_max_lifetime_days: get_parsed_var_or_default("API_TOKEN_MAX_LIFETIME_DAYS", 365),
+        // Seconds an administrator may act as another user, the token is not refreshed.
+        impersonation_ttl: get_parsed_var_or_default("IMPERSONATION_TTL", 30 * 60),
+        // Forgotten passwords, {token} in the URL is replaced with the reset token.
+        password_reset_ttl: get_parsed_var_or_default("PASSWORD_RESET_TTL", 3600),
+        password_reset_url: get_var_or_default("PASSWORD_RESET_URL", ""),
+        // Reset links per login and per client address within LOGIN_FAILURE_WINDOW, 0 disables.
+        password_reset_max_requests_per_identifier: get_parsed_var_or_default(
+            "PASSWORD_RESET_MAX_REQUESTS_PER_IDENTIFIER",
+            3
+        ),
+        password_reset_max_requests_per_ip: get_parsed_var_or_default(
+            "PASSWORD_RESET_MAX_REQUESTS_PER_IP",
+            20
+        ),
+        // Delivery of messages to users, log and file are meant for offline testing.
+        notifier: get_one_of_var_or_default("NOTIFIER", "log", &["log", "file"]),
+        notifier_file_path: get_var_or_default("NOTIFIER_FILE_PATH", "notifications.jsonl"),
+        refresh_token_ttl: get_parsed_var_or_default("REFRESH_TOKEN_TTL", 30 * 24 * 3600),
+        // Expired sessions are deleted in the background, 0 disables the task.
+        session_purge_interval: get_parsed_var_or_default("SESSION_PURGE_INTERVAL", 3600),
+        // Browser sessions, login also sets the tokens as HttpOnly cookies and requests
+        // authenticated by them must repeat the CSRF cookie in the X-CSRF-Token header.
+        auth_cookie_enabled: get_parsed_var_or_default("AUTH_COOKIE_ENABLED", false),
+        auth_cookie_secure: get_parsed_var_or_default("AUTH_COOKIE_SECURE", true),
+        auth_cookie_same_site: get_same_site_var_or_default(
+            "AUTH_COOKIE_SAME_SITE",
+            "Strict",
+            get_parsed_var_or_default("AUTH_COOKIE_SECURE", true)
+        ),
+        // Origins of browser applications on other hosts, e.g. https://app.example.com.
+        auth_cookie_allowed_origins: get_comma_separated_var_or_default(
+            "AUTH_COOKIE_ALLOWED_ORIGINS",
+            ""
+        ),
+        // Authentication providers tried in the given order on login.
+        auth_providers: get_list_of_var_or_default("AUTH_PROVIDERS", "ldap", &["ldap", "local"]),
+        // Self-registration of local accounts, requires the local provider.
+        local_registration_enabled: get_parsed_var_or_default("LOCAL_REGISTRATION_ENABLED", false),
+        bcrypt_cost: get_bcrypt_cost_var_or_default("BCRYPT_COST", 10),
+        // Two-factor authentication, secrets are encrypted with the 32 bytes hex key.
+        // An empty key disables enrollment.
+        totp_encryption_key: get_hex_key_var_or_default("TOTP_ENCRYPTION_KEY", "", 32),
+        totp_issuer: get_var_or_default("TOTP_ISSUER", "rust-actix-boilerplate").replace(':', ""),
+        totp_challenge_ttl: get_parsed_var_or_default("TOTP_CHALLENGE_TTL", 300),
+        totp_challenge_max_attempts: get_parsed_var_or_default("TOTP_CHALLENGE_MAX_ATTEMPTS", 5),
+        totp_recovery_codes: get_parsed_var_or_default("TOTP_RECOVERY_CODES", 10),
+        // Brute-force protection, durations in seconds, 0 failures disables a limit.
+        login_max_failures_per_identifier: get_parsed_var_or_default(
+            "LOGIN_MAX_FAILURES_PER_IDENTIFIER",
+            5
+        ),
+        login_max_failures_per_ip: get_parsed_var_or_default("LOGIN_MAX_FAILURES_PER_IP", 50),
+        login_failure_window: get_parsed_var_or_default("LOGIN_FAILURE_WINDOW", 15 * 60),
+        login_lockout_duration: get_parsed_var_or_default("LOGIN_LOCKOUT_DURATION", 15 * 60),
+        login_delay_initial: get_parsed_var_or_default("LOGIN_DELAY_INITIAL", 1),
+        login_delay_max: get_parsed_var_or_default("LOGIN_DELAY_MAX", 30),
+        // Take the client address from X-Forwarded-For, only behind a trusted proxy.
+        login_trust_forwarded_for: get_parsed_var_or_default("LOGIN_TRUST_FORWARDED_FOR", false),
+        // Number of trusted proxies, each of them appends one X-Forwarded-For entry.
+        login_trusted_proxy_hops: get_parsed_var_or_default("LOGIN_TRUSTED_PROXY_HOPS", 1),
 
-        // ldap
+        // ldap, LDAP_URL is a comma separated list of servers
         ldap_url: get_var("LDAP_URL"),
         ldap_auth_base_dn: get_var("LDAP_AUTH_BASE_DN"),
+        // mail, uid, cn, userPrincipalName or any other attribute users log in with.
+        ldap_login_attribute: get_attribute_var_or_default("LDAP_LOGIN_ATTRIBUTE", "cn"),
+        ldap_user_filter: get_filter_template_var_or_default(
+            "LDAP_USER_FILTER",
+            "(&(objectClass=inetOrgPerson)({attribute}={identifier}))"
+        ),
+        ldap_users_filter: get_var_or_default("LDAP_USERS_FILTER", "(objectClass=inetOrgPerson)"),
+        // Mapping of LDAP attributes to user fields. uid, name and email are required,
+        // an empty value disables an optional field.
+        ldap_attr_uid: get_attribute_var_or_default("LDAP_ATTR_UID", "uid"),
+        ldap_attr_name: get_attribute_var_or_default("LDAP_ATTR_NAME", "sn"),
+        ldap_attr_email: get_attribute_var_or_default("LDAP_ATTR_EMAIL", "cn"),
+        ldap_attr_display_name: get_optional_attribute_var("LDAP_ATTR_DISPLAY_NAME", "displayName"),
+        ldap_attr_given_name: get_optional_attribute_var("LDAP_ATTR_GIVEN_NAME", "givenName"),
+        ldap_attr_title: get_optional_attribute_var("LDAP_ATTR_TITLE", "title"),
+        ldap_attr_telephone_number: get_optional_attribute_var(
+            "LDAP_ATTR_TELEPHONE_NUMBER",
+            "telephoneNumber"
+        ),
+        ldap_attr_department: get_optional_attribute_var(
+            "LDAP_ATTR_DEPARTMENT",
+            "departmentNumber"
+        ),
+        // Roles are resolved from groups, either by searching groups which list the user
+        // as a member ("member") or by reading the user's memberOf attribute ("memberOf").
+        ldap_group_membership: get_one_of_var_or_default(
+            "LDAP_GROUP_MEMBERSHIP",
+            "member",
+            &["member", "memberOf"]
+        ),
+        ldap_group_base_dn: get_var_or_default("LDAP_GROUP_BASE_DN", ""),
+        ldap_group_filter: get_var_or_default(
+            "LDAP_GROUP_FILTER",
+            "(|(&(objectClass=groupOfNames)(member={dn}))(&(objectClass=groupOfUniqueNames)(uniqueMember={dn})))"
+        ),
+        ldap_group_name_attribute: get_attribute_var_or_default("LDAP_GROUP_NAME_ATTRIBUTE", "cn"),
+        admin_role: get_var_or_default("ADMIN_ROLE", "admin").to_lowercase(),
+        // Self-registration into the directory. Entries are created under LDAP_AUTH_BASE_DN
+        // from the template, a list of attribute=value pairs separated by semicolons where
+        // {id}, {name}, {email} and {avatar} are replaced and empty values are left out.
+        ldap_registration_enabled: get_parsed_var_or_default("LDAP_REGISTRATION_ENABLED", false),
+        ldap_registration_rdn_attribute: get_attribute_var_or_default(
+            "LDAP_REGISTRATION_RDN_ATTRIBUTE",
+            "cn"
+        ),
+        ldap_registration_template: get_entry_template_var_or_default(
+            "LDAP_REGISTRATION_TEMPLATE",
+            "objectClass=top;objectClass=person;objectClass=organizationalPerson;\
+             objectClass=inetOrgPerson;cn={id};sn={name};uid={id};mail={email};labeledURI={avatar}"
+        ),
+        // New accounts can not log in until the link sent to their email was opened,
+        // {token} in the URL is replaced with the verification token.
+        ldap_registration_verify_email: get_parsed_var_or_default(
+            "LDAP_REGISTRATION_VERIFY_EMAIL",
+            false
+        ),
+        ldap_registration_verify_ttl: get_parsed_var_or_default(
+            "LDAP_REGISTRATION_VERIFY_TTL",
+            24 * 3600
+        ),
+        ldap_registration_verify_url: get_var_or_default("LDAP_REGISTRATION_VERIFY_URL", ""),
+        // TLS, use an ldaps:// url or enable StartTLS for ldap:// urls.
+        ldap_starttls: get_parsed_var_or_default("LDAP_STARTTLS", false),
+        ldap_ca_cert_path: get_var_or_default("LDAP_CA_CERT_PATH", ""),
+        ldap_client_cert_path: get_var_or_default("LDAP_CLIENT_CERT_PATH", ""),
+        ldap_client_key_path: get_var_or_default("LDAP_CLIENT_KEY_PATH", ""),
+        ldap_allow_insecure_bind: get_parsed_var_or_default("LDAP_ALLOW_INSECURE_BIND", false),
+        // Service account used by the connection pool, empty values mean anonymous bind.
+        ldap_bind_dn: get_var_or_default("LDAP_BIND_DN", ""),
+        ldap_bind_password: get_var_or_default("LDAP_BIND_PASSWORD", ""),
+        ldap_pool_max_size: get_parsed_var_or_default("LDAP_POOL_MAX_SIZE", 10),
+        ldap_pool_min_idle: get_parsed_var_or_default("LDAP_POOL_MIN_IDLE", 0),
+        ldap_pool_idle_timeout: get_parsed_var_or_default("LDAP_POOL_IDLE_TIMEOUT", 300),
+        ldap_pool_connection_timeout: get_parsed_var_or_default("LDAP_POOL_CONNECTION_TIMEOUT", 5),
+        ldap_pool_health_check: get_parsed_var_or_default("LDAP_POOL_HEALTH_CHECK", true),
+        // Timeouts and reconnection backoff, all values in seconds.
+        ldap_connect_timeout: get_parsed_var_or_default("LDAP_CONNECT_TIMEOUT", 5),
+        ldap_operation_timeout: get_parsed_var_or_default("LDAP_OPERATION_TIMEOUT", 10),
+        ldap_reconnect_backoff_initial: get_parsed_var_or_default(
+            "LDAP_RECONNECT_BACKOFF_INITIAL",
+            1
+        ),
+        ldap_reconnect_backoff_max: get_parsed_var_or_default("LDAP_RECONNECT_BACKOFF_MAX", 60),
+        // How long a failed server stays out of rotation when LDAP_URL lists several.
+        ldap_server_cooldown: get_parsed_var_or_default("LDAP_SERVER_COOLDOWN", 30),
     };
 }
 
@@ -56,6 +299,140 @@ fn get_var_or_default(key: &str, def_value: &str) -> String {
     return def_value.to_string();
 }
 
+fn get_parsed_var_or_default<T: FromStr>(key: &str, def_value: T) -> T {
+    let value = var(key);
+    if let Ok(unwrapped_value) = value {
+        if let Ok(parsed_value) = unwrapped_value.trim().parse::<T>() {
+            return parsed_value;
+        }
+        panic!("Error in parsing value from .env by key[{}]", key);
+    }
+    return def_value;
+}
+
+fn get_attribute_var_or_default(key: &str, def_value: &str) -> String {
+    let value = get_var_or_default(key, def_value);
+    if !is_attribute_name(&value) {
+        panic!("Value by key[{}] is not a valid LDAP attribute name", key);
+    }
+    return value;
+}
+
+fn is_attribute_name(value: &str) -> bool {
+    return value.chars().next().is_some_and(|c| c.is_ascii_alphabetic()) &&
+        value.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+}
+
+fn get_one_of_var_or_default(key: &str, def_value: &str, allowed: &[&str]) -> String {
+    let value = get_var_or_default(key, def_value);
+    if !allowed.contains(&value.as_str()) {
+        panic!("Value by key[{}] must be one of {:?}", key, allowed);
+    }
+    return value;
+}
+
+/// Browsers drop `SameSite=None` cookies which are not `Secure`.
+fn get_same_site_var_or_default(key: &str, def_value: &str, secure: bool) -> String {
+    let value = get_one_of_var_or_default(key, def_value, &["Strict", "Lax", "None"]);
+    if value == "None" && !secure {
+        panic!("Value by key[{}] can be None only together with AUTH_COOKIE_SECURE=true", key);
+    }
+    return value;
+}
+
+fn get_list_of_var_or_default(key: &str, def_value: &str, allowed: &[&str]) -> Vec<String> {
+    let mut values: Vec<String> = Vec::new();
+    for value in get_var_or_default(key, def_value).split(',') {
+        let value = value.trim();
+        if !allowed.contains(&value) {
+            panic!("Values by key[{}] must be a comma-separated list of {:?}", key, allowed);
+        }
+        if !values.iter().any(|existing| existing == value) {
+            values.push(value.to_string());
+        }
+    }
+    return values;
+}
+
+fn get_comma_separated_var_or_default(key: &str, def_value: &str) -> Vec<String> {
+    return get_var_or_default(key, def_value)
+        .split(',')
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+        .collect();
+}
+
+fn get_client_credentials_var_or_default(key: &str, def_value: &str) -> Vec<(String, String)> {
+    let mut clients: Vec<(String, String)> = Vec::new();
+    for client in get_comma_separated_var_or_default(key, def_value) {
+        let credentials = client
+            .split_once(':')
+            .filter(|(_, hash)| hash.len() == 64 && hash.chars().all(|c| c.is_ascii_hexdigit()));
+        match credentials {
+            Some((client_id, hash)) => clients.push((client_id.to_string(), hash.to_lowercase())),
+            None => panic!("Values by key[{}] must be client_id:sha256 hex pairs", key),
+        }
+    }
+    return clients;
+}
+
+fn get_bcrypt_cost_var_or_default(key: &str, def_value: u32) -> u32 {
+    let value = get_parsed_var_or_default(key, def_value);
+    if !(4..=31).contains(&value) {
+        panic!("Value by key[{}] must be between 4 and 31", key);
+    }
+    return value;
+}
+
+fn get_hex_key_var_or_default(key: &str, def_value: &str, length: usize) -> String {
+    let value = get_var_or_default(key, def_value);
+    let is_valid =
+        value.is_empty() ||
+        (value.len() == length * 2 && value.chars().all(|c| c.is_ascii_hexdigit()));
+    if !is_valid {
+        panic!("Value by key[{}] must be a {} bytes hex encoded key", key, length);
+    }
+    return value;
+}
+
+fn get_optional_attribute_var(key: &str, def_value: &str) -> String {
+    if get_var_or_default(key, def_value).is_empty() {
+        return String::new();
+    }
+    return get_attribute_var_or_default(key, def_value);
+}
+
+fn get_filter_template_var_or_default(key: &str, def_value: &str) -> String {
+    let value = get_var_or_default(key, def_value);
+    if !value.contains("{identifier}") {
+        panic!("Value by key[{}] must contain the {{identifier}} placeholder", key);
+    }
+    return value;
+}
+
+fn get_entry_template_var_or_default(key: &str, def_value: &str) -> Vec<(String, String)> {
+    let mut template: Vec<(String, String)> = Vec::new();
+    for pair in get_var_or_default(key, def_value).split(';') {
+        let pair = pair.trim();
+        if pair.is_empty() {
+            continue;
+        }
+        let attribute = pair
+            .split_once('=')
+            .filter(|(attribute, _)| is_attribute_name(attribute.trim()));
+        match attribute {
+            Some((attribute, value)) => {
+                template.push((attribute.trim().to_string(), value.trim().to_string()));
+            }
+            None => panic!("Values by key[{}] must be attribute=value pairs", key),
+        }
+    }
+    if template.is_empty() {
+        panic!("Value by key[{}] must not be empty", key);
+    }
+    return template;
+}
+
 fn get_var(key: &str) -> String {
     let value = var(key);
     if let Ok(unwrapped_value) = value {
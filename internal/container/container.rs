@@ -1,14 +1,63 @@
 use std::sync::{ Arc, RwLock };
 use config::CONFIGURATION;
 use diesel::{ r2d2::{ ConnectionManager, Pool }, PgConnection };
-use ldap3::{ Ldap, LdapConnAsync, LdapError };
 
 use crate::{
+    filesystem::image_storage_service::ImageStorageService,
     infra::{
-        database::{ session_repository::SessionRepository, user_repository::UserRepository },
-        http::controllers::{ auth_controller::AuthController, user_controller::UserController },
+        database::{
+            api_token_repository::ApiTokenRepository,
+            audit_event_repository::AuditEventRepository,
+            local_user_repository::LocalUserRepository,
+            login_attempt_repository::LoginAttemptRepository,
+            login_challenge_repository::LoginChallengeRepository,
+            password_reset_token_repository::PasswordResetTokenRepository,
+            pending_registration_repository::PendingRegistrationRepository,
+            refresh_token_repository::RefreshTokenRepository,
+            session_repository::SessionRepository,
+            totp_repository::TotpRepository,
+            user_repository::UserRepository,
+        },
+        http::controllers::{
+            api_token_controller::ApiTokenController,
+            auth_controller::AuthController,
+            health_controller::HealthController,
+            impersonation_controller::ImpersonationController,
+            jwks_controller::JwksController,
+            oauth_controller::OAuthController,
+            password_reset_controller::PasswordResetController,
+            registration_controller::RegistrationController,
+            session_controller::SessionController,
+            totp_controller::TotpController,
+            user_controller::UserController,
+        },
+        ldap::ldap_client::LdapClient,
+    },
+    services::{
+        api_token_service::ApiTokenService,
+        audit_service::AuditService,
+        auth_service::{ AuthRepositories, AuthService },
+        impersonation_service::ImpersonationService,
+        jwt_key_service::JwtKeyService,
+        login_throttle_service::LoginThrottleService,
+        notifiers::{
+            file_notifier::{ FileNotifier, FILE_NOTIFIER },
+            log_notifier::{ LogNotifier, LOG_NOTIFIER },
+            Notifier,
+        },
+        oauth_service::OAuthService,
+        password_reset_service::PasswordResetService,
+        registration_service::RegistrationService,
+        providers::{
+            ldap_provider::{ LdapAuthenticationProvider, LDAP_PROVIDER },
+            local_provider::{ LocalAuthenticationProvider, LOCAL_PROVIDER },
+            AuthenticationProvider,
+            AuthenticationProviders,
+        },
+        session_purge::spawn_session_purge,
+        totp_service::TotpService,
+        user_service::{ UserDataRepositories, UserService },
     },
-    services::{ auth_service::AuthService, user_service::UserService },
 };
 
 #[allow(dead_code)]
@@ -21,11 +70,28 @@ pub struct Container {
 pub struct Services {
     pub user_service: Arc<UserService>,
     pub auth_service: Arc<AuthService>,
+    pub totp_service: Arc<TotpService>,
+    pub jwt_key_service: Arc<JwtKeyService>,
+    pub oauth_service: Arc<OAuthService>,
+    pub api_token_service: Arc<ApiTokenService>,
+    pub audit_service: Arc<AuditService>,
+    pub impersonation_service: Arc<ImpersonationService>,
+    pub password_reset_service: Arc<PasswordResetService>,
+    pub registration_service: Arc<RegistrationService>,
 }
 #[derive(Clone)]
 pub struct Controllers {
     pub user_controller: UserController,
     pub auth_controller: AuthController,
+    pub health_controller: HealthController,
+    pub totp_controller: TotpController,
+    pub session_controller: SessionController,
+    pub jwks_controller: JwksController,
+    pub oauth_controller: OAuthController,
+    pub api_token_controller: ApiTokenController,
+    pub impersonation_controller: ImpersonationController,
+    pub password_reset_controller: PasswordResetController,
+    pub registration_controller: RegistrationController,
 }
 
 pub async fn new() -> Result<Container, Box<dyn std::error::Error + Send + Sync + 'static>> {
@@ -36,30 +102,156 @@ pub async fn new() -> Result<Container, Box<dyn std::error::Error + Send + Sync
         .build(manager)?;
 
     let pool = Arc::new(RwLock::new(pool));
-    let ldap_connection = Arc::new(tokio::sync::RwLock::new(get_ldap_connection().await?));
+    let ldap_client = LdapClient::new().await?;
 
-    let user_repository = UserRepository::new(Arc::clone(&ldap_connection));
+    let user_repository = UserRepository::new(Arc::clone(&ldap_client));
     let session_repository = SessionRepository::new(Arc::clone(&pool));
-    //let file_service = Arc::new(ImageStorageService::new(&CONFIGURATION.file_storage_location));
+    let refresh_token_repository = RefreshTokenRepository::new(Arc::clone(&pool));
+    let local_user_repository = LocalUserRepository::new(Arc::clone(&pool));
+    let login_challenge_repository = LoginChallengeRepository::new(Arc::clone(&pool));
+    let totp_repository = TotpRepository::new(Arc::clone(&pool));
+    let login_attempt_repository = LoginAttemptRepository::new(Arc::clone(&pool));
+    let login_throttle_service = LoginThrottleService::new(Arc::clone(&login_attempt_repository));
+    let jwt_key_service = JwtKeyService::new()?;
+    let pending_registration_repository = PendingRegistrationRepository::new(Arc::clone(&pool));
+    let providers = get_authentication_providers(
+        Arc::clone(&ldap_client),
+        Arc::clone(&user_repository),
+        Arc::clone(&local_user_repository),
+        Arc::clone(&pending_registration_repository)
+    );
+    let totp_service = TotpService::new(Arc::clone(&totp_repository), Arc::clone(&providers));
+    let api_token_repository = ApiTokenRepository::new(Arc::clone(&pool));
+    let api_token_service = ApiTokenService::new(
+        Arc::clone(&api_token_repository),
+        Arc::clone(&providers)
+    );
+    let audit_event_repository = AuditEventRepository::new(Arc::clone(&pool));
+    let audit_service = AuditService::new(Arc::clone(&audit_event_repository));
+    let password_reset_token_repository = PasswordResetTokenRepository::new(Arc::clone(&pool));
+    let notifier = get_notifier();
+    let image_storage_service = ImageStorageService::new(&CONFIGURATION.file_storage_location);
     let services: Arc<Services> = Arc::new(Services {
-        user_service: UserService::new(Arc::clone(&user_repository)),
+        user_service: UserService::new(
+            Arc::clone(&providers),
+            Arc::clone(&user_repository),
+            UserDataRepositories {
+                session_repository: Arc::clone(&session_repository),
+                api_token_repository: Arc::clone(&api_token_repository),
+                totp_repository: Arc::clone(&totp_repository),
+                password_reset_token_repository: Arc::clone(&password_reset_token_repository),
+                pending_registration_repository: Arc::clone(&pending_registration_repository),
+            },
+            image_storage_service
+        ),
         auth_service: AuthService::new(
-            Arc::clone(&ldap_connection),
-            Arc::clone(&session_repository)
+            Arc::clone(&providers),
+            AuthRepositories {
+                local_user_repository: Arc::clone(&local_user_repository),
+                session_repository: Arc::clone(&session_repository),
+                refresh_token_repository: Arc::clone(&refresh_token_repository),
+                login_challenge_repository: Arc::clone(&login_challenge_repository),
+            },
+            Arc::clone(&totp_service),
+            Arc::clone(&login_throttle_service),
+            Arc::clone(&jwt_key_service),
+            Arc::clone(&api_token_service)
+        ),
+        totp_service,
+        impersonation_service: ImpersonationService::new(
+            Arc::clone(&providers),
+            Arc::clone(&session_repository),
+            Arc::clone(&jwt_key_service),
+            Arc::clone(&audit_service)
+        ),
+        password_reset_service: PasswordResetService::new(
+            Arc::clone(&providers),
+            Arc::clone(&password_reset_token_repository),
+            Arc::clone(&session_repository),
+            Arc::clone(&api_token_service),
+            Arc::clone(&login_throttle_service),
+            Arc::clone(&notifier)
+        ),
+        registration_service: RegistrationService::new(
+            Arc::clone(&providers),
+            Arc::clone(&user_repository),
+            Arc::clone(&pending_registration_repository),
+            notifier
+        ),
+        oauth_service: OAuthService::new(
+            Arc::clone(&jwt_key_service),
+            Arc::clone(&session_repository),
+            Arc::clone(&refresh_token_repository),
+            Arc::clone(&api_token_service)
         ),
+        jwt_key_service,
+        api_token_service,
+        audit_service,
     });
     let controllers: Controllers = Controllers {
-        user_controller: UserController::new(Arc::clone(&services.user_service)),
+        user_controller: UserController::new(
+            Arc::clone(&services.user_service),
+            Arc::clone(&services.registration_service)
+        ),
         auth_controller: AuthController::new(Arc::clone(&services.auth_service)),
+        health_controller: HealthController::new(Arc::clone(&ldap_client)),
+        totp_controller: TotpController::new(Arc::clone(&services.totp_service)),
+        session_controller: SessionController::new(Arc::clone(&services.auth_service)),
+        jwks_controller: JwksController::new(Arc::clone(&services.jwt_key_service)),
+        oauth_controller: OAuthController::new(Arc::clone(&services.oauth_service)),
+        api_token_controller: ApiTokenController::new(Arc::clone(&services.api_token_service)),
+        impersonation_controller: ImpersonationController::new(
+            Arc::clone(&services.impersonation_service)
+        ),
+        password_reset_controller: PasswordResetController::new(
+            Arc::clone(&services.password_reset_service)
+        ),
+        registration_controller: RegistrationController::new(
+            Arc::clone(&services.registration_service)
+        ),
     };
+    spawn_session_purge(Arc::clone(&session_repository));
     let container = Container { services, controllers };
     return Ok(container);
 }
 
-async fn get_ldap_connection() -> Result<Ldap, LdapError> {
-    let (conn, ldap) = LdapConnAsync::new(&CONFIGURATION.ldap_url).await?;
-    ldap3::drive!(conn);
-    Ok(ldap)
+/// Builds the providers in the order given by AUTH_PROVIDERS.
+fn get_authentication_providers(
+    ldap_client: Arc<LdapClient>,
+    user_repository: Arc<UserRepository>,
+    local_user_repository: Arc<LocalUserRepository>,
+    pending_registration_repository: Arc<PendingRegistrationRepository>
+) -> Arc<AuthenticationProviders> {
+    let mut providers: Vec<Arc<dyn AuthenticationProvider>> = Vec::new();
+    for name in CONFIGURATION.auth_providers.iter() {
+        match name.as_str() {
+            LDAP_PROVIDER => {
+                providers.push(
+                    LdapAuthenticationProvider::new(
+                        Arc::clone(&ldap_client),
+                        Arc::clone(&user_repository),
+                        Arc::clone(&pending_registration_repository)
+                    )
+                );
+            }
+            LOCAL_PROVIDER => {
+                providers.push(
+                    LocalAuthenticationProvider::new(Arc::clone(&local_user_repository))
+                );
+            }
+            _ => unreachable!("Unknown authentication provider [{}]", name),
+        }
+    }
+    return AuthenticationProviders::new(providers);
+}
+
+/// Builds the notifier selected by NOTIFIER.
+fn get_notifier() -> Arc<dyn Notifier> {
+    return match CONFIGURATION.notifier.as_str() {
+        LOG_NOTIFIER => LogNotifier::new(),
+        FILE_NOTIFIER => FileNotifier::new(&CONFIGURATION.notifier_file_path),
+        name => unreachable!("Unknown notifier [{}]", name),
+    };
 }
 
 fn get_database_connection() -> ConnectionManager<PgConnection> {
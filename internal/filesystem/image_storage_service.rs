@@ -1,6 +1,7 @@
 use std::{ error, fs, io::{ self, Write }, path::{ Path, PathBuf } };
 
-use rand::Rng;
+/// Extensions of the image types which are accepted, see `image_extension`.
+pub const IMAGE_EXTENSIONS: [&str; 4] = ["png", "jpg", "gif", "webp"];
 
 #[derive(Clone)]
 pub struct ImageStorageService {
@@ -12,15 +13,25 @@ impl ImageStorageService {
         return ImageStorageService { loc: location.to_owned() };
     }
 
+    /// Writes the image under the given name, an existing file is replaced. The content
+    /// goes to a temporary file first, so readers never see a partly written image.
     pub fn save_image(
         &self,
         filename: &str,
         content: &[u8]
     ) -> Result<String, Box<dyn error::Error + Send + Sync + 'static>> {
-        let new_filename = self.image_name_generator(filename)?;
-        let full_path = Path::new(&self.loc).join(new_filename.as_str());
-        ImageStorageService::write_file_to_storage(full_path, content)?;
-        return Ok(new_filename);
+        let full_path = Path::new(&self.loc).join(filename);
+        let temporary_path = Path::new(&self.loc).join(format!("{}.tmp", filename));
+        ImageStorageService::write_file_to_storage(temporary_path.clone(), content)?;
+        if let Err(e) = fs::rename(&temporary_path, &full_path) {
+            let _ = fs::remove_file(&temporary_path);
+            return Err(Box::from(e));
+        }
+        return Ok(filename.to_owned());
+    }
+
+    pub fn exists(&self, filename: &str) -> bool {
+        return Path::new(&self.loc).join(filename).exists();
     }
 
     pub fn remove_file_image(
@@ -32,22 +43,14 @@ impl ImageStorageService {
         return Ok(());
     }
 
-    fn image_name_generator(
+    /// Moves the image to another name, an existing file is replaced.
+    pub fn rename_file_image(
         &self,
-        filename: &str
-    ) -> Result<String, Box<dyn error::Error + Send + Sync + 'static>> {
-        let full_path = Path::new(&self.loc).join(filename);
-        if full_path.exists() {
-            let num: u64 = rand::thread_rng().gen();
-            let num_str: String = num.to_string();
-            let parts: Vec<&str> = filename.split('.').collect();
-            if parts.len() != 2 {
-                return Err(Box::from("Uploaded file has not correct filename"));
-            }
-            let new_file_name = format!("{}_{}.{}", parts[0], num_str, parts[1]);
-            return self.image_name_generator(&new_file_name);
-        }
-        return Ok(filename.to_owned());
+        filename: &str,
+        new_filename: &str
+    ) -> Result<(), Box<dyn error::Error + Send + Sync + 'static>> {
+        fs::rename(Path::new(&self.loc).join(filename), Path::new(&self.loc).join(new_filename))?;
+        return Ok(());
     }
 
     fn write_file_to_storage(location: PathBuf, content: &[u8]) -> io::Result<()> {
@@ -57,3 +60,59 @@ impl ImageStorageService {
         Ok(())
     }
 }
+
+/// Detects the image type from its signature, the declared content type is not trusted.
+pub fn image_extension(content: &[u8]) -> Option<&'static str> {
+    if content.starts_with(b"\x89PNG\r\n\x1a\n") {
+        return Some("png");
+    }
+    if content.starts_with(b"\xff\xd8\xff") {
+        return Some("jpg");
+    }
+    if content.starts_with(b"GIF87a") || content.starts_with(b"GIF89a") {
+        return Some("gif");
+    }
+    if content.len() >= 12 && content.starts_with(b"RIFF") && &content[8..12] == b"WEBP" {
+        return Some("webp");
+    }
+    return None;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::image_extension;
+
+    #[test]
+    fn detects_supported_types() {
+        assert_eq!(image_extension(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"), Some("png"));
+        assert_eq!(image_extension(b"\xff\xd8\xff\xe0\0\x10JFIF"), Some("jpg"));
+        assert_eq!(image_extension(b"GIF87a\x01\0\x01\0"), Some("gif"));
+        assert_eq!(image_extension(b"GIF89a\x01\0\x01\0"), Some("gif"));
+        assert_eq!(image_extension(b"RIFF\x24\0\0\0WEBPVP8 "), Some("webp"));
+    }
+
+    #[test]
+    fn rejects_truncated_signatures() {
+        assert_eq!(image_extension(b""), None);
+        assert_eq!(image_extension(b"\x89PNG\r\n\x1a"), None);
+        assert_eq!(image_extension(b"\xff\xd8"), None);
+        assert_eq!(image_extension(b"GIF89"), None);
+        assert_eq!(image_extension(b"RIFF\x24\0\0\0WEB"), None);
+    }
+
+    #[test]
+    fn rejects_markup_and_other_riff_files() {
+        assert_eq!(image_extension(b"<svg xmlns=\"http://www.w3.org/2000/svg\"/>"), None);
+        assert_eq!(image_extension(b"<html>\x89PNG\r\n\x1a\n"), None);
+        assert_eq!(image_extension(b" \x89PNG\r\n\x1a\n"), None);
+        assert_eq!(image_extension(b"RIFF\x24\0\0\0WAVEfmt "), None);
+    }
+
+    #[test]
+    fn detects_polyglots_by_their_leading_signature() {
+        // The rest of the file is not inspected, such files are only ever served with the
+        // image type of their signature.
+        assert_eq!(image_extension(b"GIF89a/*<script>alert(1)</script>*/"), Some("gif"));
+        assert_eq!(image_extension(b"\xff\xd8\xff<html><body></body></html>"), Some("jpg"));
+    }
+}
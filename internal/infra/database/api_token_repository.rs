@@ -0,0 +1,140 @@
+use std::sync::{ Arc, RwLock };
+
+use chrono::NaiveDateTime;
+use diesel::{
+    prelude::{ Insertable, Queryable },
+    query_dsl::methods::{ FilterDsl, OrderDsl },
+    r2d2::{ ConnectionManager, Pool, PooledConnection },
+    ExpressionMethods,
+    OptionalExtension,
+    PgConnection,
+    RunQueryDsl,
+    Selectable,
+};
+use uuid::Uuid;
+
+diesel::table! {
+    api_tokens (id) {
+        id -> Uuid,
+        user_id -> Text,
+        provider -> Text,
+        name -> Text,
+        token_hash -> Text,
+        scopes -> Array<Text>,
+        expires_at -> Timestamp,
+        last_used_at -> Nullable<Timestamp>,
+        created_at -> Timestamp,
+    }
+}
+
+#[derive(Selectable, Queryable, Debug)]
+#[diesel(table_name = api_tokens)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct ApiToken {
+    pub id: Uuid,
+    pub user_id: String,
+    pub provider: String,
+    pub name: String,
+    pub token_hash: String,
+    pub scopes: Vec<String>,
+    pub expires_at: NaiveDateTime,
+    pub last_used_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = api_tokens)]
+pub struct NewApiToken {
+    pub id: Uuid,
+    pub user_id: String,
+    pub provider: String,
+    pub name: String,
+    pub token_hash: String,
+    pub scopes: Vec<String>,
+    pub expires_at: NaiveDateTime,
+}
+
+#[derive(Clone)]
+pub struct ApiTokenRepository {
+    pub pool: Arc<RwLock<Pool<ConnectionManager<PgConnection>>>>,
+}
+
+impl ApiTokenRepository {
+    pub fn new(
+        pool: Arc<RwLock<Pool<ConnectionManager<PgConnection>>>>
+    ) -> Arc<ApiTokenRepository> {
+        return Arc::new(ApiTokenRepository { pool });
+    }
+
+    fn get_connection(&self) -> PooledConnection<ConnectionManager<PgConnection>> {
+        self.pool.write().unwrap().get().expect("Failed to get a connection")
+    }
+
+    pub fn save(&self, token: NewApiToken) -> Result<ApiToken, diesel::result::Error> {
+        use self::api_tokens::dsl::*;
+        let result = diesel
+            ::insert_into(api_tokens)
+            .values(&token)
+            .get_result::<ApiToken>(&mut self.get_connection())?;
+        return Ok(result);
+    }
+
+    pub fn find_by_hash(&self, hash: &str) -> Result<Option<ApiToken>, diesel::result::Error> {
+        use self::api_tokens::dsl::*;
+        let result = api_tokens
+            .filter(token_hash.eq(hash))
+            .first::<ApiToken>(&mut self.get_connection())
+            .optional()?;
+        return Ok(result);
+    }
+
+    pub fn find_by_user_id(&self, owner_id: &str) -> Result<Vec<ApiToken>, diesel::result::Error> {
+        use self::api_tokens::dsl::*;
+        let result = api_tokens
+            .filter(user_id.eq(owner_id))
+            .order(created_at.desc())
+            .load::<ApiToken>(&mut self.get_connection())?;
+        return Ok(result);
+    }
+
+    pub fn touch(
+        &self,
+        token_id: Uuid,
+        used_at: NaiveDateTime
+    ) -> Result<usize, diesel::result::Error> {
+        use self::api_tokens::dsl::*;
+        let result = diesel
+            ::update(api_tokens.filter(id.eq(token_id)))
+            .set(last_used_at.eq(used_at))
+            .execute(&mut self.get_connection());
+        return result;
+    }
+
+    pub fn delete(&self, owner_id: &str, token_id: Uuid) -> Result<usize, diesel::result::Error> {
+        use self::api_tokens::dsl::*;
+        let result = diesel
+            ::delete(api_tokens.filter(user_id.eq(owner_id)).filter(id.eq(token_id)))
+            .execute(&mut self.get_connection());
+        return result;
+    }
+
+    pub fn delete_by_hash(&self, hash: &str) -> Result<usize, diesel::result::Error> {
+        use self::api_tokens::dsl::*;
+        let result = diesel
+            ::delete(api_tokens.filter(token_hash.eq(hash)))
+            .execute(&mut self.get_connection());
+        return result;
+    }
+
+    pub fn delete_by_user_id(
+        &self,
+        provider_name: &str,
+        owner_id: &str
+    ) -> Result<usize, diesel::result::Error> {
+        use self::api_tokens::dsl::*;
+        let result = diesel
+            ::delete(api_tokens.filter(provider.eq(provider_name)).filter(user_id.eq(owner_id)))
+            .execute(&mut self.get_connection());
+        return result;
+    }
+}
@@ -0,0 +1,60 @@
+use std::sync::{ Arc, RwLock };
+
+use diesel::{
+    prelude::Insertable,
+    r2d2::{ ConnectionManager, Pool, PooledConnection },
+    PgConnection,
+    RunQueryDsl,
+};
+use uuid::Uuid;
+
+diesel::table! {
+    audit_events (id) {
+        id -> Uuid,
+        actor_id -> Text,
+        action -> Text,
+        target_user_id -> Nullable<Text>,
+        session_uuid -> Nullable<Uuid>,
+        ip -> Nullable<Text>,
+        details -> Nullable<Text>,
+        created_at -> Timestamp,
+    }
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = audit_events)]
+pub struct NewAuditEvent {
+    pub id: Uuid,
+    pub actor_id: String,
+    pub action: String,
+    pub target_user_id: Option<String>,
+    pub session_uuid: Option<Uuid>,
+    pub ip: Option<String>,
+    pub details: Option<String>,
+}
+
+#[derive(Clone)]
+pub struct AuditEventRepository {
+    pub pool: Arc<RwLock<Pool<ConnectionManager<PgConnection>>>>,
+}
+
+impl AuditEventRepository {
+    pub fn new(
+        pool: Arc<RwLock<Pool<ConnectionManager<PgConnection>>>>
+    ) -> Arc<AuditEventRepository> {
+        return Arc::new(AuditEventRepository { pool });
+    }
+
+    fn get_connection(&self) -> PooledConnection<ConnectionManager<PgConnection>> {
+        self.pool.write().unwrap().get().expect("Failed to get a connection")
+    }
+
+    pub fn save(&self, event: NewAuditEvent) -> Result<usize, diesel::result::Error> {
+        use self::audit_events::dsl::*;
+        let result = diesel
+            ::insert_into(audit_events)
+            .values(&event)
+            .execute(&mut self.get_connection());
+        return result;
+    }
+}
@@ -0,0 +1,136 @@
+use std::sync::{ Arc, RwLock };
+
+use chrono::NaiveDateTime;
+use diesel::{
+    prelude::{ Insertable, Queryable },
+    query_dsl::methods::{ FilterDsl, OrderDsl, SelectDsl },
+    r2d2::{ ConnectionManager, Pool, PooledConnection },
+    BoolExpressionMethods,
+    ExpressionMethods,
+    OptionalExtension,
+    PgConnection,
+    RunQueryDsl,
+    Selectable,
+};
+
+use crate::services::providers::local_provider::LOCAL_PROVIDER;
+
+use super::user_repository::User;
+
+diesel::table! {
+    users (id) {
+        id -> Text,
+        name -> Text,
+        email -> Text,
+        password -> Text,
+        roles -> Array<Text>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+#[derive(Selectable, Queryable, Debug)]
+#[diesel(table_name = users)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct LocalUser {
+    pub id: String,
+    pub name: String,
+    pub email: String,
+    pub password: String,
+    pub roles: Vec<String>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+impl LocalUser {
+    /// Local users have no directory entry, so the DN is left empty.
+    pub fn to_user(&self) -> User {
+        return User {
+            provider: Arc::from(LOCAL_PROVIDER),
+            dn: Arc::from(""),
+            id: Arc::from(self.id.as_str()),
+            uid: Arc::from(self.id.as_str()),
+            name: Arc::from(self.name.as_str()),
+            email: Arc::from(self.email.as_str()),
+            display_name: None,
+            given_name: None,
+            title: None,
+            telephone_numbers: Vec::new(),
+            department: None,
+        };
+    }
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = users)]
+pub struct NewLocalUser {
+    pub id: String,
+    pub name: String,
+    pub email: String,
+    pub password: String,
+}
+
+#[derive(Clone)]
+pub struct LocalUserRepository {
+    pub pool: Arc<RwLock<Pool<ConnectionManager<PgConnection>>>>,
+}
+
+impl LocalUserRepository {
+    pub fn new(
+        pool: Arc<RwLock<Pool<ConnectionManager<PgConnection>>>>
+    ) -> Arc<LocalUserRepository> {
+        return Arc::new(LocalUserRepository { pool });
+    }
+
+    fn get_connection(&self) -> PooledConnection<ConnectionManager<PgConnection>> {
+        self.pool.write().unwrap().get().expect("Failed to get a connection")
+    }
+
+    pub fn find_all(&self) -> Result<Vec<LocalUser>, diesel::result::Error> {
+        use self::users::dsl::*;
+        let result = users.order(id.asc()).load::<LocalUser>(&mut self.get_connection())?;
+        return Ok(result);
+    }
+
+    pub fn find_by_id(&self, user_id: &str) -> Result<Option<LocalUser>, diesel::result::Error> {
+        use self::users::dsl::*;
+        let result = users
+            .filter(id.eq(user_id))
+            .first::<LocalUser>(&mut self.get_connection())
+            .optional()?;
+        return Ok(result);
+    }
+
+    /// Checks whether an account already uses the id or the email address.
+    pub fn exists(&self, user_id: &str, user_email: &str) -> Result<bool, diesel::result::Error> {
+        use self::users::dsl::*;
+        let result = users
+            .filter(id.eq(user_id).or(email.eq(user_email)))
+            .select(id)
+            .first::<String>(&mut self.get_connection())
+            .optional()?;
+        return Ok(result.is_some());
+    }
+
+    pub fn save(&self, user: NewLocalUser) -> Result<LocalUser, diesel::result::Error> {
+        use self::users::dsl::*;
+        let result = diesel
+            ::insert_into(users)
+            .values(&user)
+            .get_result::<LocalUser>(&mut self.get_connection())?;
+        return Ok(result);
+    }
+
+    pub fn update_password(
+        &self,
+        user_id: &str,
+        password_hash: &str
+    ) -> Result<usize, diesel::result::Error> {
+        use self::users::dsl::*;
+        let result = diesel
+            ::update(users.filter(id.eq(user_id)))
+            .set(password.eq(password_hash))
+            .execute(&mut self.get_connection())?;
+        return Ok(result);
+    }
+}
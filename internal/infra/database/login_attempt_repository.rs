@@ -0,0 +1,120 @@
+use std::sync::{ Arc, RwLock };
+
+use chrono::NaiveDateTime;
+use diesel::{
+    prelude::{ Queryable, QueryableByName },
+    query_dsl::methods::FilterDsl,
+    r2d2::{ ConnectionManager, Pool, PooledConnection },
+    sql_types::{ Text, Timestamp },
+    ExpressionMethods,
+    OptionalExtension,
+    PgConnection,
+    RunQueryDsl,
+    Selectable,
+};
+
+diesel::table! {
+    login_attempts (kind, key) {
+        kind -> Text,
+        key -> Text,
+        failures -> Integer,
+        window_started_at -> Timestamp,
+        locked_until -> Nullable<Timestamp>,
+    }
+}
+
+#[derive(Selectable, Queryable, QueryableByName, Debug)]
+#[diesel(table_name = login_attempts)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct LoginAttempt {
+    pub kind: String,
+    pub key: String,
+    pub failures: i32,
+    pub window_started_at: NaiveDateTime,
+    pub locked_until: Option<NaiveDateTime>,
+}
+
+#[derive(Clone)]
+pub struct LoginAttemptRepository {
+    pub pool: Arc<RwLock<Pool<ConnectionManager<PgConnection>>>>,
+}
+
+impl LoginAttemptRepository {
+    pub fn new(
+        pool: Arc<RwLock<Pool<ConnectionManager<PgConnection>>>>
+    ) -> Arc<LoginAttemptRepository> {
+        return Arc::new(LoginAttemptRepository { pool });
+    }
+
+    fn get_connection(&self) -> PooledConnection<ConnectionManager<PgConnection>> {
+        self.pool.write().unwrap().get().expect("Failed to get a connection")
+    }
+
+    pub fn find(
+        &self,
+        attempt_kind: &str,
+        attempt_key: &str
+    ) -> Result<Option<LoginAttempt>, diesel::result::Error> {
+        use self::login_attempts::dsl::*;
+        let result = login_attempts
+            .filter(kind.eq(attempt_kind))
+            .filter(key.eq(attempt_key))
+            .first::<LoginAttempt>(&mut self.get_connection())
+            .optional()?;
+        return Ok(result);
+    }
+
+    /// Atomically counts a failure. Counting starts over when the window started before
+    /// `window_expired_before`, so concurrent instances never lose an increment.
+    pub fn register_failure(
+        &self,
+        attempt_kind: &str,
+        attempt_key: &str,
+        now: NaiveDateTime,
+        window_expired_before: NaiveDateTime
+    ) -> Result<LoginAttempt, diesel::result::Error> {
+        let result = diesel
+            ::sql_query(
+                "INSERT INTO login_attempts (kind, key, failures, window_started_at) \
+                 VALUES ($1, $2, 1, $3) \
+                 ON CONFLICT (kind, key) DO UPDATE SET \
+                 failures = CASE WHEN login_attempts.window_started_at < $4 \
+                     THEN 1 ELSE login_attempts.failures + 1 END, \
+                 window_started_at = CASE WHEN login_attempts.window_started_at < $4 \
+                     THEN $3 ELSE login_attempts.window_started_at END \
+                 RETURNING kind, key, failures, window_started_at, locked_until"
+            )
+            .bind::<Text, _>(attempt_kind)
+            .bind::<Text, _>(attempt_key)
+            .bind::<Timestamp, _>(now)
+            .bind::<Timestamp, _>(window_expired_before)
+            .get_result::<LoginAttempt>(&mut self.get_connection())?;
+        return Ok(result);
+    }
+
+    pub fn lock(
+        &self,
+        attempt_kind: &str,
+        attempt_key: &str,
+        until: NaiveDateTime
+    ) -> Result<usize, diesel::result::Error> {
+        use self::login_attempts::dsl::*;
+        let result = diesel
+            ::update(login_attempts.filter(kind.eq(attempt_kind)).filter(key.eq(attempt_key)))
+            .set(locked_until.eq(until))
+            .execute(&mut self.get_connection())?;
+        return Ok(result);
+    }
+
+    pub fn delete(
+        &self,
+        attempt_kind: &str,
+        attempt_key: &str
+    ) -> Result<usize, diesel::result::Error> {
+        use self::login_attempts::dsl::*;
+        let result = diesel
+            ::delete(login_attempts.filter(kind.eq(attempt_kind)).filter(key.eq(attempt_key)))
+            .execute(&mut self.get_connection());
+        return result;
+    }
+}
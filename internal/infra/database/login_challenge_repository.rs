@@ -0,0 +1,107 @@
+use std::sync::{ Arc, RwLock };
+
+use chrono::{ NaiveDateTime, Utc };
+use diesel::{
+    prelude::{ Insertable, Queryable },
+    query_dsl::methods::FilterDsl,
+    r2d2::{ ConnectionManager, Pool, PooledConnection },
+    ExpressionMethods,
+    OptionalExtension,
+    PgConnection,
+    RunQueryDsl,
+    Selectable,
+};
+
+diesel::table! {
+    login_challenges (token_hash) {
+        token_hash -> Text,
+        user_id -> Text,
+        provider -> Text,
+        attempts -> Integer,
+        expires_at -> Timestamp,
+        created_at -> Timestamp,
+        login -> Text,
+    }
+}
+
+#[derive(Selectable, Queryable, Debug)]
+#[diesel(table_name = login_challenges)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct LoginChallenge {
+    pub token_hash: String,
+    pub user_id: String,
+    pub provider: String,
+    pub attempts: i32,
+    pub expires_at: NaiveDateTime,
+    pub created_at: NaiveDateTime,
+    /// Login the password was checked for, failed codes are throttled with it.
+    pub login: String,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = login_challenges)]
+pub struct NewLoginChallenge {
+    pub token_hash: String,
+    pub user_id: String,
+    pub provider: String,
+    pub expires_at: NaiveDateTime,
+    pub login: String,
+}
+
+#[derive(Clone)]
+pub struct LoginChallengeRepository {
+    pub pool: Arc<RwLock<Pool<ConnectionManager<PgConnection>>>>,
+}
+
+impl LoginChallengeRepository {
+    pub fn new(
+        pool: Arc<RwLock<Pool<ConnectionManager<PgConnection>>>>
+    ) -> Arc<LoginChallengeRepository> {
+        return Arc::new(LoginChallengeRepository { pool });
+    }
+
+    fn get_connection(&self) -> PooledConnection<ConnectionManager<PgConnection>> {
+        self.pool.write().unwrap().get().expect("Failed to get a connection")
+    }
+
+    pub fn save(
+        &self,
+        challenge: NewLoginChallenge
+    ) -> Result<LoginChallenge, diesel::result::Error> {
+        use self::login_challenges::dsl::*;
+        let result = diesel
+            ::insert_into(login_challenges)
+            .values(&challenge)
+            .get_result::<LoginChallenge>(&mut self.get_connection())?;
+        return Ok(result);
+    }
+
+    /// Counts an attempt on a not expired challenge which has attempts left. Returns
+    /// `None` when the challenge can not be used anymore.
+    pub fn register_attempt(
+        &self,
+        hash: &str,
+        max_attempts: i32
+    ) -> Result<Option<LoginChallenge>, diesel::result::Error> {
+        use self::login_challenges::dsl::*;
+        let result = diesel
+            ::update(
+                login_challenges
+                    .filter(token_hash.eq(hash))
+                    .filter(attempts.lt(max_attempts))
+                    .filter(expires_at.gt(Utc::now().naive_utc()))
+            )
+            .set(attempts.eq(attempts + 1))
+            .get_result::<LoginChallenge>(&mut self.get_connection())
+            .optional()?;
+        return Ok(result);
+    }
+
+    pub fn delete(&self, hash: &str) -> Result<usize, diesel::result::Error> {
+        use self::login_challenges::dsl::*;
+        let result = diesel
+            ::delete(login_challenges.filter(token_hash.eq(hash)))
+            .execute(&mut self.get_connection());
+        return result;
+    }
+}
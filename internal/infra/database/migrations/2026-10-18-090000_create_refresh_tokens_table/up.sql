@@ -0,0 +1,14 @@
+CREATE TABLE IF NOT EXISTS refresh_tokens
+(
+    token_hash   TEXT      NOT NULL,
+    user_id      TEXT      NOT NULL,
+    session_uuid UUID      NOT NULL,
+    used         BOOLEAN   NOT NULL DEFAULT FALSE,
+    expires_at   TIMESTAMP NOT NULL,
+    created_at   TIMESTAMP NOT NULL DEFAULT NOW(),
+    CONSTRAINT refresh_tokens_pkey PRIMARY KEY (token_hash),
+    CONSTRAINT refresh_tokens_session_fkey FOREIGN KEY (user_id, session_uuid)
+        REFERENCES sessions (user_id, uuid) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS refresh_tokens_session_idx ON refresh_tokens (user_id, session_uuid);
@@ -0,0 +1,14 @@
+CREATE TABLE IF NOT EXISTS users
+(
+    id         TEXT      NOT NULL,
+    name       TEXT      NOT NULL,
+    email      TEXT      NOT NULL,
+    password   TEXT      NOT NULL,
+    roles      TEXT[]    NOT NULL DEFAULT '{}',
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    CONSTRAINT users_pkey PRIMARY KEY (id),
+    CONSTRAINT users_email_key UNIQUE (email)
+);
+
+SELECT diesel_manage_updated_at('users');
@@ -0,0 +1,36 @@
+CREATE TABLE IF NOT EXISTS user_totp
+(
+    provider       TEXT      NOT NULL,
+    user_id        TEXT      NOT NULL,
+    secret         TEXT      NOT NULL,
+    enabled        BOOLEAN   NOT NULL DEFAULT FALSE,
+    last_used_step BIGINT,
+    created_at     TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at     TIMESTAMP NOT NULL DEFAULT NOW(),
+    CONSTRAINT user_totp_pkey PRIMARY KEY (provider, user_id)
+);
+
+SELECT diesel_manage_updated_at('user_totp');
+
+CREATE TABLE IF NOT EXISTS totp_recovery_codes
+(
+    code_hash  TEXT      NOT NULL,
+    provider   TEXT      NOT NULL,
+    user_id    TEXT      NOT NULL,
+    used_at    TIMESTAMP,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    CONSTRAINT totp_recovery_codes_pkey PRIMARY KEY (provider, user_id, code_hash),
+    CONSTRAINT totp_recovery_codes_user_fkey FOREIGN KEY (provider, user_id)
+        REFERENCES user_totp (provider, user_id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS login_challenges
+(
+    token_hash TEXT      NOT NULL,
+    user_id    TEXT      NOT NULL,
+    provider   TEXT      NOT NULL,
+    attempts   INTEGER   NOT NULL DEFAULT 0,
+    expires_at TIMESTAMP NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    CONSTRAINT login_challenges_pkey PRIMARY KEY (token_hash)
+);
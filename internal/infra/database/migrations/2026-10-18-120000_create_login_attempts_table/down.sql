@@ -0,0 +1,2 @@
+ALTER TABLE login_challenges DROP COLUMN IF EXISTS login;
+DROP TABLE IF EXISTS login_attempts;
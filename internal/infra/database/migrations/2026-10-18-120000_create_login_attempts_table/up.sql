@@ -0,0 +1,14 @@
+CREATE TABLE IF NOT EXISTS login_attempts
+(
+    kind              TEXT      NOT NULL,
+    key               TEXT      NOT NULL,
+    failures          INTEGER   NOT NULL DEFAULT 0,
+    window_started_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    locked_until      TIMESTAMP,
+    CONSTRAINT login_attempts_pkey PRIMARY KEY (kind, key)
+);
+
+-- Failed second factors count against the login the challenge was issued for. Pending
+-- challenges have no login yet, their users simply log in again.
+DELETE FROM login_challenges;
+ALTER TABLE login_challenges ADD COLUMN IF NOT EXISTS login TEXT NOT NULL;
@@ -0,0 +1,8 @@
+DROP INDEX IF EXISTS sessions_expires_at_idx;
+
+ALTER TABLE sessions
+    DROP COLUMN IF EXISTS created_at,
+    DROP COLUMN IF EXISTS expires_at,
+    DROP COLUMN IF EXISTS last_seen_at,
+    DROP COLUMN IF EXISTS ip,
+    DROP COLUMN IF EXISTS user_agent;
@@ -0,0 +1,8 @@
+ALTER TABLE sessions
+    ADD COLUMN IF NOT EXISTS created_at   TIMESTAMP NOT NULL DEFAULT NOW(),
+    ADD COLUMN IF NOT EXISTS expires_at   TIMESTAMP NOT NULL DEFAULT NOW() + INTERVAL '30 days',
+    ADD COLUMN IF NOT EXISTS last_seen_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    ADD COLUMN IF NOT EXISTS ip           TEXT,
+    ADD COLUMN IF NOT EXISTS user_agent   TEXT;
+
+CREATE INDEX IF NOT EXISTS sessions_expires_at_idx ON sessions (expires_at);
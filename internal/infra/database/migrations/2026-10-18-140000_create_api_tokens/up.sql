@@ -0,0 +1,16 @@
+CREATE TABLE IF NOT EXISTS api_tokens
+(
+    id           UUID      NOT NULL,
+    user_id      TEXT      NOT NULL,
+    provider     TEXT      NOT NULL,
+    name         TEXT      NOT NULL,
+    token_hash   TEXT      NOT NULL,
+    scopes       TEXT[]    NOT NULL DEFAULT '{}',
+    expires_at   TIMESTAMP NOT NULL,
+    last_used_at TIMESTAMP,
+    created_at   TIMESTAMP NOT NULL DEFAULT NOW(),
+    CONSTRAINT api_tokens_pkey PRIMARY KEY (id),
+    CONSTRAINT api_tokens_token_hash_key UNIQUE (token_hash)
+);
+
+CREATE INDEX IF NOT EXISTS api_tokens_user_id_idx ON api_tokens (user_id);
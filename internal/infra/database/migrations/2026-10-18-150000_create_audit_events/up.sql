@@ -0,0 +1,15 @@
+CREATE TABLE IF NOT EXISTS audit_events
+(
+    id             UUID      NOT NULL,
+    actor_id       TEXT      NOT NULL,
+    action         TEXT      NOT NULL,
+    target_user_id TEXT,
+    session_uuid   UUID,
+    ip             TEXT,
+    details        TEXT,
+    created_at     TIMESTAMP NOT NULL DEFAULT NOW(),
+    CONSTRAINT audit_events_pkey PRIMARY KEY (id)
+);
+
+CREATE INDEX IF NOT EXISTS audit_events_actor_id_idx ON audit_events (actor_id);
+CREATE INDEX IF NOT EXISTS audit_events_target_user_id_idx ON audit_events (target_user_id);
@@ -0,0 +1,12 @@
+CREATE TABLE IF NOT EXISTS password_reset_tokens
+(
+    token_hash TEXT      NOT NULL,
+    user_id    TEXT      NOT NULL,
+    provider   TEXT      NOT NULL,
+    used       BOOLEAN   NOT NULL DEFAULT FALSE,
+    expires_at TIMESTAMP NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    CONSTRAINT password_reset_tokens_pkey PRIMARY KEY (token_hash)
+);
+
+CREATE INDEX IF NOT EXISTS password_reset_tokens_user_id_idx ON password_reset_tokens (user_id);
@@ -0,0 +1,10 @@
+CREATE TABLE IF NOT EXISTS pending_registrations
+(
+    user_id    TEXT      NOT NULL,
+    token_hash TEXT      NOT NULL,
+    expires_at TIMESTAMP NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    CONSTRAINT pending_registrations_pkey PRIMARY KEY (user_id)
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS pending_registrations_token_hash_idx ON pending_registrations (token_hash);
@@ -1,3 +1,12 @@
+pub mod api_token_repository;
+pub mod audit_event_repository;
+pub mod local_user_repository;
+pub mod login_attempt_repository;
+pub mod login_challenge_repository;
 pub mod migration;
+pub mod password_reset_token_repository;
+pub mod pending_registration_repository;
+pub mod refresh_token_repository;
 pub mod session_repository;
+pub mod totp_repository;
 pub mod user_repository;
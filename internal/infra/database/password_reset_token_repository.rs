@@ -0,0 +1,104 @@
+use std::sync::{ Arc, RwLock };
+
+use chrono::NaiveDateTime;
+use diesel::{
+    prelude::{ Insertable, Queryable },
+    query_dsl::methods::FilterDsl,
+    r2d2::{ ConnectionManager, Pool, PooledConnection },
+    ExpressionMethods,
+    OptionalExtension,
+    PgConnection,
+    RunQueryDsl,
+    Selectable,
+};
+
+diesel::table! {
+    password_reset_tokens (token_hash) {
+        token_hash -> Text,
+        user_id -> Text,
+        provider -> Text,
+        used -> Bool,
+        expires_at -> Timestamp,
+        created_at -> Timestamp,
+    }
+}
+
+#[derive(Selectable, Queryable, Debug)]
+#[diesel(table_name = password_reset_tokens)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct PasswordResetToken {
+    pub token_hash: String,
+    pub user_id: String,
+    pub provider: String,
+    pub used: bool,
+    pub expires_at: NaiveDateTime,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = password_reset_tokens)]
+pub struct NewPasswordResetToken {
+    pub token_hash: String,
+    pub user_id: String,
+    pub provider: String,
+    pub expires_at: NaiveDateTime,
+}
+
+#[derive(Clone)]
+pub struct PasswordResetTokenRepository {
+    pub pool: Arc<RwLock<Pool<ConnectionManager<PgConnection>>>>,
+}
+
+impl PasswordResetTokenRepository {
+    pub fn new(
+        pool: Arc<RwLock<Pool<ConnectionManager<PgConnection>>>>
+    ) -> Arc<PasswordResetTokenRepository> {
+        return Arc::new(PasswordResetTokenRepository { pool });
+    }
+
+    fn get_connection(&self) -> PooledConnection<ConnectionManager<PgConnection>> {
+        self.pool.write().unwrap().get().expect("Failed to get a connection")
+    }
+
+    pub fn save(&self, token: NewPasswordResetToken) -> Result<usize, diesel::result::Error> {
+        use self::password_reset_tokens::dsl::*;
+        let result = diesel
+            ::insert_into(password_reset_tokens)
+            .values(&token)
+            .execute(&mut self.get_connection());
+        return result;
+    }
+
+    pub fn delete_by_user_id(&self, owner_id: &str) -> Result<usize, diesel::result::Error> {
+        use self::password_reset_tokens::dsl::*;
+        let result = diesel
+            ::delete(password_reset_tokens.filter(user_id.eq(owner_id)))
+            .execute(&mut self.get_connection());
+        return result;
+    }
+
+    /// Atomically marks a not yet used token as used. Returns `None` when the token
+    /// does not exist or was already consumed by a concurrent request.
+    pub fn consume(
+        &self,
+        hash: &str
+    ) -> Result<Option<PasswordResetToken>, diesel::result::Error> {
+        use self::password_reset_tokens::dsl::*;
+        let result = diesel
+            ::update(password_reset_tokens.filter(token_hash.eq(hash)).filter(used.eq(false)))
+            .set(used.eq(true))
+            .get_result::<PasswordResetToken>(&mut self.get_connection())
+            .optional()?;
+        return Ok(result);
+    }
+
+    /// Makes a consumed token usable again, e.g. when the directory rejected the password.
+    pub fn release(&self, hash: &str) -> Result<usize, diesel::result::Error> {
+        use self::password_reset_tokens::dsl::*;
+        let result = diesel
+            ::update(password_reset_tokens.filter(token_hash.eq(hash)))
+            .set(used.eq(false))
+            .execute(&mut self.get_connection());
+        return result;
+    }
+}
@@ -0,0 +1,119 @@
+use std::sync::{ Arc, RwLock };
+
+use chrono::NaiveDateTime;
+use diesel::{
+    prelude::{ Insertable, Queryable },
+    query_dsl::methods::FilterDsl,
+    r2d2::{ ConnectionManager, Pool, PooledConnection },
+    ExpressionMethods,
+    OptionalExtension,
+    PgConnection,
+    RunQueryDsl,
+    Selectable,
+};
+
+diesel::table! {
+    pending_registrations (user_id) {
+        user_id -> Text,
+        token_hash -> Text,
+        expires_at -> Timestamp,
+        created_at -> Timestamp,
+    }
+}
+
+#[derive(Selectable, Queryable, Debug)]
+#[diesel(table_name = pending_registrations)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct PendingRegistration {
+    pub user_id: String,
+    pub token_hash: String,
+    pub expires_at: NaiveDateTime,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = pending_registrations)]
+pub struct NewPendingRegistration {
+    pub user_id: String,
+    pub token_hash: String,
+    pub expires_at: NaiveDateTime,
+}
+
+#[derive(Clone)]
+pub struct PendingRegistrationRepository {
+    pub pool: Arc<RwLock<Pool<ConnectionManager<PgConnection>>>>,
+}
+
+impl PendingRegistrationRepository {
+    pub fn new(
+        pool: Arc<RwLock<Pool<ConnectionManager<PgConnection>>>>
+    ) -> Arc<PendingRegistrationRepository> {
+        return Arc::new(PendingRegistrationRepository { pool });
+    }
+
+    fn get_connection(&self) -> PooledConnection<ConnectionManager<PgConnection>> {
+        self.pool.write().unwrap().get().expect("Failed to get a connection")
+    }
+
+    pub fn save(
+        &self,
+        registration: NewPendingRegistration
+    ) -> Result<usize, diesel::result::Error> {
+        use self::pending_registrations::dsl::*;
+        let result = diesel
+            ::insert_into(pending_registrations)
+            .values(&registration)
+            .execute(&mut self.get_connection());
+        return result;
+    }
+
+    pub fn find_by_user_id(
+        &self,
+        owner_id: &str
+    ) -> Result<Option<PendingRegistration>, diesel::result::Error> {
+        use self::pending_registrations::dsl::*;
+        let result = pending_registrations
+            .filter(user_id.eq(owner_id))
+            .first::<PendingRegistration>(&mut self.get_connection())
+            .optional()?;
+        return Ok(result);
+    }
+
+    /// Deletes the registration of a not expired token and returns it, so a token can be
+    /// used once. Expired registrations are kept until the address is registered again.
+    pub fn consume(
+        &self,
+        hash: &str,
+        now: NaiveDateTime
+    ) -> Result<Option<PendingRegistration>, diesel::result::Error> {
+        use self::pending_registrations::dsl::*;
+        let result = diesel
+            ::delete(pending_registrations.filter(token_hash.eq(hash)).filter(expires_at.gt(now)))
+            .get_result::<PendingRegistration>(&mut self.get_connection())
+            .optional()?;
+        return Ok(result);
+    }
+
+    pub fn delete_by_user_id(&self, owner_id: &str) -> Result<usize, diesel::result::Error> {
+        use self::pending_registrations::dsl::*;
+        let result = diesel
+            ::delete(pending_registrations.filter(user_id.eq(owner_id)))
+            .execute(&mut self.get_connection());
+        return result;
+    }
+
+    /// Moves the registration to the new login of a renamed user, so the account still has
+    /// to be verified.
+    pub fn update_user_id(
+        &self,
+        owner_id: &str,
+        new_owner_id: &str
+    ) -> Result<usize, diesel::result::Error> {
+        use self::pending_registrations::dsl::*;
+        let result = diesel
+            ::update(pending_registrations.filter(user_id.eq(owner_id)))
+            .set(user_id.eq(new_owner_id))
+            .execute(&mut self.get_connection());
+        return result;
+    }
+}
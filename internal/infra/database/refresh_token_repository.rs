@@ -0,0 +1,93 @@
+use std::sync::{ Arc, RwLock };
+
+use chrono::NaiveDateTime;
+use diesel::{
+    prelude::{ Insertable, Queryable },
+    query_dsl::methods::FilterDsl,
+    r2d2::{ ConnectionManager, Pool, PooledConnection },
+    ExpressionMethods,
+    OptionalExtension,
+    PgConnection,
+    RunQueryDsl,
+    Selectable,
+};
+use uuid::Uuid;
+
+diesel::table! {
+    refresh_tokens (token_hash) {
+        token_hash -> Text,
+        user_id -> Text,
+        session_uuid -> Uuid,
+        used -> Bool,
+        expires_at -> Timestamp,
+        created_at -> Timestamp,
+    }
+}
+
+#[derive(Selectable, Queryable, Debug)]
+#[diesel(table_name = refresh_tokens)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct RefreshToken {
+    pub token_hash: String,
+    pub user_id: String,
+    pub session_uuid: Uuid,
+    pub used: bool,
+    pub expires_at: NaiveDateTime,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = refresh_tokens)]
+pub struct NewRefreshToken {
+    pub token_hash: String,
+    pub user_id: String,
+    pub session_uuid: Uuid,
+    pub expires_at: NaiveDateTime,
+}
+
+#[derive(Clone)]
+pub struct RefreshTokenRepository {
+    pub pool: Arc<RwLock<Pool<ConnectionManager<PgConnection>>>>,
+}
+
+impl RefreshTokenRepository {
+    pub fn new(
+        pool: Arc<RwLock<Pool<ConnectionManager<PgConnection>>>>
+    ) -> Arc<RefreshTokenRepository> {
+        return Arc::new(RefreshTokenRepository { pool });
+    }
+
+    fn get_connection(&self) -> PooledConnection<ConnectionManager<PgConnection>> {
+        self.pool.write().unwrap().get().expect("Failed to get a connection")
+    }
+
+    pub fn save(&self, token: NewRefreshToken) -> Result<RefreshToken, diesel::result::Error> {
+        use self::refresh_tokens::dsl::*;
+        let result = diesel
+            ::insert_into(refresh_tokens)
+            .values(&token)
+            .get_result::<RefreshToken>(&mut self.get_connection())?;
+        return Ok(result);
+    }
+
+    pub fn find_by_hash(&self, hash: &str) -> Result<Option<RefreshToken>, diesel::result::Error> {
+        use self::refresh_tokens::dsl::*;
+        let result = refresh_tokens
+            .filter(token_hash.eq(hash))
+            .first::<RefreshToken>(&mut self.get_connection())
+            .optional()?;
+        return Ok(result);
+    }
+
+    /// Atomically marks a not yet used token as used. Returns `None` when the token
+    /// does not exist or was already consumed by a concurrent request.
+    pub fn consume(&self, hash: &str) -> Result<Option<RefreshToken>, diesel::result::Error> {
+        use self::refresh_tokens::dsl::*;
+        let result = diesel
+            ::update(refresh_tokens.filter(token_hash.eq(hash)).filter(used.eq(false)))
+            .set(used.eq(true))
+            .get_result::<RefreshToken>(&mut self.get_connection())
+            .optional()?;
+        return Ok(result);
+    }
+}
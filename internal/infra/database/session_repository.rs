@@ -1,10 +1,12 @@
 use std::sync::{ Arc, RwLock };
 
+use chrono::NaiveDateTime;
 use diesel::{
     prelude::{ Insertable, Queryable },
     query_dsl::methods::FilterDsl,
     r2d2::{ ConnectionManager, Pool, PooledConnection },
     ExpressionMethods,
+    OptionalExtension,
     PgConnection,
     RunQueryDsl,
     Selectable,
@@ -17,15 +19,40 @@ diesel::table! {
     sessions (user_id, uuid) {
         user_id -> Text,
         uuid -> Uuid,
+        provider -> Text,
+        created_at -> Timestamp,
+        expires_at -> Timestamp,
+        last_seen_at -> Timestamp,
+        ip -> Nullable<Text>,
+        user_agent -> Nullable<Text>,
     }
 }
 
-#[derive(Selectable, Insertable, Queryable, Debug)]
+#[derive(Selectable, Queryable, Debug)]
 #[diesel(table_name = sessions)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
 pub struct Session {
     pub user_id: String,
     pub uuid: Uuid,
+    pub provider: String,
+    pub created_at: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+    pub last_seen_at: NaiveDateTime,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = sessions)]
+pub struct NewSession {
+    pub user_id: String,
+    pub uuid: Uuid,
+    pub provider: String,
+    pub created_at: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+    pub last_seen_at: NaiveDateTime,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
 }
 
 #[derive(Clone)]
@@ -42,16 +69,33 @@ impl SessionRepository {
         self.pool.write().unwrap().get().expect("Failed to get a connection")
     }
 
-    pub fn save(&self, session: SessionDTO) -> Result<Session, diesel::result::Error> {
+    pub fn save(&self, session: NewSession) -> Result<Session, diesel::result::Error> {
         use self::sessions::dsl::*;
-        let session_model = Session { user_id: session.user_id.to_string(), uuid: session.uuid };
         let result = diesel
             ::insert_into(sessions)
-            .values(&session_model)
+            .values(&session)
             .get_result::<Session>(&mut self.get_connection())?;
         return Ok(result);
     }
 
+    pub fn find(&self, session: SessionDTO) -> Result<Option<Session>, diesel::result::Error> {
+        use self::sessions::dsl::*;
+        let result = sessions
+            .filter(user_id.eq(&session.user_id.to_string()))
+            .filter(uuid.eq(&session.uuid))
+            .first::<Session>(&mut self.get_connection())
+            .optional()?;
+        return Ok(result);
+    }
+
+    pub fn find_by_user_id(&self, id: &str) -> Result<Vec<Session>, diesel::result::Error> {
+        use self::sessions::dsl::*;
+        let result = sessions
+            .filter(user_id.eq(id))
+            .load::<Session>(&mut self.get_connection())?;
+        return Ok(result);
+    }
+
     pub fn exists(&self, session: SessionDTO) -> Result<bool, diesel::result::Error> {
         use self::sessions::dsl::*;
         use diesel::dsl::exists;
@@ -86,4 +130,59 @@ impl SessionRepository {
             .execute(&mut self.get_connection());
         return result;
     }
+
+    /// Deletes every session of the user except the given one.
+    pub fn delete_others(&self, session: SessionDTO) -> Result<usize, diesel::result::Error> {
+        use self::sessions::dsl::*;
+        let result = diesel
+            ::delete(
+                sessions
+                    .filter(user_id.eq(&session.user_id.to_string()))
+                    .filter(uuid.ne(&session.uuid))
+            )
+            .execute(&mut self.get_connection());
+        return result;
+    }
+
+    pub fn touch(
+        &self,
+        session: SessionDTO,
+        seen_at: NaiveDateTime
+    ) -> Result<usize, diesel::result::Error> {
+        use self::sessions::dsl::*;
+        let result = diesel
+            ::update(
+                sessions
+                    .filter(user_id.eq(&session.user_id.to_string()))
+                    .filter(uuid.eq(&session.uuid))
+            )
+            .set(last_seen_at.eq(seen_at))
+            .execute(&mut self.get_connection());
+        return result;
+    }
+
+    pub fn extend(
+        &self,
+        session: SessionDTO,
+        new_expires_at: NaiveDateTime
+    ) -> Result<usize, diesel::result::Error> {
+        use self::sessions::dsl::*;
+        let result = diesel
+            ::update(
+                sessions
+                    .filter(user_id.eq(&session.user_id.to_string()))
+                    .filter(uuid.eq(&session.uuid))
+            )
+            .set(expires_at.eq(new_expires_at))
+            .execute(&mut self.get_connection());
+        return result;
+    }
+
+    pub fn delete_expired(&self, now: NaiveDateTime) -> Result<usize, diesel::result::Error> {
+        use self::sessions::dsl::*;
+        let result = diesel
+            ::delete(sessions.filter(expires_at.le(now)))
+            .execute(&mut self.get_connection());
+        return result;
+    }
 }
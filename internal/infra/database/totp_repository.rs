@@ -0,0 +1,188 @@
+use std::sync::{ Arc, RwLock };
+
+use chrono::{ NaiveDateTime, Utc };
+use diesel::{
+    prelude::{ Insertable, Queryable },
+    query_dsl::methods::FilterDsl,
+    r2d2::{ ConnectionManager, Pool, PooledConnection },
+    BoolExpressionMethods,
+    Connection,
+    ExpressionMethods,
+    OptionalExtension,
+    PgConnection,
+    RunQueryDsl,
+    Selectable,
+};
+
+diesel::table! {
+    user_totp (provider, user_id) {
+        provider -> Text,
+        user_id -> Text,
+        secret -> Text,
+        enabled -> Bool,
+        last_used_step -> Nullable<BigInt>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    totp_recovery_codes (provider, user_id, code_hash) {
+        code_hash -> Text,
+        provider -> Text,
+        user_id -> Text,
+        used_at -> Nullable<Timestamp>,
+        created_at -> Timestamp,
+    }
+}
+
+#[derive(Selectable, Queryable, Debug)]
+#[diesel(table_name = user_totp)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct UserTotp {
+    pub provider: String,
+    pub user_id: String,
+    pub secret: String,
+    pub enabled: bool,
+    pub last_used_step: Option<i64>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = user_totp)]
+pub struct NewUserTotp {
+    pub provider: String,
+    pub user_id: String,
+    pub secret: String,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = totp_recovery_codes)]
+pub struct NewRecoveryCode {
+    pub code_hash: String,
+    pub provider: String,
+    pub user_id: String,
+}
+
+#[derive(Clone)]
+pub struct TotpRepository {
+    pub pool: Arc<RwLock<Pool<ConnectionManager<PgConnection>>>>,
+}
+
+impl TotpRepository {
+    pub fn new(pool: Arc<RwLock<Pool<ConnectionManager<PgConnection>>>>) -> Arc<TotpRepository> {
+        return Arc::new(TotpRepository { pool });
+    }
+
+    fn get_connection(&self) -> PooledConnection<ConnectionManager<PgConnection>> {
+        self.pool.write().unwrap().get().expect("Failed to get a connection")
+    }
+
+    pub fn find(
+        &self,
+        provider_name: &str,
+        id: &str
+    ) -> Result<Option<UserTotp>, diesel::result::Error> {
+        use self::user_totp::dsl::*;
+        let result = user_totp
+            .filter(provider.eq(provider_name))
+            .filter(user_id.eq(id))
+            .first::<UserTotp>(&mut self.get_connection())
+            .optional()?;
+        return Ok(result);
+    }
+
+    /// Stores a not yet verified secret, replacing a previous pending enrollment.
+    pub fn save_pending(&self, totp: NewUserTotp) -> Result<UserTotp, diesel::result::Error> {
+        use self::user_totp::dsl::*;
+        let result = diesel
+            ::insert_into(user_totp)
+            .values(&totp)
+            .on_conflict((provider, user_id))
+            .do_update()
+            .set((secret.eq(&totp.secret), enabled.eq(false), last_used_step.eq(None::<i64>)))
+            .get_result::<UserTotp>(&mut self.get_connection())?;
+        return Ok(result);
+    }
+
+    /// Enables the secret and replaces the recovery codes in one transaction.
+    pub fn enable(
+        &self,
+        provider_name: &str,
+        id: &str,
+        step: i64,
+        codes: Vec<NewRecoveryCode>
+    ) -> Result<(), diesel::result::Error> {
+        return self.get_connection().transaction(|connection| {
+            diesel
+                ::update(
+                    user_totp::table
+                        .filter(user_totp::provider.eq(provider_name))
+                        .filter(user_totp::user_id.eq(id))
+                )
+                .set((user_totp::enabled.eq(true), user_totp::last_used_step.eq(step)))
+                .execute(connection)?;
+            diesel
+                ::delete(
+                    totp_recovery_codes::table
+                        .filter(totp_recovery_codes::provider.eq(provider_name))
+                        .filter(totp_recovery_codes::user_id.eq(id))
+                )
+                .execute(connection)?;
+            diesel::insert_into(totp_recovery_codes::table).values(&codes).execute(connection)?;
+            return Ok(());
+        });
+    }
+
+    /// Records the time step of an accepted code. Returns `false` when the same or a
+    /// later step was already used, so every code works only once.
+    pub fn use_step(
+        &self,
+        provider_name: &str,
+        id: &str,
+        step: i64
+    ) -> Result<bool, diesel::result::Error> {
+        use self::user_totp::dsl::*;
+        let updated = diesel
+            ::update(
+                user_totp
+                    .filter(provider.eq(provider_name))
+                    .filter(user_id.eq(id))
+                    .filter(last_used_step.is_null().or(last_used_step.lt(step)))
+            )
+            .set(last_used_step.eq(step))
+            .execute(&mut self.get_connection())?;
+        return Ok(updated > 0);
+    }
+
+    /// Marks an unused recovery code as used. Returns `false` when there is no such code.
+    pub fn use_recovery_code(
+        &self,
+        provider_name: &str,
+        id: &str,
+        hash: &str
+    ) -> Result<bool, diesel::result::Error> {
+        use self::totp_recovery_codes::dsl::*;
+        let updated = diesel
+            ::update(
+                totp_recovery_codes
+                    .filter(provider.eq(provider_name))
+                    .filter(user_id.eq(id))
+                    .filter(code_hash.eq(hash))
+                    .filter(used_at.is_null())
+            )
+            .set(used_at.eq(Utc::now().naive_utc()))
+            .execute(&mut self.get_connection())?;
+        return Ok(updated > 0);
+    }
+
+    /// Removes the secret together with its recovery codes.
+    pub fn delete(&self, provider_name: &str, id: &str) -> Result<usize, diesel::result::Error> {
+        use self::user_totp::dsl::*;
+        let result = diesel
+            ::delete(user_totp.filter(provider.eq(provider_name)).filter(user_id.eq(id)))
+            .execute(&mut self.get_connection());
+        return result;
+    }
+}
@@ -1,72 +1,302 @@
 use std::sync::Arc;
 
-use config::CONFIGURATION;
-use ldap3::{ Ldap, LdapError, SearchEntry };
-use tokio::sync::RwLock;
-use core::error;
+use config::{ log::warn, CONFIGURATION };
+use ldap3::{ dn_escape, ldap_escape, LdapError, SearchEntry };
+use thiserror::Error;
+
+use crate::{
+    infra::ldap::{
+        dn::{ first_rdn_value, split_first_rdn },
+        entry::NewEntry,
+        filter::{ group_filter, normalize_identifier, user_filter },
+        ldap_client::{ LdapClient, LdapClientError },
+    },
+    services::providers::ldap_provider::LDAP_PROVIDER,
+};
+
+/// Result code returned by the directory when the DN is taken.
+const ENTRY_ALREADY_EXISTS: u32 = 68;
+
 pub struct User {
-    pub cn: Arc<str>,
+    pub provider: Arc<str>,
+    pub dn: Arc<str>,
+    pub id: Arc<str>,
     pub uid: Arc<str>,
-    pub sn: Arc<str>,
+    pub name: Arc<str>,
+    pub email: Arc<str>,
+    pub display_name: Option<Arc<str>>,
+    pub given_name: Option<Arc<str>>,
+    pub title: Option<Arc<str>>,
+    pub telephone_numbers: Vec<Arc<str>>,
+    pub department: Option<Arc<str>>,
+}
+
+#[derive(Error, Debug)]
+pub enum UserRepositoryError {
+    #[error("{0}")] LdapError(#[from] LdapClientError),
+    #[error("There is no one user was found")] NotFound,
+    #[error("Multiply users was found")] Ambiguous,
+    #[error("Entry [{0}] already exists")] AlreadyExists(String),
+    #[error("Entry [{0}] has no parent entry")] InvalidDn(String),
+    #[error("Entry [{dn}] has no [{attribute}] attribute")] MissingAttribute {
+        dn: String,
+        attribute: String,
+    },
+}
+
+impl User {
+    /// Maps a directory entry with the configured attribute mapping.
+    pub fn from_entry(entry: &SearchEntry) -> Result<User, UserRepositoryError> {
+        return Ok(User {
+            provider: Arc::from(LDAP_PROVIDER),
+            dn: Arc::from(entry.dn.as_str()),
+            id: Arc::from(
+                normalize_identifier(
+                    required_value(entry, &CONFIGURATION.ldap_login_attribute)?.as_ref()
+                ).as_str()
+            ),
+            uid: required_value(entry, &CONFIGURATION.ldap_attr_uid)?,
+            name: required_value(entry, &CONFIGURATION.ldap_attr_name)?,
+            email: required_value(entry, &CONFIGURATION.ldap_attr_email)?,
+            display_name: first_value(entry, &CONFIGURATION.ldap_attr_display_name),
+            given_name: first_value(entry, &CONFIGURATION.ldap_attr_given_name),
+            title: first_value(entry, &CONFIGURATION.ldap_attr_title),
+            telephone_numbers: all_values(entry, &CONFIGURATION.ldap_attr_telephone_number),
+            department: first_value(entry, &CONFIGURATION.ldap_attr_department),
+        });
+    }
 }
 
 pub struct UserRepository {
-    pub ldap: Arc<RwLock<Ldap>>,
+    pub ldap: Arc<LdapClient>,
 }
 
 impl UserRepository {
-    pub fn new(ldap: Arc<RwLock<Ldap>>) -> Arc<UserRepository> {
+    pub fn new(ldap: Arc<LdapClient>) -> Arc<UserRepository> {
         return Arc::new(UserRepository { ldap });
     }
 
-    pub async fn find_all(&self) -> Result<Vec<User>, LdapError> {
-        let result = self.ldap
-            .write().await
-            .search(
-                &CONFIGURATION.ldap_auth_base_dn,
-                ldap3::Scope::Subtree,
-                "(objectClass=inetOrgPerson)",
-                vec!["dn", "cn", "sn", "uid"]
-            ).await?;
+    pub async fn find_all(&self) -> Result<Vec<User>, UserRepositoryError> {
+        let entries = self.ldap.search(
+            &CONFIGURATION.ldap_auth_base_dn,
+            ldap3::Scope::Subtree,
+            &CONFIGURATION.ldap_users_filter,
+            user_attributes()
+        ).await?;
 
-        let entries = result.success()?.0;
         let mut parsed_entries = Vec::new();
         for entry in entries {
-            let en = SearchEntry::construct(entry).attrs;
-            parsed_entries.push(User {
-                cn: Arc::from(en.get("cn").unwrap().get(0).unwrap().as_str()),
-                uid: Arc::from(en.get("uid").unwrap().get(0).unwrap().as_str()),
-                sn: Arc::from(en.get("sn").unwrap().get(0).unwrap().as_str()),
-            });
+            // A single incomplete entry must not hide every other user.
+            match User::from_entry(&entry) {
+                Ok(user) => parsed_entries.push(user),
+                Err(e) => warn!("Skipping directory entry: {}", e),
+            }
         }
         return Ok(parsed_entries);
     }
 
-    pub async fn find_by_id(
+    /// Looks the user up by the configured login attribute. The identifier is
+    /// matched case-insensitively.
+    pub async fn find_by_id(&self, user_id: Arc<str>) -> Result<User, UserRepositoryError> {
+        let entries = self.ldap.search(
+            &CONFIGURATION.ldap_auth_base_dn,
+            ldap3::Scope::Subtree,
+            &user_filter(&user_id),
+            user_attributes()
+        ).await?;
+        return single_user(&entries);
+    }
+
+    /// Same as `find_by_id`, but reads from the primary which received the last write.
+    pub async fn find_by_id_on_primary(
         &self,
         user_id: Arc<str>
-    ) -> Result<User, Box<dyn error::Error + Send + Sync + 'static>> {
-        let result = self.ldap
-            .write().await
-            .search(
-                &format!("cn={},{}", user_id, CONFIGURATION.ldap_auth_base_dn),
+    ) -> Result<User, UserRepositoryError> {
+        let entries = self.ldap.search_primary(
+            &CONFIGURATION.ldap_auth_base_dn,
+            ldap3::Scope::Subtree,
+            &user_filter(&user_id),
+            user_attributes()
+        ).await?;
+        return single_user(&entries);
+    }
+
+    /// Reads the entry from the primary, it is only used right after a write.
+    async fn find_by_dn_on_primary(&self, dn: &str) -> Result<User, UserRepositoryError> {
+        let entries = self.ldap.search_primary(
+            dn,
+            ldap3::Scope::Base,
+            "(objectClass=*)",
+            user_attributes()
+        ).await?;
+        return match entries.first() {
+            Some(entry) => User::from_entry(entry),
+            None => Err(UserRepositoryError::NotFound),
+        };
+    }
+
+    /// Checks whether an entry already uses the login identifier or the email address.
+    pub async fn is_taken(&self, user_id: &str, email: &str) -> Result<bool, UserRepositoryError> {
+        let filter = format!(
+            "(|({}={})({}={}))",
+            CONFIGURATION.ldap_login_attribute,
+            ldap_escape(normalize_identifier(user_id)),
+            CONFIGURATION.ldap_attr_email,
+            ldap_escape(email.trim())
+        );
+        let entries = self.ldap.search(
+            &CONFIGURATION.ldap_auth_base_dn,
+            ldap3::Scope::Subtree,
+            &filter,
+            vec!["1.1"]
+        ).await?;
+        return Ok(!entries.is_empty());
+    }
+
+    pub async fn create(&self, entry: &NewEntry) -> Result<(), UserRepositoryError> {
+        return match self.ldap.add(entry).await {
+            Ok(_) => Ok(()),
+            Err(LdapClientError::LdapError(LdapError::LdapResult { result })) if
+                result.rc == ENTRY_ALREADY_EXISTS
+            => Err(UserRepositoryError::AlreadyExists(entry.dn.clone())),
+            Err(e) => Err(e.into()),
+        };
+    }
+
+    /// Replaces the given attributes. A new value of the RDN attribute renames the entry
+    /// with a modrdn first, the updated user is read back from its new DN.
+    pub async fn update(
+        &self,
+        user: &User,
+        changes: &[(&str, &str)]
+    ) -> Result<User, UserRepositoryError> {
+        let (rdn_attribute, parent_dn) = split_first_rdn(&user.dn).ok_or_else(|| {
+            return UserRepositoryError::InvalidDn(user.dn.to_string());
+        })?;
+        let mut dn = user.dn.to_string();
+        let new_rdn_value = changes
+            .iter()
+            .find(|(attribute, _)| attribute.eq_ignore_ascii_case(rdn_attribute))
+            .map(|(_, value)| *value)
+            .filter(|value| first_rdn_value(&user.dn).as_deref() != Some(*value));
+        if let Some(value) = new_rdn_value {
+            let rdn = format!("{}={}", rdn_attribute, dn_escape(value));
+            let new_dn = format!("{},{}", rdn, parent_dn);
+            match self.ldap.rename(&dn, &rdn).await {
+                Ok(_) => {}
+                Err(LdapClientError::LdapError(LdapError::LdapResult { result })) if
+                    result.rc == ENTRY_ALREADY_EXISTS
+                => {
+                    return Err(UserRepositoryError::AlreadyExists(new_dn));
+                }
+                Err(e) => {
+                    return Err(e.into());
+                }
+            }
+            dn = new_dn;
+        }
+        if !changes.is_empty() {
+            self.ldap.replace(&dn, changes).await?;
+        }
+        return self.find_by_dn_on_primary(&dn).await;
+    }
+
+    pub async fn delete(&self, dn: &str) -> Result<(), UserRepositoryError> {
+        return Ok(self.ldap.delete(dn).await?);
+    }
+
+    /// Resolves the user's roles from group membership. Role names are the
+    /// lowercased group names.
+    pub async fn find_roles(&self, user: &User) -> Result<Vec<Arc<str>>, UserRepositoryError> {
+        let mut roles: Vec<String> = Vec::new();
+        if CONFIGURATION.ldap_group_membership == "memberOf" {
+            let entries = self.ldap.search(
+                &user.dn,
+                ldap3::Scope::Base,
+                "(objectClass=*)",
+                vec!["memberOf"]
+            ).await?;
+            for entry in entries.iter() {
+                roles.extend(
+                    all_values(entry, "memberOf")
+                        .iter()
+                        .filter_map(|group_dn| first_rdn_value(group_dn))
+                );
+            }
+        } else {
+            let base_dn = match CONFIGURATION.ldap_group_base_dn.is_empty() {
+                true => &CONFIGURATION.ldap_auth_base_dn,
+                false => &CONFIGURATION.ldap_group_base_dn,
+            };
+            let entries = self.ldap.search(
+                base_dn,
                 ldap3::Scope::Subtree,
-                "(objectClass=inetOrgPerson)",
-                vec!["dn", "cn", "sn", "uid"]
+                &group_filter(&user.dn),
+                vec![CONFIGURATION.ldap_group_name_attribute.as_str()]
             ).await?;
-        let (entries, _) = result.success()?;
-        if entries.is_empty() {
-            return Err(Box::from("There is no one user was found"));
-        }
-        if entries.len() > 1 {
-            return Err(Box::from("Multiply users was found"));
+            for entry in entries.iter() {
+                if let Some(name) = first_value(entry, &CONFIGURATION.ldap_group_name_attribute) {
+                    roles.push(name.to_string());
+                }
+            }
         }
-        let user_dn = SearchEntry::construct(entries[0].clone());
-        let user = User {
-            cn: Arc::from(user_dn.attrs.get("cn").unwrap().get(0).unwrap().as_str()),
-            uid: Arc::from(user_dn.attrs.get("uid").unwrap().get(0).unwrap().as_str()),
-            sn: Arc::from(user_dn.attrs.get("sn").unwrap().get(0).unwrap().as_str()),
-        };
-        return Ok(user);
+        let mut roles: Vec<Arc<str>> = roles
+            .into_iter()
+            .map(|role| Arc::from(role.to_lowercase().as_str()))
+            .collect();
+        roles.sort_unstable();
+        roles.dedup();
+        return Ok(roles);
+    }
+}
+
+fn single_user(entries: &[SearchEntry]) -> Result<User, UserRepositoryError> {
+    if entries.is_empty() {
+        return Err(UserRepositoryError::NotFound);
+    }
+    if entries.len() > 1 {
+        return Err(UserRepositoryError::Ambiguous);
     }
+    return User::from_entry(&entries[0]);
+}
+
+fn user_attributes() -> Vec<&'static str> {
+    let mut attributes: Vec<&'static str> = vec![
+        &CONFIGURATION.ldap_login_attribute,
+        &CONFIGURATION.ldap_attr_uid,
+        &CONFIGURATION.ldap_attr_name,
+        &CONFIGURATION.ldap_attr_email,
+        &CONFIGURATION.ldap_attr_display_name,
+        &CONFIGURATION.ldap_attr_given_name,
+        &CONFIGURATION.ldap_attr_title,
+        &CONFIGURATION.ldap_attr_telephone_number,
+        &CONFIGURATION.ldap_attr_department
+    ];
+    attributes.retain(|attribute| !attribute.is_empty());
+    attributes.sort_unstable();
+    attributes.dedup();
+    return attributes;
+}
+
+/// Attribute names are case-insensitive, servers may return them in any casing.
+fn all_values(entry: &SearchEntry, attribute: &str) -> Vec<Arc<str>> {
+    if attribute.is_empty() {
+        return Vec::new();
+    }
+    return entry.attrs
+        .iter()
+        .filter(|(name, _)| name.eq_ignore_ascii_case(attribute))
+        .flat_map(|(_, values)| values.iter().map(|value| Arc::from(value.as_str())))
+        .collect();
+}
+
+fn first_value(entry: &SearchEntry, attribute: &str) -> Option<Arc<str>> {
+    return all_values(entry, attribute).into_iter().next();
+}
+
+fn required_value(entry: &SearchEntry, attribute: &str) -> Result<Arc<str>, UserRepositoryError> {
+    return first_value(entry, attribute).ok_or_else(|| UserRepositoryError::MissingAttribute {
+        dn: entry.dn.clone(),
+        attribute: attribute.to_owned(),
+    });
 }
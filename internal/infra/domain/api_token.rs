@@ -0,0 +1,16 @@
+use std::sync::Arc;
+
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use uuid::Uuid;
+
+/// Newly created API token, the only response which contains the token itself.
+#[derive(Clone, Serialize)]
+pub struct CreatedApiTokenDTO {
+    pub id: Uuid,
+    pub name: Arc<str>,
+    pub token: Arc<str>,
+    pub scopes: Vec<Arc<str>>,
+    pub expires_at: NaiveDateTime,
+    pub created_at: NaiveDateTime,
+}
@@ -0,0 +1,29 @@
+use std::sync::Arc;
+
+use uuid::Uuid;
+
+use crate::services::auth_service::Claims;
+
+/// Action taken by a user. The actor is the real user, also while impersonating.
+#[derive(Clone)]
+pub struct AuditEventDTO {
+    pub actor_id: Arc<str>,
+    pub action: &'static str,
+    pub target_user_id: Option<Arc<str>>,
+    pub session_uuid: Option<Uuid>,
+    pub ip: Option<Arc<str>>,
+    pub details: Option<String>,
+}
+
+impl AuditEventDTO {
+    pub fn new(claims: &Claims, action: &'static str) -> AuditEventDTO {
+        return AuditEventDTO {
+            actor_id: claims.actor_id(),
+            action,
+            target_user_id: None,
+            session_uuid: Some(claims.uuid),
+            ip: None,
+            details: None,
+        };
+    }
+}
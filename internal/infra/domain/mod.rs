@@ -1,2 +1,7 @@
 pub mod user;
 pub mod session;
+pub mod totp;
+pub mod oauth;
+pub mod api_token;
+pub mod audit;
+pub mod notification;
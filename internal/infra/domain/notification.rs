@@ -0,0 +1,12 @@
+use std::sync::Arc;
+
+use serde::Serialize;
+
+/// Message for a user, delivered by the configured notifier.
+#[derive(Clone, Serialize)]
+pub struct NotificationDTO {
+    pub user_id: Arc<str>,
+    pub recipient: Arc<str>,
+    pub subject: String,
+    pub body: String,
+}
@@ -0,0 +1,17 @@
+use std::sync::Arc;
+
+use serde::Serialize;
+
+/// RFC 7662 introspection response, inactive tokens only carry `active`.
+#[derive(Clone, Serialize, Default)]
+pub struct IntrospectionDTO {
+    pub active: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sub: Option<Arc<str>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exp: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub roles: Option<Vec<Arc<str>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_type: Option<&'static str>,
+}
@@ -14,3 +14,16 @@ impl SessionDTO {
         return SessionDTO { user_id, uuid };
     }
 }
+
+#[derive(Clone, Serialize)]
+pub struct TokenPairDTO {
+    pub token: Arc<str>,
+    pub refresh_token: Arc<str>,
+}
+
+/// Client details stored with a new session.
+#[derive(Clone, Default)]
+pub struct SessionMetadataDTO {
+    pub ip: Option<Arc<str>>,
+    pub user_agent: Option<Arc<str>>,
+}
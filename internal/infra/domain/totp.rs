@@ -0,0 +1,22 @@
+use std::sync::Arc;
+
+use serde::Serialize;
+
+#[derive(Clone, Serialize)]
+pub struct TotpEnrollmentDTO {
+    pub secret: Arc<str>,
+    /// otpauth:// URI, the payload of the QR code shown to the user.
+    pub provisioning_uri: Arc<str>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct RecoveryCodesDTO {
+    pub recovery_codes: Vec<Arc<str>>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct TotpChallengeDTO {
+    pub totp_required: bool,
+    pub challenge_token: Arc<str>,
+    pub expires_in: u64,
+}
@@ -1,31 +1,66 @@
 use std::sync::Arc;
 
+use chrono::NaiveDateTime;
 use serde::Serialize;
 
 use crate::infra::{
     database::user_repository::User,
+    domain::totp::TotpChallengeDTO,
     http::{ middlewares::Userable, resources::user_resource::UserResponse },
 };
 
 #[derive(Clone, PartialEq, Serialize)]
 pub struct UserDTO {
+    pub provider: Arc<str>,
+    pub dn: Arc<str>,
+    pub id: Arc<str>,
     pub uid: Arc<str>,
     pub name: Arc<str>,
     pub email: Arc<str>,
+    pub display_name: Option<Arc<str>>,
+    pub given_name: Option<Arc<str>>,
+    pub title: Option<Arc<str>>,
+    pub telephone_numbers: Vec<Arc<str>>,
+    pub department: Option<Arc<str>>,
 }
 
 #[derive(Clone, Serialize)]
 pub struct AuthenticatedUserDTO {
     pub user: UserResponse,
     pub token: Arc<str>,
+    pub refresh_token: Arc<str>,
+}
+
+/// Token of an administrator acting as the user, it can not be refreshed.
+#[derive(Clone, Serialize)]
+pub struct ImpersonationDTO {
+    pub user: UserResponse,
+    pub token: Arc<str>,
+    pub expires_at: NaiveDateTime,
+}
+
+/// Result of the first login step, users with two-factor authentication get a challenge.
+#[derive(Clone, Serialize)]
+#[serde(untagged)]
+pub enum LoginOutcomeDTO {
+    Authenticated(AuthenticatedUserDTO),
+    TotpRequired(TotpChallengeDTO),
 }
 
 impl UserDTO {
     pub(crate) fn model_to_dto(user: User) -> UserDTO {
         return UserDTO {
+            provider: user.provider,
+            dn: user.dn,
+            id: user.id,
             uid: user.uid,
-            name: user.sn,
-            email: user.cn,
+            name: user.name,
+            email: user.email,
+            display_name: user.display_name,
+            given_name: user.given_name,
+            title: user.title,
+            telephone_numbers: user.telephone_numbers,
+            department: user.department,
         };
     }
 
@@ -39,15 +74,23 @@ impl UserDTO {
 
     pub fn dto_to_model(&self) -> User {
         return User {
+            provider: self.provider.clone(),
+            dn: self.dn.clone(),
+            id: self.id.clone(),
             uid: self.uid.clone(),
-            sn: self.name.clone(),
-            cn: self.email.clone(),
+            name: self.name.clone(),
+            email: self.email.clone(),
+            display_name: self.display_name.clone(),
+            given_name: self.given_name.clone(),
+            title: self.title.clone(),
+            telephone_numbers: self.telephone_numbers.clone(),
+            department: self.department.clone(),
         };
     }
 }
 
 impl Userable for UserDTO {
     fn get_user_id(&self) -> Arc<str> {
-        return self.email.clone();
+        return self.id.clone();
     }
 }
@@ -0,0 +1,124 @@
+use std::sync::Arc;
+
+use actix_web::{ web, HttpMessage, HttpRequest, HttpResponse, Responder };
+use uuid::Uuid;
+
+use crate::{
+    infra::http::{
+        requests::{ user_request::ApiTokenRequest, JsonValidator },
+        resources::{ api_token_resource::ApiTokenResponse, ErrorResponse },
+    },
+    services::{
+        api_token_service::{ ApiTokenService, ApiTokenServiceError },
+        auth_service::Claims,
+    },
+};
+
+#[derive(Clone)]
+pub struct ApiTokenController {
+    api_token_service: Arc<ApiTokenService>,
+}
+
+impl ApiTokenController {
+    pub fn new(api_token_service: Arc<ApiTokenService>) -> ApiTokenController {
+        return ApiTokenController { api_token_service };
+    }
+
+    async fn create_api_token(
+        &self,
+        request: HttpRequest,
+        token_request: JsonValidator<ApiTokenRequest>
+    ) -> impl Responder {
+        let claims = match current_claims(&request) {
+            Some(claims) => claims,
+            None => {
+                return HttpResponse::Unauthorized().finish();
+            }
+        };
+        match self.api_token_service.create(&claims, token_request.into_inner()) {
+            Ok(token) => {
+                return HttpResponse::Created().json(token);
+            }
+            Err(e @ ApiTokenServiceError::NotAllowed) => {
+                return HttpResponse::Forbidden().json(
+                    ErrorResponse::new_error(Some(e.to_string()))
+                );
+            }
+            Err(e) => {
+                return HttpResponse::BadRequest().json(
+                    ErrorResponse::new_error(Some(e.to_string()))
+                );
+            }
+        }
+    }
+
+    async fn find_my_api_tokens(&self, request: HttpRequest) -> impl Responder {
+        let claims = match current_claims(&request) {
+            Some(claims) => claims,
+            None => {
+                return HttpResponse::Unauthorized().finish();
+            }
+        };
+        match self.api_token_service.find_all(&claims.user_id) {
+            Ok(tokens) => {
+                return HttpResponse::Ok().json(ApiTokenResponse::tokens_to_response(tokens));
+            }
+            Err(e) => {
+                return HttpResponse::BadRequest().json(
+                    ErrorResponse::new_error(Some(e.to_string()))
+                );
+            }
+        }
+    }
+
+    async fn revoke_api_token(&self, request: HttpRequest, id: web::Path<Uuid>) -> impl Responder {
+        let claims = match current_claims(&request) {
+            Some(claims) => claims,
+            None => {
+                return HttpResponse::Unauthorized().finish();
+            }
+        };
+        match self.api_token_service.revoke(&claims.user_id, id.into_inner()) {
+            Ok(true) => {
+                return HttpResponse::Ok().finish();
+            }
+            Ok(false) => {
+                return HttpResponse::NotFound().json(
+                    ErrorResponse::new_error(Some(String::from("Token was not found")))
+                );
+            }
+            Err(e) => {
+                return HttpResponse::BadRequest().json(
+                    ErrorResponse::new_error(Some(e.to_string()))
+                );
+            }
+        }
+    }
+}
+
+fn current_claims(request: &HttpRequest) -> Option<Arc<Claims>> {
+    return request.extensions().get::<Arc<Claims>>().cloned();
+}
+
+pub async fn create_api_token(
+    api_token_controller: web::Data<ApiTokenController>,
+    request: HttpRequest,
+    token_request: JsonValidator<ApiTokenRequest>
+) -> impl Responder {
+    return api_token_controller.create_api_token(request, token_request).await;
+}
+
+pub async fn find_my_api_tokens(
+    api_token_controller: web::Data<ApiTokenController>,
+    request: HttpRequest
+) -> impl Responder {
+    return api_token_controller.find_my_api_tokens(request).await;
+}
+
+pub async fn revoke_api_token(
+    api_token_controller: web::Data<ApiTokenController>,
+    request: HttpRequest,
+    id: web::Path<Uuid>
+) -> impl Responder {
+    return api_token_controller.revoke_api_token(request, id).await;
+}
@@ -1,13 +1,35 @@
-use std::sync::Arc;
+use std::{ collections::HashMap, sync::Arc };
 
-use actix_web::{ web, HttpMessage, HttpRequest, HttpResponse, Responder };
+use actix_web::{ http::header, web, HttpMessage, HttpRequest, HttpResponse, Responder };
 
 use crate::{
     infra::{
-        domain::session::SessionDTO,
-        http::{ requests::user_request::AuthRequest, resources::ErrorResponse },
+        domain::{ session::SessionDTO, user::LoginOutcomeDTO },
+        http::{
+            cookies::{
+                add_session_cookies,
+                find_cookie,
+                remove_session_cookies,
+                REFRESH_TOKEN_COOKIE,
+            },
+            requests::{
+                user_request::{
+                    AuthRequest,
+                    PasswordChangeRequest,
+                    RefreshRequest,
+                    TotpLoginRequest,
+                    UserRequest,
+                },
+                session_metadata,
+                JsonValidator,
+            },
+            resources::{ password_rejected_response, unavailable_response, ErrorResponse },
+        },
+    },
+    services::{
+        auth_service::{ AuthService, AuthServiceError, Claims },
+        providers::AuthenticationProviderError,
     },
-    services::auth_service::{ AuthService, Claims },
 };
 
 #[derive(Clone)]
@@ -20,12 +42,189 @@ impl AuthController {
         return AuthController { auth_service };
     }
 
-    async fn login(&self, user_credentials: web::Json<AuthRequest>) -> impl Responder {
-        match self.auth_service.login(user_credentials.into_inner()).await {
+    async fn login(
+        &self,
+        request: HttpRequest,
+        user_credentials: web::Json<AuthRequest>
+    ) -> impl Responder {
+        let metadata = session_metadata(&request);
+        match self.auth_service.login(user_credentials.into_inner(), metadata).await {
+            Ok(outcome) => {
+                let mut response = HttpResponse::Ok();
+                if let LoginOutcomeDTO::Authenticated(user) = &outcome {
+                    add_session_cookies(&mut response, &user.token, &user.refresh_token);
+                }
+                return response.json(outcome);
+            }
+            Err(e @ AuthServiceError::TooManyAttempts(retry_after)) => {
+                return HttpResponse::TooManyRequests()
+                    .insert_header((header::RETRY_AFTER, retry_after.as_secs().max(1)))
+                    .json(ErrorResponse::new_error(Some(e.to_string())));
+            }
+            Err(e @ AuthServiceError::ProviderError(AuthenticationProviderError::NotVerified)) => {
+                return HttpResponse::Forbidden().json(
+                    ErrorResponse::new_error(Some(e.to_string()))
+                );
+            }
+            Err(e) => {
+                if let Some(response) = unavailable_response(&e) {
+                    return response;
+                }
+                return HttpResponse::BadRequest().json(
+                    ErrorResponse::new_error(Some(e.to_string()))
+                );
+            }
+        }
+    }
+
+    async fn login_totp(
+        &self,
+        request: HttpRequest,
+        totp_request: JsonValidator<TotpLoginRequest>
+    ) -> impl Responder {
+        let metadata = session_metadata(&request);
+        match self.auth_service.login_totp(totp_request.into_inner(), metadata).await {
+            Ok(user) => {
+                let mut response = HttpResponse::Ok();
+                add_session_cookies(&mut response, &user.token, &user.refresh_token);
+                return response.json(user);
+            }
+            Err(e @ AuthServiceError::TooManyAttempts(retry_after)) => {
+                return HttpResponse::TooManyRequests()
+                    .insert_header((header::RETRY_AFTER, retry_after.as_secs().max(1)))
+                    .json(ErrorResponse::new_error(Some(e.to_string())));
+            }
+            Err(e @ (AuthServiceError::InvalidChallenge | AuthServiceError::InvalidTotpCode)) => {
+                return HttpResponse::Unauthorized().json(
+                    ErrorResponse::new_error(Some(e.to_string()))
+                );
+            }
+            Err(e) => {
+                if let Some(response) = unavailable_response(&e) {
+                    return response;
+                }
+                return HttpResponse::BadRequest().json(
+                    ErrorResponse::new_error(Some(e.to_string()))
+                );
+            }
+        }
+    }
+
+    /// Browser sessions may send an empty body, the refresh token then comes from its cookie.
+    async fn refresh(
+        &self,
+        request: HttpRequest,
+        refresh_request: Option<web::Json<RefreshRequest>>
+    ) -> impl Responder {
+        let refresh_token = match refresh_request {
+            Some(refresh_request) => Some(refresh_request.into_inner().refresh_token),
+            None => find_cookie(&request, REFRESH_TOKEN_COOKIE),
+        };
+        let refresh_token = match refresh_token {
+            Some(refresh_token) => refresh_token,
+            None => {
+                return HttpResponse::Unauthorized().json(
+                    ErrorResponse::new_error(
+                        Some(AuthServiceError::InvalidRefreshToken.to_string())
+                    )
+                );
+            }
+        };
+        match self.auth_service.refresh(&refresh_token).await {
+            Ok(tokens) => {
+                let mut response = HttpResponse::Ok();
+                add_session_cookies(&mut response, &tokens.token, &tokens.refresh_token);
+                return response.json(tokens);
+            }
+            Err(
+                e @ (AuthServiceError::InvalidRefreshToken | AuthServiceError::RefreshTokenReused),
+            ) => {
+                return HttpResponse::Unauthorized().json(
+                    ErrorResponse::new_error(Some(e.to_string()))
+                );
+            }
+            Err(e) => {
+                if let Some(response) = unavailable_response(&e) {
+                    return response;
+                }
+                return HttpResponse::BadRequest().json(
+                    ErrorResponse::new_error(Some(e.to_string()))
+                );
+            }
+        }
+    }
+
+    async fn register_local(
+        &self,
+        request: HttpRequest,
+        user: JsonValidator<UserRequest>
+    ) -> impl Responder {
+        let metadata = session_metadata(&request);
+        match self.auth_service.register_local(user.into_inner(), metadata).await {
             Ok(user) => {
-                return HttpResponse::Ok().json(user);
+                let mut response = HttpResponse::Created();
+                add_session_cookies(&mut response, &user.token, &user.refresh_token);
+                return response.json(user);
+            }
+            Err(e @ AuthServiceError::RegistrationDisabled) => {
+                return HttpResponse::Forbidden().json(
+                    ErrorResponse::new_error(Some(e.to_string()))
+                );
+            }
+            Err(e @ AuthServiceError::UserAlreadyExists) => {
+                return HttpResponse::Conflict().json(
+                    ErrorResponse::new_field_errors(
+                        Some(HashMap::from([(String::from("email"), vec![e.to_string()])]))
+                    )
+                );
             }
             Err(e) => {
+                if let Some(response) = unavailable_response(&e) {
+                    return response;
+                }
+                return HttpResponse::BadRequest().json(
+                    ErrorResponse::new_error(Some(e.to_string()))
+                );
+            }
+        }
+    }
+
+    async fn change_password(
+        &self,
+        request: HttpRequest,
+        password_request: JsonValidator<PasswordChangeRequest>
+    ) -> impl Responder {
+        let claims = match request.extensions().get::<Arc<Claims>>() {
+            Some(claims) => claims.clone(),
+            None => {
+                return HttpResponse::Unauthorized().finish();
+            }
+        };
+        match self.auth_service.change_password(&claims, password_request.into_inner()).await {
+            Ok(_) => {
+                return HttpResponse::Ok().finish();
+            }
+            Err(e @ AuthServiceError::InvalidCurrentPassword) => {
+                return HttpResponse::BadRequest().json(
+                    ErrorResponse::new_field_errors(
+                        Some(
+                            HashMap::from([(String::from("current_password"), vec![e.to_string()])])
+                        )
+                    )
+                );
+            }
+            Err(AuthServiceError::PasswordRejected(e)) => {
+                return password_rejected_response(&e);
+            }
+            Err(e @ AuthServiceError::PasswordChangeNotSupported) => {
+                return HttpResponse::Forbidden().json(
+                    ErrorResponse::new_error(Some(e.to_string()))
+                );
+            }
+            Err(e) => {
+                if let Some(response) = unavailable_response(&e) {
+                    return response;
+                }
                 return HttpResponse::BadRequest().json(
                     ErrorResponse::new_error(Some(e.to_string()))
                 );
@@ -41,7 +240,9 @@ impl AuthController {
             };
             match self.auth_service.logout(session) {
                 Ok(_) => {
-                    return HttpResponse::Ok().finish().map_into_boxed_body();
+                    let mut response = HttpResponse::Ok();
+                    remove_session_cookies(&mut response);
+                    return response.finish().map_into_boxed_body();
                 }
                 Err(e) => {
                     return HttpResponse::BadRequest().json(
@@ -64,7 +265,40 @@ pub async fn logout(
 
 pub async fn login(
     auth_controller: web::Data<AuthController>,
+    request: HttpRequest,
     user: web::Json<AuthRequest>
 ) -> impl Responder {
-    return auth_controller.login(user).await;
+    return auth_controller.login(request, user).await;
+}
+
+pub async fn login_totp(
+    auth_controller: web::Data<AuthController>,
+    request: HttpRequest,
+    totp_request: JsonValidator<TotpLoginRequest>
+) -> impl Responder {
+    return auth_controller.login_totp(request, totp_request).await;
+}
+
+pub async fn refresh(
+    auth_controller: web::Data<AuthController>,
+    request: HttpRequest,
+    refresh_request: Option<web::Json<RefreshRequest>>
+) -> impl Responder {
+    return auth_controller.refresh(request, refresh_request).await;
+}
+
+pub async fn register_local(
+    auth_controller: web::Data<AuthController>,
+    request: HttpRequest,
+    user: JsonValidator<UserRequest>
+) -> impl Responder {
+    return auth_controller.register_local(request, user).await;
+}
+
+pub async fn change_password(
+    auth_controller: web::Data<AuthController>,
+    request: HttpRequest,
+    password_request: JsonValidator<PasswordChangeRequest>
+) -> impl Responder {
+    return auth_controller.change_password(request, password_request).await;
 }
@@ -0,0 +1,34 @@
+use std::sync::Arc;
+
+use actix_web::{ web, HttpResponse, Responder };
+
+use crate::infra::{
+    http::resources::health_resource::{ HealthResponse, LdapHealthResponse },
+    ldap::ldap_client::LdapClient,
+};
+
+#[derive(Clone)]
+pub struct HealthController {
+    ldap_client: Arc<LdapClient>,
+}
+
+impl HealthController {
+    pub fn new(ldap_client: Arc<LdapClient>) -> HealthController {
+        return HealthController { ldap_client };
+    }
+
+    async fn health(&self) -> impl Responder {
+        let ldap = LdapHealthResponse::statuses_to_response(self.ldap_client.status());
+        if ldap.status == "down" {
+            return HttpResponse::ServiceUnavailable().json(HealthResponse {
+                status: "down",
+                ldap,
+            });
+        }
+        return HttpResponse::Ok().json(HealthResponse { status: ldap.status, ldap });
+    }
+}
+
+pub async fn health(health_controller: web::Data<HealthController>) -> impl Responder {
+    return health_controller.health().await;
+}
@@ -0,0 +1,107 @@
+use std::sync::Arc;
+
+use actix_web::{ web, HttpMessage, HttpRequest, HttpResponse, Responder };
+
+use crate::{
+    infra::http::{
+        requests::session_metadata,
+        resources::{ unavailable_response, ErrorResponse },
+    },
+    services::{
+        auth_service::Claims,
+        impersonation_service::{ ImpersonationService, ImpersonationServiceError },
+    },
+};
+
+#[derive(Clone)]
+pub struct ImpersonationController {
+    impersonation_service: Arc<ImpersonationService>,
+}
+
+impl ImpersonationController {
+    pub fn new(impersonation_service: Arc<ImpersonationService>) -> ImpersonationController {
+        return ImpersonationController { impersonation_service };
+    }
+
+    async fn impersonate(
+        &self,
+        request: HttpRequest,
+        user_id: web::Path<String>
+    ) -> impl Responder {
+        let claims = match current_claims(&request) {
+            Some(claims) => claims,
+            None => {
+                return HttpResponse::Unauthorized().finish();
+            }
+        };
+        let metadata = session_metadata(&request);
+        let user_id: Arc<str> = Arc::from(user_id.into_inner().as_str());
+        match self.impersonation_service.start(&claims, user_id, metadata).await {
+            Ok(impersonation) => {
+                return HttpResponse::Ok().json(impersonation);
+            }
+            Err(
+                e @ (
+                    ImpersonationServiceError::NotAllowed
+                    | ImpersonationServiceError::AdminTarget
+                    | ImpersonationServiceError::SelfTarget
+                ),
+            ) => {
+                return HttpResponse::Forbidden().json(
+                    ErrorResponse::new_error(Some(e.to_string()))
+                );
+            }
+            Err(e @ ImpersonationServiceError::UserNotFound(_)) => {
+                return HttpResponse::NotFound().json(
+                    ErrorResponse::new_error(Some(e.to_string()))
+                );
+            }
+            Err(e) => {
+                if let Some(response) = unavailable_response(&e) {
+                    return response;
+                }
+                return HttpResponse::BadRequest().json(
+                    ErrorResponse::new_error(Some(e.to_string()))
+                );
+            }
+        }
+    }
+
+    async fn end_impersonation(&self, request: HttpRequest) -> impl Responder {
+        let claims = match current_claims(&request) {
+            Some(claims) => claims,
+            None => {
+                return HttpResponse::Unauthorized().finish();
+            }
+        };
+        match self.impersonation_service.end(&claims, session_metadata(&request)) {
+            Ok(_) => {
+                return HttpResponse::Ok().finish();
+            }
+            Err(e) => {
+                return HttpResponse::BadRequest().json(
+                    ErrorResponse::new_error(Some(e.to_string()))
+                );
+            }
+        }
+    }
+}
+
+fn current_claims(request: &HttpRequest) -> Option<Arc<Claims>> {
+    return request.extensions().get::<Arc<Claims>>().cloned();
+}
+
+pub async fn impersonate(
+    impersonation_controller: web::Data<ImpersonationController>,
+    request: HttpRequest,
+    user_id: web::Path<String>
+) -> impl Responder {
+    return impersonation_controller.impersonate(request, user_id).await;
+}
+
+pub async fn end_impersonation(
+    impersonation_controller: web::Data<ImpersonationController>,
+    request: HttpRequest
+) -> impl Responder {
+    return impersonation_controller.end_impersonation(request).await;
+}
@@ -0,0 +1,29 @@
+use std::sync::Arc;
+
+use actix_web::{ http::header, web, HttpResponse, Responder };
+
+use crate::services::jwt_key_service::JwtKeyService;
+
+/// Verifiers may cache the key set for this many seconds.
+const JWKS_MAX_AGE: u64 = 300;
+
+#[derive(Clone)]
+pub struct JwksController {
+    jwt_key_service: Arc<JwtKeyService>,
+}
+
+impl JwksController {
+    pub fn new(jwt_key_service: Arc<JwtKeyService>) -> JwksController {
+        return JwksController { jwt_key_service };
+    }
+
+    async fn jwks(&self) -> impl Responder {
+        return HttpResponse::Ok()
+            .insert_header((header::CACHE_CONTROL, format!("public, max-age={}", JWKS_MAX_AGE)))
+            .json(self.jwt_key_service.jwks());
+    }
+}
+
+pub async fn jwks(jwks_controller: web::Data<JwksController>) -> impl Responder {
+    return jwks_controller.jwks().await;
+}
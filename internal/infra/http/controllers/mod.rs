@@ -1,2 +1,11 @@
 pub mod user_controller;
 pub mod auth_controller;
+pub mod health_controller;
+pub mod totp_controller;
+pub mod session_controller;
+pub mod jwks_controller;
+pub mod oauth_controller;
+pub mod api_token_controller;
+pub mod impersonation_controller;
+pub mod password_reset_controller;
+pub mod registration_controller;
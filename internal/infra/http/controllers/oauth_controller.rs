@@ -0,0 +1,125 @@
+use std::sync::Arc;
+
+use actix_web::{ http::header, web, HttpRequest, HttpResponse, Responder };
+use base64::{ engine::general_purpose::STANDARD, Engine };
+use serde_json::json;
+
+use crate::{
+    infra::http::requests::user_request::OAuthTokenRequest,
+    services::oauth_service::OAuthService,
+};
+
+#[derive(Clone)]
+pub struct OAuthController {
+    oauth_service: Arc<OAuthService>,
+}
+
+impl OAuthController {
+    pub fn new(oauth_service: Arc<OAuthService>) -> OAuthController {
+        return OAuthController { oauth_service };
+    }
+
+    async fn introspect(
+        &self,
+        request: HttpRequest,
+        token_request: web::Form<OAuthTokenRequest>
+    ) -> impl Responder {
+        if !self.is_client_authenticated(&request, &token_request) {
+            return invalid_client_response();
+        }
+        let token = match token_request.token.as_deref() {
+            Some(token) => token,
+            None => {
+                return invalid_request_response();
+            }
+        };
+        let token_type_hint = token_request.token_type_hint.as_deref();
+        match self.oauth_service.introspect(token, token_type_hint).await {
+            Ok(introspection) => {
+                return HttpResponse::Ok()
+                    .insert_header((header::CACHE_CONTROL, "no-store"))
+                    .json(introspection);
+            }
+            Err(e) => {
+                return HttpResponse::InternalServerError().json(
+                    json!({ "error": "server_error", "error_description": e.to_string() })
+                );
+            }
+        }
+    }
+
+    async fn revoke(
+        &self,
+        request: HttpRequest,
+        token_request: web::Form<OAuthTokenRequest>
+    ) -> impl Responder {
+        if !self.is_client_authenticated(&request, &token_request) {
+            return invalid_client_response();
+        }
+        let token = match token_request.token.as_deref() {
+            Some(token) => token,
+            None => {
+                return invalid_request_response();
+            }
+        };
+        match self.oauth_service.revoke(token, token_request.token_type_hint.as_deref()) {
+            Ok(_) => {
+                return HttpResponse::Ok().finish();
+            }
+            Err(e) => {
+                // RFC 7009 asks for 503 when the token could not be revoked.
+                return HttpResponse::ServiceUnavailable().json(
+                    json!({ "error": "server_error", "error_description": e.to_string() })
+                );
+            }
+        }
+    }
+
+    /// Credentials come from HTTP Basic authentication or from the form.
+    fn is_client_authenticated(&self, request: &HttpRequest, form: &OAuthTokenRequest) -> bool {
+        if let Some((client_id, client_secret)) = basic_credentials(request) {
+            return self.oauth_service.authenticate_client(&client_id, &client_secret);
+        }
+        return match (form.client_id.as_deref(), form.client_secret.as_deref()) {
+            (Some(client_id), Some(client_secret)) =>
+                self.oauth_service.authenticate_client(client_id, client_secret),
+            _ => false,
+        };
+    }
+}
+
+fn basic_credentials(request: &HttpRequest) -> Option<(String, String)> {
+    let authorization = request.headers().get(header::AUTHORIZATION)?.to_str().ok()?;
+    let encoded = authorization.strip_prefix("Basic ")?;
+    let decoded = String::from_utf8(STANDARD.decode(encoded.trim()).ok()?).ok()?;
+    let (client_id, client_secret) = decoded.split_once(':')?;
+    return Some((client_id.to_string(), client_secret.to_string()));
+}
+
+fn invalid_client_response() -> HttpResponse {
+    return HttpResponse::Unauthorized()
+        .insert_header((header::WWW_AUTHENTICATE, "Basic"))
+        .json(json!({ "error": "invalid_client" }));
+}
+
+fn invalid_request_response() -> HttpResponse {
+    return HttpResponse::BadRequest().json(
+        json!({ "error": "invalid_request", "error_description": "token is required" })
+    );
+}
+
+pub async fn introspect(
+    oauth_controller: web::Data<OAuthController>,
+    request: HttpRequest,
+    token_request: web::Form<OAuthTokenRequest>
+) -> impl Responder {
+    return oauth_controller.introspect(request, token_request).await;
+}
+
+pub async fn revoke(
+    oauth_controller: web::Data<OAuthController>,
+    request: HttpRequest,
+    token_request: web::Form<OAuthTokenRequest>
+) -> impl Responder {
+    return oauth_controller.revoke(request, token_request).await;
+}
@@ -0,0 +1,100 @@
+use std::{ collections::HashMap, sync::Arc };
+
+use actix_web::{ http::header, web, HttpRequest, HttpResponse, Responder };
+
+use crate::{
+    infra::http::{
+        requests::{
+            client_ip,
+            user_request::{ PasswordForgotRequest, PasswordResetRequest },
+            JsonValidator,
+        },
+        resources::{ password_rejected_response, unavailable_response, ErrorResponse },
+    },
+    services::password_reset_service::{ PasswordResetService, PasswordResetServiceError },
+};
+
+#[derive(Clone)]
+pub struct PasswordResetController {
+    password_reset_service: Arc<PasswordResetService>,
+}
+
+impl PasswordResetController {
+    pub fn new(password_reset_service: Arc<PasswordResetService>) -> PasswordResetController {
+        return PasswordResetController { password_reset_service };
+    }
+
+    async fn forgot_password(
+        &self,
+        request: HttpRequest,
+        forgot_request: JsonValidator<PasswordForgotRequest>
+    ) -> impl Responder {
+        let client_ip = client_ip(&request);
+        let forgot = self.password_reset_service.forgot(
+            forgot_request.into_inner(),
+            client_ip.as_deref()
+        ).await;
+        match forgot {
+            Ok(_) => {
+                return HttpResponse::Accepted().finish();
+            }
+            Err(e @ PasswordResetServiceError::TooManyRequests(retry_after)) => {
+                return HttpResponse::TooManyRequests()
+                    .insert_header((header::RETRY_AFTER, retry_after.as_secs().max(1)))
+                    .json(ErrorResponse::new_error(Some(e.to_string())));
+            }
+            Err(e) => {
+                if let Some(response) = unavailable_response(&e) {
+                    return response;
+                }
+                return HttpResponse::InternalServerError().json(
+                    ErrorResponse::new_error(Some(e.to_string()))
+                );
+            }
+        }
+    }
+
+    async fn reset_password(
+        &self,
+        reset_request: JsonValidator<PasswordResetRequest>
+    ) -> impl Responder {
+        match self.password_reset_service.reset(reset_request.into_inner()).await {
+            Ok(_) => {
+                return HttpResponse::Ok().finish();
+            }
+            Err(e @ PasswordResetServiceError::InvalidToken) => {
+                return HttpResponse::BadRequest().json(
+                    ErrorResponse::new_field_errors(
+                        Some(HashMap::from([(String::from("token"), vec![e.to_string()])]))
+                    )
+                );
+            }
+            Err(PasswordResetServiceError::PasswordRejected(e)) => {
+                return password_rejected_response(&e);
+            }
+            Err(e) => {
+                if let Some(response) = unavailable_response(&e) {
+                    return response;
+                }
+                return HttpResponse::BadRequest().json(
+                    ErrorResponse::new_error(Some(e.to_string()))
+                );
+            }
+        }
+    }
+}
+
+pub async fn forgot_password(
+    password_reset_controller: web::Data<PasswordResetController>,
+    request: HttpRequest,
+    forgot_request: JsonValidator<PasswordForgotRequest>
+) -> impl Responder {
+    return password_reset_controller.forgot_password(request, forgot_request).await;
+}
+
+pub async fn reset_password(
+    password_reset_controller: web::Data<PasswordResetController>,
+    reset_request: JsonValidator<PasswordResetRequest>
+) -> impl Responder {
+    return password_reset_controller.reset_password(reset_request).await;
+}
@@ -0,0 +1,102 @@
+use std::{ collections::HashMap, sync::Arc };
+
+use actix_web::{ web, HttpResponse, Responder };
+use config::CONFIGURATION;
+
+use crate::{
+    infra::http::{
+        requests::{
+            user_request::{ RegistrationVerifyRequest, UserRequest },
+            JsonValidator,
+        },
+        resources::{ unavailable_response, ErrorResponse },
+    },
+    services::registration_service::{ RegistrationService, RegistrationServiceError },
+};
+
+#[derive(Clone)]
+pub struct RegistrationController {
+    registration_service: Arc<RegistrationService>,
+}
+
+impl RegistrationController {
+    pub fn new(registration_service: Arc<RegistrationService>) -> RegistrationController {
+        return RegistrationController { registration_service };
+    }
+
+    /// Accounts which still have to verify their email address are answered with 202.
+    async fn register(&self, user: JsonValidator<UserRequest>) -> impl Responder {
+        match self.registration_service.register(user.into_inner()).await {
+            Ok(user) => {
+                if CONFIGURATION.ldap_registration_verify_email {
+                    return HttpResponse::Accepted().json(user);
+                }
+                return HttpResponse::Created().json(user);
+            }
+            Err(e @ RegistrationServiceError::RegistrationDisabled) => {
+                return HttpResponse::Forbidden().json(
+                    ErrorResponse::new_error(Some(e.to_string()))
+                );
+            }
+            Err(e @ RegistrationServiceError::UserAlreadyExists) => {
+                return HttpResponse::Conflict().json(
+                    ErrorResponse::new_field_errors(
+                        Some(HashMap::from([(String::from("email"), vec![e.to_string()])]))
+                    )
+                );
+            }
+            Err(RegistrationServiceError::PasswordRejected(e)) => {
+                return HttpResponse::BadRequest().json(
+                    ErrorResponse::new_field_errors(
+                        Some(HashMap::from([(String::from("password"), vec![e.to_string()])]))
+                    )
+                );
+            }
+            Err(e) => {
+                if let Some(response) = unavailable_response(&e) {
+                    return response;
+                }
+                return HttpResponse::BadRequest().json(
+                    ErrorResponse::new_error(Some(e.to_string()))
+                );
+            }
+        }
+    }
+
+    async fn verify_registration(
+        &self,
+        verify_request: JsonValidator<RegistrationVerifyRequest>
+    ) -> impl Responder {
+        match self.registration_service.verify(verify_request.into_inner()) {
+            Ok(_) => {
+                return HttpResponse::Ok().finish();
+            }
+            Err(e @ RegistrationServiceError::InvalidToken) => {
+                return HttpResponse::BadRequest().json(
+                    ErrorResponse::new_field_errors(
+                        Some(HashMap::from([(String::from("token"), vec![e.to_string()])]))
+                    )
+                );
+            }
+            Err(e) => {
+                return HttpResponse::InternalServerError().json(
+                    ErrorResponse::new_error(Some(e.to_string()))
+                );
+            }
+        }
+    }
+}
+
+pub async fn register(
+    registration_controller: web::Data<RegistrationController>,
+    user: JsonValidator<UserRequest>
+) -> impl Responder {
+    return registration_controller.register(user).await;
+}
+
+pub async fn verify_registration(
+    registration_controller: web::Data<RegistrationController>,
+    verify_request: JsonValidator<RegistrationVerifyRequest>
+) -> impl Responder {
+    return registration_controller.verify_registration(verify_request).await;
+}
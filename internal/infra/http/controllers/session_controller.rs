@@ -0,0 +1,167 @@
+use std::sync::Arc;
+
+use actix_web::{ web, HttpMessage, HttpRequest, HttpResponse, Responder };
+use uuid::Uuid;
+
+use crate::{
+    infra::http::{
+        requests::user_request::LogoutAllRequest,
+        resources::{
+            session_resource::{ RevokedSessionsResponse, SessionResponse },
+            ErrorResponse,
+        },
+    },
+    services::auth_service::{ AuthService, Claims },
+};
+
+#[derive(Clone)]
+pub struct SessionController {
+    auth_service: Arc<AuthService>,
+}
+
+impl SessionController {
+    pub fn new(auth_service: Arc<AuthService>) -> SessionController {
+        return SessionController { auth_service };
+    }
+
+    async fn find_my_sessions(&self, request: HttpRequest) -> impl Responder {
+        let claims = match current_claims(&request) {
+            Some(claims) => claims,
+            None => {
+                return HttpResponse::Unauthorized().finish();
+            }
+        };
+        match self.auth_service.find_sessions(&claims.user_id) {
+            Ok(sessions) => {
+                return HttpResponse::Ok().json(
+                    SessionResponse::sessions_to_response(sessions, Some(claims.uuid))
+                );
+            }
+            Err(e) => {
+                return HttpResponse::BadRequest().json(
+                    ErrorResponse::new_error(Some(e.to_string()))
+                );
+            }
+        }
+    }
+
+    async fn revoke_my_session(
+        &self,
+        request: HttpRequest,
+        uuid: web::Path<Uuid>
+    ) -> impl Responder {
+        let claims = match current_claims(&request) {
+            Some(claims) => claims,
+            None => {
+                return HttpResponse::Unauthorized().finish();
+            }
+        };
+        match self.auth_service.revoke_session(claims.user_id.clone(), uuid.into_inner()) {
+            Ok(true) => {
+                return HttpResponse::Ok().finish();
+            }
+            Ok(false) => {
+                return HttpResponse::NotFound().json(
+                    ErrorResponse::new_error(Some(String::from("Session was not found")))
+                );
+            }
+            Err(e) => {
+                return HttpResponse::BadRequest().json(
+                    ErrorResponse::new_error(Some(e.to_string()))
+                );
+            }
+        }
+    }
+
+    async fn logout_all(
+        &self,
+        request: HttpRequest,
+        logout_request: web::Query<LogoutAllRequest>
+    ) -> impl Responder {
+        let claims = match current_claims(&request) {
+            Some(claims) => claims,
+            None => {
+                return HttpResponse::Unauthorized().finish();
+            }
+        };
+        match self.auth_service.logout_all(&claims, logout_request.keep_current) {
+            Ok(revoked) => {
+                return HttpResponse::Ok().json(RevokedSessionsResponse { revoked });
+            }
+            Err(e) => {
+                return HttpResponse::BadRequest().json(
+                    ErrorResponse::new_error(Some(e.to_string()))
+                );
+            }
+        }
+    }
+
+    async fn find_user_sessions(&self, user_id: web::Path<String>) -> impl Responder {
+        match self.auth_service.find_sessions(&user_id) {
+            Ok(sessions) => {
+                return HttpResponse::Ok().json(
+                    SessionResponse::sessions_to_response(sessions, None)
+                );
+            }
+            Err(e) => {
+                return HttpResponse::BadRequest().json(
+                    ErrorResponse::new_error(Some(e.to_string()))
+                );
+            }
+        }
+    }
+
+    async fn revoke_user_sessions(&self, user_id: web::Path<String>) -> impl Responder {
+        match self.auth_service.revoke_user_sessions(&user_id) {
+            Ok(revoked) => {
+                return HttpResponse::Ok().json(RevokedSessionsResponse { revoked });
+            }
+            Err(e) => {
+                return HttpResponse::BadRequest().json(
+                    ErrorResponse::new_error(Some(e.to_string()))
+                );
+            }
+        }
+    }
+}
+
+fn current_claims(request: &HttpRequest) -> Option<Arc<Claims>> {
+    return request.extensions().get::<Arc<Claims>>().cloned();
+}
+
+pub async fn find_my_sessions(
+    session_controller: web::Data<SessionController>,
+    request: HttpRequest
+) -> impl Responder {
+    return session_controller.find_my_sessions(request).await;
+}
+
+pub async fn revoke_my_session(
+    session_controller: web::Data<SessionController>,
+    request: HttpRequest,
+    uuid: web::Path<Uuid>
+) -> impl Responder {
+    return session_controller.revoke_my_session(request, uuid).await;
+}
+
+pub async fn logout_all(
+    session_controller: web::Data<SessionController>,
+    request: HttpRequest,
+    logout_request: web::Query<LogoutAllRequest>
+) -> impl Responder {
+    return session_controller.logout_all(request, logout_request).await;
+}
+
+pub async fn find_user_sessions(
+    session_controller: web::Data<SessionController>,
+    user_id: web::Path<String>
+) -> impl Responder {
+    return session_controller.find_user_sessions(user_id).await;
+}
+
+pub async fn revoke_user_sessions(
+    session_controller: web::Data<SessionController>,
+    user_id: web::Path<String>
+) -> impl Responder {
+    return session_controller.revoke_user_sessions(user_id).await;
+}
@@ -0,0 +1,153 @@
+use std::sync::Arc;
+
+use actix_web::{ web, HttpMessage, HttpRequest, HttpResponse, Responder };
+
+use crate::{
+    infra::{
+        domain::user::UserDTO,
+        http::{
+            requests::{ user_request::TotpCodeRequest, JsonValidator },
+            resources::ErrorResponse,
+        },
+    },
+    services::{ auth_service::Claims, totp_service::{ TotpService, TotpServiceError } },
+};
+
+#[derive(Clone)]
+pub struct TotpController {
+    totp_service: Arc<TotpService>,
+}
+
+impl TotpController {
+    pub fn new(totp_service: Arc<TotpService>) -> TotpController {
+        return TotpController { totp_service };
+    }
+
+    async fn enroll(&self, request: HttpRequest) -> impl Responder {
+        let (claims, user) = match current_user(&request) {
+            Some(current_user) => current_user,
+            None => {
+                return HttpResponse::Unauthorized().finish();
+            }
+        };
+        match self.totp_service.enroll(&claims, &user.email) {
+            Ok(enrollment) => {
+                return HttpResponse::Ok().json(enrollment);
+            }
+            Err(e) => {
+                return error_response(e);
+            }
+        }
+    }
+
+    async fn enable(
+        &self,
+        request: HttpRequest,
+        code_request: JsonValidator<TotpCodeRequest>
+    ) -> impl Responder {
+        let (claims, _) = match current_user(&request) {
+            Some(current_user) => current_user,
+            None => {
+                return HttpResponse::Unauthorized().finish();
+            }
+        };
+        match self.totp_service.enable(&claims, &code_request.code) {
+            Ok(recovery_codes) => {
+                return HttpResponse::Ok().json(recovery_codes);
+            }
+            Err(e) => {
+                return error_response(e);
+            }
+        }
+    }
+
+    async fn disable(
+        &self,
+        request: HttpRequest,
+        code_request: JsonValidator<TotpCodeRequest>
+    ) -> impl Responder {
+        let (claims, _) = match current_user(&request) {
+            Some(current_user) => current_user,
+            None => {
+                return HttpResponse::Unauthorized().finish();
+            }
+        };
+        match self.totp_service.disable(&claims, &code_request.code) {
+            Ok(_) => {
+                return HttpResponse::Ok().finish();
+            }
+            Err(e) => {
+                return error_response(e);
+            }
+        }
+    }
+
+    async fn reset(&self, user_id: web::Path<String>) -> impl Responder {
+        match self.totp_service.reset(&user_id).await {
+            Ok(true) => {
+                return HttpResponse::Ok().finish();
+            }
+            Ok(false) => {
+                return HttpResponse::NotFound().json(
+                    ErrorResponse::new_error(Some(TotpServiceError::NotEnabled.to_string()))
+                );
+            }
+            Err(e) => {
+                return error_response(e);
+            }
+        }
+    }
+}
+
+fn current_user(request: &HttpRequest) -> Option<(Arc<Claims>, UserDTO)> {
+    let extensions = request.extensions();
+    let claims = extensions.get::<Arc<Claims>>()?;
+    let user = extensions.get::<UserDTO>()?;
+    return Some((claims.clone(), user.clone()));
+}
+
+fn error_response(error: TotpServiceError) -> HttpResponse {
+    let response = ErrorResponse::new_error(Some(error.to_string()));
+    return match error {
+        TotpServiceError::AlreadyEnabled => HttpResponse::Conflict().json(response),
+        TotpServiceError::NotAllowed => HttpResponse::Forbidden().json(response),
+        TotpServiceError::NotConfigured => HttpResponse::ServiceUnavailable().json(response),
+        TotpServiceError::ProviderError(e) if e.is_unavailable() =>
+            HttpResponse::ServiceUnavailable().json(response),
+        TotpServiceError::DieselError(_) |
+        TotpServiceError::ProviderError(_) |
+        TotpServiceError::EncryptionError =>
+            HttpResponse::InternalServerError().json(response),
+        _ => HttpResponse::BadRequest().json(response),
+    };
+}
+
+pub async fn enroll(
+    totp_controller: web::Data<TotpController>,
+    request: HttpRequest
+) -> impl Responder {
+    return totp_controller.enroll(request).await;
+}
+
+pub async fn enable(
+    totp_controller: web::Data<TotpController>,
+    request: HttpRequest,
+    code_request: JsonValidator<TotpCodeRequest>
+) -> impl Responder {
+    return totp_controller.enable(request, code_request).await;
+}
+
+pub async fn disable(
+    totp_controller: web::Data<TotpController>,
+    request: HttpRequest,
+    code_request: JsonValidator<TotpCodeRequest>
+) -> impl Responder {
+    return totp_controller.disable(request, code_request).await;
+}
+
+pub async fn reset(
+    totp_controller: web::Data<TotpController>,
+    user_id: web::Path<String>
+) -> impl Responder {
+    return totp_controller.reset(user_id).await;
+}
@@ -1,23 +1,45 @@
-use std::sync::Arc;
+use std::{ collections::HashMap, sync::Arc };
 
+use actix_multipart::Multipart;
 use actix_web::{ web, HttpMessage, HttpRequest, HttpResponse, Responder };
+use config::CONFIGURATION;
+use futures::StreamExt;
 
 use crate::{
     infra::{
         domain::user::UserDTO,
-        http::resources::{ user_resource::UserResponse, BasedListResponse, ErrorResponse },
+        http::{
+            requests::{ user_request::{ UserRequest, UserUpdateRequest }, JsonValidator },
+            resources::{
+                unavailable_response,
+                user_resource::{ PermissionsResponse, UserResponse },
+                BasedListResponse,
+                ErrorResponse,
+            },
+        },
+    },
+    services::{
+        auth_service::Claims,
+        registration_service::{ RegistrationService, RegistrationServiceError },
+        user_service::{ UserService, UserServiceError },
     },
-    services::user_service::UserService,
 };
 
+/// Multipart field which carries the avatar image.
+const AVATAR_FIELD: &str = "avatar";
+
 #[derive(Clone)]
 pub struct UserController {
     user_service: Arc<UserService>,
+    registration_service: Arc<RegistrationService>,
 }
 
 impl UserController {
-    pub fn new(user_service: Arc<UserService>) -> UserController {
-        return UserController { user_service };
+    pub fn new(
+        user_service: Arc<UserService>,
+        registration_service: Arc<RegistrationService>
+    ) -> UserController {
+        return UserController { user_service, registration_service };
     }
 
     async fn find_all(&self) -> impl Responder {
@@ -31,6 +53,9 @@ impl UserController {
                 return HttpResponse::Ok().json(response);
             }
             Err(e) => {
+                if let Some(response) = unavailable_response(e.as_ref()) {
+                    return response;
+                }
                 return HttpResponse::BadRequest().json(
                     ErrorResponse::new_error(Some(e.to_string()))
                 );
@@ -44,6 +69,244 @@ impl UserController {
         }
         return HttpResponse::BadRequest().json("Something went wrong");
     }
+
+    async fn permissions(&self, request: HttpRequest) -> impl Responder {
+        if let Some(claims) = request.extensions().get::<Arc<Claims>>() {
+            return HttpResponse::Ok().json(PermissionsResponse {
+                user_id: claims.user_id.clone(),
+                roles: claims.roles.clone(),
+                is_admin: claims.roles.iter().any(|role| **role == CONFIGURATION.admin_role),
+            });
+        }
+        return HttpResponse::Unauthorized().finish();
+    }
+
+    async fn upload_avatar(&self, request: HttpRequest, mut payload: Multipart) -> impl Responder {
+        let user = match request.extensions().get::<UserDTO>().cloned() {
+            Some(user) => user,
+            None => {
+                return HttpResponse::Unauthorized().finish();
+            }
+        };
+        let content = match read_avatar(&mut payload).await {
+            Ok(content) => content,
+            Err(response) => {
+                return response;
+            }
+        };
+        let user_service = Arc::clone(&self.user_service);
+        let (provider, user_id) = (user.provider.clone(), user.id.clone());
+        match web::block(move || user_service.save_avatar(&provider, &user_id, &content)).await {
+            Ok(Ok(_)) => {
+                return HttpResponse::Ok().json(UserResponse::dto_to_response(&user));
+            }
+            Ok(Err(e @ UserServiceError::UnsupportedImage)) => {
+                return HttpResponse::UnsupportedMediaType().json(
+                    ErrorResponse::new_field_errors(
+                        Some(HashMap::from([(String::from(AVATAR_FIELD), vec![e.to_string()])]))
+                    )
+                );
+            }
+            Ok(Err(e)) => {
+                return HttpResponse::InternalServerError().json(
+                    ErrorResponse::new_error(Some(e.to_string()))
+                );
+            }
+            Err(e) => {
+                return HttpResponse::InternalServerError().json(
+                    ErrorResponse::new_error(Some(e.to_string()))
+                );
+            }
+        }
+    }
+
+    async fn delete_avatar(&self, request: HttpRequest) -> impl Responder {
+        let (provider, user_id) = match request.extensions().get::<UserDTO>() {
+            Some(user) => (user.provider.clone(), user.id.clone()),
+            None => {
+                return HttpResponse::Unauthorized().finish();
+            }
+        };
+        let user_service = Arc::clone(&self.user_service);
+        match web::block(move || user_service.delete_avatar(&provider, &user_id)).await {
+            Ok(Ok(true)) => {
+                return HttpResponse::NoContent().finish();
+            }
+            Ok(Ok(false)) => {
+                return HttpResponse::NotFound().json(
+                    ErrorResponse::new_error(Some(String::from("User has no avatar")))
+                );
+            }
+            Ok(Err(e)) => {
+                return HttpResponse::InternalServerError().json(
+                    ErrorResponse::new_error(Some(e.to_string()))
+                );
+            }
+            Err(e) => {
+                return HttpResponse::InternalServerError().json(
+                    ErrorResponse::new_error(Some(e.to_string()))
+                );
+            }
+        }
+    }
+
+    async fn create_user(&self, user: JsonValidator<UserRequest>) -> impl Responder {
+        match self.registration_service.create(user.into_inner()).await {
+            Ok(user) => {
+                return HttpResponse::Created().json(user);
+            }
+            Err(e @ RegistrationServiceError::UserAlreadyExists) => {
+                return HttpResponse::Conflict().json(
+                    ErrorResponse::new_field_errors(
+                        Some(HashMap::from([(String::from("email"), vec![e.to_string()])]))
+                    )
+                );
+            }
+            Err(RegistrationServiceError::PasswordRejected(e)) => {
+                return HttpResponse::BadRequest().json(
+                    ErrorResponse::new_field_errors(
+                        Some(HashMap::from([(String::from("password"), vec![e.to_string()])]))
+                    )
+                );
+            }
+            Err(e) => {
+                if let Some(response) = unavailable_response(&e) {
+                    return response;
+                }
+                return HttpResponse::BadRequest().json(
+                    ErrorResponse::new_error(Some(e.to_string()))
+                );
+            }
+        }
+    }
+
+    /// PUT replaces both fields, PATCH only the given ones.
+    async fn update_user(
+        &self,
+        user_id: web::Path<String>,
+        user: JsonValidator<UserUpdateRequest>,
+        is_partial: bool
+    ) -> impl Responder {
+        let user = user.into_inner();
+        if !is_partial {
+            let mut field_errors: HashMap<String, Vec<String>> = HashMap::new();
+            if user.name.is_none() {
+                field_errors.insert(String::from("name"), vec![String::from("Name is required")]);
+            }
+            if user.email.is_none() {
+                field_errors.insert(
+                    String::from("email"),
+                    vec![String::from("Email is required")]
+                );
+            }
+            if !field_errors.is_empty() {
+                return HttpResponse::BadRequest().json(
+                    ErrorResponse::new_field_errors(Some(field_errors))
+                );
+            }
+        }
+        match self.user_service.update(&user_id, user).await {
+            Ok(user) => {
+                return HttpResponse::Ok().json(UserResponse::dto_to_response(&user));
+            }
+            Err(e) => {
+                return user_error_response(e);
+            }
+        }
+    }
+
+    async fn delete_user(
+        &self,
+        request: HttpRequest,
+        user_id: web::Path<String>
+    ) -> impl Responder {
+        let claims = match request.extensions().get::<Arc<Claims>>().cloned() {
+            Some(claims) => claims,
+            None => {
+                return HttpResponse::Unauthorized().finish();
+            }
+        };
+        match self.user_service.delete(&claims.user_id, &user_id).await {
+            Ok(_) => {
+                return HttpResponse::NoContent().finish();
+            }
+            Err(e) => {
+                return user_error_response(e);
+            }
+        }
+    }
+}
+
+/// Reads the avatar field of the form, other fields are skipped. An upload over
+/// AVATAR_MAX_SIZE is refused before it was read completely.
+async fn read_avatar(payload: &mut Multipart) -> Result<Vec<u8>, HttpResponse> {
+    while let Some(field) = payload.next().await {
+        let mut field = field.map_err(|e| {
+            return HttpResponse::BadRequest().json(ErrorResponse::new_error(Some(e.to_string())));
+        })?;
+        if field.name() != Some(AVATAR_FIELD) {
+            continue;
+        }
+        let mut content: Vec<u8> = Vec::new();
+        while let Some(chunk) = field.next().await {
+            let chunk = chunk.map_err(|e| {
+                return HttpResponse::BadRequest().json(
+                    ErrorResponse::new_error(Some(e.to_string()))
+                );
+            })?;
+            if content.len() + chunk.len() > CONFIGURATION.avatar_max_size {
+                return Err(
+                    HttpResponse::PayloadTooLarge().json(
+                        ErrorResponse::new_error(
+                            Some(
+                                format!(
+                                    "Avatar must not be larger than {} bytes",
+                                    CONFIGURATION.avatar_max_size
+                                )
+                            )
+                        )
+                    )
+                );
+            }
+            content.extend_from_slice(&chunk);
+        }
+        return Ok(content);
+    }
+    return Err(
+        HttpResponse::BadRequest().json(
+            ErrorResponse::new_field_errors(
+                Some(
+                    HashMap::from([
+                        (String::from(AVATAR_FIELD), vec![String::from("Avatar file is required")]),
+                    ])
+                )
+            )
+        )
+    );
+}
+
+fn user_error_response(error: UserServiceError) -> HttpResponse {
+    return match error {
+        UserServiceError::NotFound => {
+            HttpResponse::NotFound().json(ErrorResponse::new_error(Some(error.to_string())))
+        }
+        UserServiceError::UserAlreadyExists => {
+            HttpResponse::Conflict().json(
+                ErrorResponse::new_field_errors(
+                    Some(HashMap::from([(String::from("email"), vec![error.to_string()])]))
+                )
+            )
+        }
+        UserServiceError::DeleteSelf => {
+            HttpResponse::Forbidden().json(ErrorResponse::new_error(Some(error.to_string())))
+        }
+        e => {
+            if let Some(response) = unavailable_response(&e) {
+                return response;
+            }
+            HttpResponse::BadRequest().json(ErrorResponse::new_error(Some(e.to_string())))
+        }
+    };
 }
 
 // HANDLERS USER ROUTE
@@ -54,6 +317,59 @@ pub async fn find_me(
     return user_controller.find_me(request).await;
 }
 
+pub async fn permissions(
+    user_controller: web::Data<UserController>,
+    request: HttpRequest
+) -> impl Responder {
+    return user_controller.permissions(request).await;
+}
+
 pub async fn find_all(user_controller: web::Data<UserController>) -> impl Responder {
     return user_controller.find_all().await;
 }
+
+pub async fn create_user(
+    user_controller: web::Data<UserController>,
+    user: JsonValidator<UserRequest>
+) -> impl Responder {
+    return user_controller.create_user(user).await;
+}
+
+pub async fn update_user(
+    user_controller: web::Data<UserController>,
+    user_id: web::Path<String>,
+    user: JsonValidator<UserUpdateRequest>
+) -> impl Responder {
+    return user_controller.update_user(user_id, user, false).await;
+}
+
+pub async fn patch_user(
+    user_controller: web::Data<UserController>,
+    user_id: web::Path<String>,
+    user: JsonValidator<UserUpdateRequest>
+) -> impl Responder {
+    return user_controller.update_user(user_id, user, true).await;
+}
+
+pub async fn delete_user(
+    user_controller: web::Data<UserController>,
+    request: HttpRequest,
+    user_id: web::Path<String>
+) -> impl Responder {
+    return user_controller.delete_user(request, user_id).await;
+}
+
+pub async fn upload_avatar(
+    user_controller: web::Data<UserController>,
+    request: HttpRequest,
+    payload: Multipart
+) -> impl Responder {
+    return user_controller.upload_avatar(request, payload).await;
+}
+
+pub async fn delete_avatar(
+    user_controller: web::Data<UserController>,
+    request: HttpRequest
+) -> impl Responder {
+    return user_controller.delete_avatar(request).await;
+}
@@ -0,0 +1,96 @@
+use actix_web::{
+    cookie::{ time::Duration, Cookie, SameSite },
+    HttpRequest,
+    HttpResponseBuilder,
+};
+use config::CONFIGURATION;
+
+use crate::services::auth_service::generate_random_token;
+
+pub const ACCESS_TOKEN_COOKIE: &str = "access_token";
+pub const REFRESH_TOKEN_COOKIE: &str = "refresh_token";
+pub const CSRF_TOKEN_COOKIE: &str = "csrf_token";
+pub const CSRF_TOKEN_HEADER: &str = "X-CSRF-Token";
+const CSRF_TOKEN_LENGTH: usize = 32;
+/// The refresh token is only sent to the refresh and logout endpoints.
+const REFRESH_TOKEN_COOKIE_PATH: &str = "/api/v1/auth";
+
+/// Sets the tokens and a new CSRF token as cookies when cookie sessions are enabled.
+pub fn add_session_cookies(response: &mut HttpResponseBuilder, token: &str, refresh_token: &str) {
+    if !CONFIGURATION.auth_cookie_enabled {
+        return;
+    }
+    let refresh_token_ttl = Duration::seconds(CONFIGURATION.refresh_token_ttl as i64);
+    response
+        .cookie(
+            session_cookie(ACCESS_TOKEN_COOKIE, token, "/", true)
+                .max_age(Duration::seconds(CONFIGURATION.jwt_ttl as i64))
+                .finish()
+        )
+        .cookie(
+            session_cookie(REFRESH_TOKEN_COOKIE, refresh_token, REFRESH_TOKEN_COOKIE_PATH, true)
+                .max_age(refresh_token_ttl)
+                .finish()
+        )
+        .cookie(
+            // Readable by the application, which repeats it in the CSRF header.
+            session_cookie(
+                CSRF_TOKEN_COOKIE,
+                &generate_random_token(CSRF_TOKEN_LENGTH),
+                "/",
+                false
+            )
+                .max_age(refresh_token_ttl)
+                .finish()
+        );
+}
+
+pub fn remove_session_cookies(response: &mut HttpResponseBuilder) {
+    if !CONFIGURATION.auth_cookie_enabled {
+        return;
+    }
+    for (name, path) in [
+        (ACCESS_TOKEN_COOKIE, "/"),
+        (REFRESH_TOKEN_COOKIE, REFRESH_TOKEN_COOKIE_PATH),
+        (CSRF_TOKEN_COOKIE, "/"),
+    ] {
+        let mut cookie = session_cookie(name, "", path, true).finish();
+        cookie.make_removal();
+        response.cookie(cookie);
+    }
+}
+
+/// Value of the cookie, always None when cookie sessions are disabled.
+pub fn find_cookie(request: &HttpRequest, name: &str) -> Option<String> {
+    if !CONFIGURATION.auth_cookie_enabled {
+        return None;
+    }
+    return request
+        .cookie(name)
+        .map(|cookie| cookie.value().to_string())
+        .filter(|value| !value.is_empty());
+}
+
+/// Whether the request carries one of the cookies which authenticate the user.
+pub fn has_session_cookie(request: &HttpRequest) -> bool {
+    return find_cookie(request, ACCESS_TOKEN_COOKIE).is_some() ||
+        find_cookie(request, REFRESH_TOKEN_COOKIE).is_some();
+}
+
+fn session_cookie<'c>(
+    name: &'c str,
+    value: &str,
+    path: &'c str,
+    http_only: bool
+) -> actix_web::cookie::CookieBuilder<'c> {
+    let same_site = match CONFIGURATION.auth_cookie_same_site.as_str() {
+        "None" => SameSite::None,
+        "Lax" => SameSite::Lax,
+        _ => SameSite::Strict,
+    };
+    return Cookie::build(name, value.to_string())
+        .path(path)
+        .http_only(http_only)
+        .secure(CONFIGURATION.auth_cookie_secure)
+        .same_site(same_site);
+}
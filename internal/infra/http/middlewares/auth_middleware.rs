@@ -8,10 +8,15 @@ use actix_web::{
     HttpMessage,
     HttpResponse,
 };
-use config::CONFIGURATION;
-use jsonwebtoken::{ decode, DecodingKey, Validation };
+use config::log::info;
 
-use crate::services::{ auth_service::{ AuthService, Claims }, user_service::UserService };
+use crate::{
+    infra::http::{
+        cookies::{ find_cookie, ACCESS_TOKEN_COOKIE },
+        resources::unavailable_response,
+    },
+    services::{ auth_service::AuthService, user_service::UserService },
+};
 
 pub async fn auth_middleware<B>(
     user_service: Arc<UserService>,
@@ -21,46 +26,54 @@ pub async fn auth_middleware<B>(
 ) -> Result<ServiceResponse<BoxBody>, Error>
     where B: MessageBody + 'static
 {
-    let auth_header = req.headers().get("Authorization");
-    if let Some(auth_header) = auth_header {
-        let token_str = auth_header.to_str().unwrap_or("").replace("Bearer ", "");
-        let validation = Validation::default();
-        let token_data = decode::<Claims>(
-            &token_str,
-            &DecodingKey::from_secret(CONFIGURATION.jwt_secret.as_ref()),
-            &validation
-        );
-
-        match token_data {
-            Ok(data) => {
-                let claims = Arc::new(data.claims);
-                if auth_service.check(claims.clone()).await {
-                    match user_service.find_user_by_id(claims.user_id.clone()).await {
-                        Ok(user) => {
-                            req.extensions_mut().insert(user);
-                            req.extensions_mut().insert(claims.clone());
-                            let res = next.call(req).await?;
-                            return Ok(res.map_into_boxed_body());
-                        }
-                        Err(e) => {
-                            return Ok(
-                                req.into_response(
-                                    HttpResponse::BadRequest()
-                                        .json(e.to_string())
-                                        .map_into_boxed_body()
-                                )
-                            );
+    // The Authorization header wins over the session cookie.
+    let token_str = match req.headers().get("Authorization") {
+        Some(auth_header) => Some(auth_header.to_str().unwrap_or("").replace("Bearer ", "")),
+        None => find_cookie(req.request(), ACCESS_TOKEN_COOKIE),
+    };
+    if let Some(token_str) = token_str {
+        match auth_service.authenticate(&token_str).await {
+            Ok(Some(claims)) => {
+                let claims = Arc::new(claims);
+                if claims.act.is_some() {
+                    info!(
+                        "[{}] acting as [{}]: {} {}",
+                        claims.actor_id(),
+                        claims.user_id,
+                        req.method(),
+                        req.path()
+                    );
+                }
+                match
+                    user_service.find_provider_user(&claims.provider, claims.user_id.clone()).await
+                {
+                    Ok(user) => {
+                        req.extensions_mut().insert(user);
+                        req.extensions_mut().insert(claims.clone());
+                        let res = next.call(req).await?;
+                        return Ok(res.map_into_boxed_body());
+                    }
+                    Err(e) => {
+                        if let Some(response) = unavailable_response(e.as_ref()) {
+                            return Ok(req.into_response(response));
                         }
+                        return Ok(
+                            req.into_response(
+                                HttpResponse::BadRequest().json(e.to_string()).map_into_boxed_body()
+                            )
+                        );
                     }
-                } else {
-                    return Ok(
-                        req.into_response(
-                            HttpResponse::Unauthorized().finish().map_into_boxed_body()
-                        )
-                    );
                 }
             }
-            Err(_) => {
+            Ok(None) => {
+                return Ok(
+                    req.into_response(HttpResponse::Unauthorized().finish().map_into_boxed_body())
+                );
+            }
+            Err(e) => {
+                if let Some(response) = unavailable_response(&e) {
+                    return Ok(req.into_response(response));
+                }
                 return Ok(
                     req.into_response(HttpResponse::Unauthorized().finish().map_into_boxed_body())
                 );
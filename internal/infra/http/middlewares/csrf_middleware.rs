@@ -0,0 +1,64 @@
+use actix_web::{
+    body::{ BoxBody, MessageBody },
+    dev::{ ServiceRequest, ServiceResponse },
+    http::{ header, Method },
+    middleware::Next,
+    Error,
+    HttpResponse,
+};
+
+use crate::infra::http::{
+    cookies::{ find_cookie, has_session_cookie, CSRF_TOKEN_COOKIE, CSRF_TOKEN_HEADER },
+    resources::ErrorResponse,
+};
+
+/// Double-submit CSRF check. State-changing requests authenticated by the session
+/// cookies must repeat the CSRF cookie in the X-CSRF-Token header. Requests with an
+/// Authorization header are not sent by the browser on its own and are let through.
+pub async fn csrf_middleware<B>(
+    req: ServiceRequest,
+    next: Next<B>
+) -> Result<ServiceResponse<BoxBody>, Error>
+    where B: MessageBody + 'static
+{
+    let is_safe_method = matches!(*req.method(), Method::GET | Method::HEAD | Method::OPTIONS);
+    if
+        is_safe_method ||
+        req.headers().contains_key(header::AUTHORIZATION) ||
+        !has_session_cookie(req.request())
+    {
+        let res = next.call(req).await?;
+        return Ok(res.map_into_boxed_body());
+    }
+    let csrf_cookie = find_cookie(req.request(), CSRF_TOKEN_COOKIE);
+    let csrf_header = req
+        .headers()
+        .get(CSRF_TOKEN_HEADER)
+        .and_then(|value| value.to_str().ok());
+    match (csrf_cookie, csrf_header) {
+        (Some(cookie), Some(header)) if tokens_match(&cookie, header) => {
+            let res = next.call(req).await?;
+            return Ok(res.map_into_boxed_body());
+        }
+        _ => {
+            return Ok(
+                req.into_response(
+                    HttpResponse::Forbidden().json(
+                        ErrorResponse::new_error(Some("CSRF token is not valid".to_owned()))
+                    )
+                )
+            );
+        }
+    }
+}
+
+/// Compares in constant time, so the token can not be guessed byte by byte.
+fn tokens_match(expected: &str, actual: &str) -> bool {
+    if expected.len() != actual.len() {
+        return false;
+    }
+    return expected
+        .bytes()
+        .zip(actual.bytes())
+        .fold(0, |difference, (a, b)| difference | (a ^ b)) == 0;
+}
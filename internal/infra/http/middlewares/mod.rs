@@ -4,8 +4,10 @@ use serde::Serialize;
 use async_trait::async_trait;
 
 pub mod auth_middleware;
+pub mod csrf_middleware;
 pub mod is_owner_middleware;
 pub mod path_object_middleware;
+pub mod role_middleware;
 
 pub trait Userable {
     fn get_user_id(&self) -> Arc<str>;
@@ -0,0 +1,44 @@
+use std::sync::Arc;
+
+use actix_web::{
+    body::{ BoxBody, MessageBody },
+    dev::{ ServiceRequest, ServiceResponse },
+    middleware::Next,
+    Error,
+    HttpMessage,
+    HttpResponse,
+};
+
+use crate::{ infra::http::resources::ErrorResponse, services::auth_service::Claims };
+
+/// Lets the request through when the authenticated user has at least one of the
+/// required roles. Must run after `auth_middleware`, which puts the claims in place.
+pub async fn role_middleware<B>(
+    required_roles: Arc<[Arc<str>]>,
+    req: ServiceRequest,
+    next: Next<B>
+) -> Result<ServiceResponse<BoxBody>, Error>
+    where B: MessageBody + 'static
+{
+    let claims = req.extensions().get::<Arc<Claims>>().cloned();
+    match claims {
+        Some(claims) => {
+            if claims.has_any_role(&required_roles) {
+                let res = next.call(req).await?;
+                return Ok(res.map_into_boxed_body());
+            }
+            return Ok(
+                req.into_response(
+                    HttpResponse::Forbidden().json(
+                        ErrorResponse::new_error(Some("Permission denied".to_owned()))
+                    )
+                )
+            );
+        }
+        None => {
+            return Ok(
+                req.into_response(HttpResponse::Unauthorized().finish().map_into_boxed_body())
+            );
+        }
+    }
+}
@@ -4,3 +4,4 @@ pub mod controllers;
 pub mod resources;
 pub mod requests;
 pub mod middlewares;
+pub mod cookies;
@@ -1,21 +1,28 @@
 //! Json extractor.
 use core::fmt::Debug;
+use std::net::{ IpAddr, SocketAddr };
 use std::ops::Deref;
 use std::sync::Arc;
 
 use actix_web::dev::{ JsonBody, Payload };
+use actix_web::http::header;
 use actix_web::FromRequest;
 use actix_web::HttpRequest;
 use config::log::debug;
+use config::CONFIGURATION;
 use futures::future::{ FutureExt, LocalBoxFuture };
 // use futures_util::future::{LocalBoxFuture, Try};
 use serde::de::DeserializeOwned;
 use validator::Validate;
 use error::Error;
 
+use crate::infra::domain::session::SessionMetadataDTO;
+
 mod error;
 pub mod user_request;
 
+const USER_AGENT_MAX_LENGTH: usize = 512;
+
 #[derive(Debug)]
 pub struct JsonValidator<T>(pub T);
 
@@ -127,3 +134,79 @@ impl Default for JsonConfig {
         }
     }
 }
+
+/// Address of the client, taken from X-Forwarded-For only when the proxy is trusted.
+pub fn client_ip(request: &HttpRequest) -> Option<String> {
+    if CONFIGURATION.login_trust_forwarded_for {
+        let forwarded_for: Vec<&str> = request
+            .headers()
+            .get_all(header::X_FORWARDED_FOR)
+            .filter_map(|value| value.to_str().ok())
+            .collect();
+        let address = forwarded_client_ip(&forwarded_for, CONFIGURATION.login_trusted_proxy_hops);
+        if address.is_some() {
+            return address;
+        }
+    }
+    return request.peer_addr().map(|address| address.ip().to_string());
+}
+
+/// Every trusted proxy appends the address it received the request from, so the client
+/// is the entry `hops` positions from the right. Entries further left are sent by the
+/// client and can not be trusted.
+fn forwarded_client_ip(forwarded_for: &[&str], hops: usize) -> Option<String> {
+    let entries: Vec<&str> = forwarded_for
+        .iter()
+        .flat_map(|value| value.split(','))
+        .map(|entry| entry.trim())
+        .collect();
+    let index = entries.len().checked_sub(hops.max(1))?;
+    let entry = entries[index];
+    if let Ok(address) = entry.parse::<IpAddr>() {
+        return Some(address.to_string());
+    }
+    return entry.parse::<SocketAddr>().ok().map(|address| address.ip().to_string());
+}
+
+pub fn session_metadata(request: &HttpRequest) -> SessionMetadataDTO {
+    let user_agent = request
+        .headers()
+        .get(header::USER_AGENT)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| Arc::from(value.chars().take(USER_AGENT_MAX_LENGTH).collect::<String>()));
+    return SessionMetadataDTO {
+        ip: client_ip(request).map(|ip| Arc::from(ip.as_str())),
+        user_agent,
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::forwarded_client_ip;
+
+    #[test]
+    fn takes_the_entry_appended_by_the_trusted_proxy() {
+        let forwarded_for = ["203.0.113.7, 198.51.100.1"];
+        assert_eq!(forwarded_client_ip(&forwarded_for, 1).as_deref(), Some("198.51.100.1"));
+        assert_eq!(forwarded_client_ip(&forwarded_for, 2).as_deref(), Some("203.0.113.7"));
+    }
+
+    #[test]
+    fn combines_repeated_headers() {
+        let forwarded_for = ["203.0.113.7", "198.51.100.1"];
+        assert_eq!(forwarded_client_ip(&forwarded_for, 1).as_deref(), Some("198.51.100.1"));
+    }
+
+    #[test]
+    fn rejects_missing_and_invalid_entries() {
+        assert_eq!(forwarded_client_ip(&[], 1), None);
+        assert_eq!(forwarded_client_ip(&["198.51.100.1"], 2), None);
+        assert_eq!(forwarded_client_ip(&["unknown"], 1), None);
+    }
+
+    #[test]
+    fn strips_ports() {
+        assert_eq!(forwarded_client_ip(&["198.51.100.1:4711"], 1).as_deref(), Some("198.51.100.1"));
+        assert_eq!(forwarded_client_ip(&["[2001:db8::1]:4711"], 1).as_deref(), Some("2001:db8::1"));
+    }
+}
@@ -22,8 +22,88 @@ pub struct UserUpdateRequest {
 
 #[derive(Debug, Deserialize, Validate)]
 pub struct AuthRequest {
-    #[validate(email(message = "Email must be a valid email address"))]
-    pub email: String,
+    /// Value of the configured LDAP login attribute, `email` is accepted for compatibility.
+    #[serde(alias = "email")]
+    #[validate(length(min = 1, message = "Login must not be empty"))]
+    pub login: String,
     #[validate(length(min = 4, message = "Password must be at least 4 characters long"))]
     pub password: String,
 }
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct RefreshRequest {
+    #[validate(length(min = 1, message = "Refresh token must not be empty"))]
+    pub refresh_token: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct PasswordChangeRequest {
+    #[validate(length(min = 1, message = "Current password must not be empty"))]
+    pub current_password: String,
+    #[validate(length(min = 4, message = "Password must be at least 4 characters long"))]
+    pub new_password: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct PasswordForgotRequest {
+    #[serde(alias = "email")]
+    #[validate(length(min = 1, message = "Login must not be empty"))]
+    pub login: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct PasswordResetRequest {
+    #[validate(length(min = 1, message = "Token must not be empty"))]
+    pub token: String,
+    #[validate(length(min = 4, message = "Password must be at least 4 characters long"))]
+    pub new_password: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct RegistrationVerifyRequest {
+    #[validate(length(min = 1, message = "Token must not be empty"))]
+    pub token: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct TotpCodeRequest {
+    #[validate(length(min = 1, message = "Code must not be empty"))]
+    pub code: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct TotpLoginRequest {
+    #[validate(length(min = 1, message = "Challenge token must not be empty"))]
+    pub challenge_token: String,
+    /// TOTP code or one of the recovery codes.
+    #[validate(length(min = 1, message = "Code must not be empty"))]
+    pub code: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct ApiTokenRequest {
+    #[validate(length(min = 1, max = 100, message = "Name must be 1 to 100 characters long"))]
+    pub name: String,
+    /// Roles of the owner the token may use, none by default.
+    #[serde(default)]
+    pub scopes: Vec<String>,
+    #[validate(range(min = 1, message = "Token must be valid for at least one day"))]
+    pub expires_in_days: u32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LogoutAllRequest {
+    /// Keeps the session the request was made with.
+    #[serde(default)]
+    pub keep_current: bool,
+}
+
+/// Form of the RFC 7662 and RFC 7009 endpoints, client credentials may be sent here
+/// instead of the Authorization header.
+#[derive(Debug, Deserialize)]
+pub struct OAuthTokenRequest {
+    pub token: Option<String>,
+    pub token_type_hint: Option<String>,
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+}
@@ -0,0 +1,37 @@
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::infra::database::api_token_repository::ApiToken;
+
+#[derive(Clone, Serialize)]
+pub struct ApiTokenResponse {
+    pub id: Uuid,
+    pub name: String,
+    pub scopes: Vec<String>,
+    pub expires_at: NaiveDateTime,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_used_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+}
+
+impl ApiTokenResponse {
+    pub fn token_to_response(token: ApiToken) -> Self {
+        return ApiTokenResponse {
+            id: token.id,
+            name: token.name,
+            scopes: token.scopes,
+            expires_at: token.expires_at,
+            last_used_at: token.last_used_at,
+            created_at: token.created_at,
+        };
+    }
+
+    pub fn tokens_to_response(tokens: Vec<ApiToken>) -> Vec<Self> {
+        let mut response_objects: Vec<Self> = Vec::new();
+        for token in tokens {
+            response_objects.push(Self::token_to_response(token));
+        }
+        return response_objects;
+    }
+}
@@ -0,0 +1,57 @@
+use serde::Serialize;
+
+use crate::infra::ldap::ldap_server::LdapServerStatus;
+
+#[derive(Clone, Serialize)]
+pub struct HealthResponse {
+    pub status: &'static str,
+    pub ldap: LdapHealthResponse,
+}
+
+#[derive(Clone, Serialize)]
+pub struct LdapHealthResponse {
+    pub status: &'static str,
+    pub servers: Vec<LdapServerResponse>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct LdapServerResponse {
+    pub url: String,
+    pub status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_in_seconds: Option<u64>,
+    pub connections: u32,
+    pub idle_connections: u32,
+    pub requests: u64,
+}
+
+impl LdapHealthResponse {
+    pub fn statuses_to_response(statuses: Vec<LdapServerStatus>) -> Self {
+        let available = statuses
+            .iter()
+            .filter(|status| status.available)
+            .count();
+        let status = match available {
+            0 => "down",
+            n if n == statuses.len() => "up",
+            _ => "degraded",
+        };
+        return LdapHealthResponse {
+            status,
+            servers: statuses.into_iter().map(LdapServerResponse::status_to_response).collect(),
+        };
+    }
+}
+
+impl LdapServerResponse {
+    pub fn status_to_response(status: LdapServerStatus) -> Self {
+        return LdapServerResponse {
+            url: status.url,
+            status: if status.available { "up" } else { "down" },
+            retry_in_seconds: status.retry_in.map(|retry_in| retry_in.as_secs().max(1)),
+            connections: status.connections,
+            idle_connections: status.idle_connections,
+            requests: status.requests,
+        };
+    }
+}
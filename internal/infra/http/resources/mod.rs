@@ -1,7 +1,16 @@
 use std::collections::HashMap;
 
+use actix_web::{ http::{ header, StatusCode }, HttpResponse, ResponseError };
 use serde::Serialize;
 
+use crate::infra::ldap::{
+    ldap_client::LdapClientError,
+    password_policy::{ PasswordChangeError, PasswordPolicyError },
+};
+
+pub mod api_token_resource;
+pub mod health_resource;
+pub mod session_resource;
 pub mod user_resource;
 
 #[derive(Serialize, Clone, PartialEq)]
@@ -29,3 +38,62 @@ impl ErrorResponse {
         return ErrorResponse { field_errors, error: None };
     }
 }
+
+/// Walks the error chain and builds a 503 response when the directory is unreachable.
+pub fn unavailable_response(error: &(dyn std::error::Error + 'static)) -> Option<HttpResponse> {
+    let mut current = Some(error);
+    while let Some(e) = current {
+        if let Some(ldap_error) = e.downcast_ref::<LdapClientError>() {
+            if ldap_error.is_unavailable() {
+                return Some(ldap_error.error_response());
+            }
+        }
+        current = e.source();
+    }
+    return None;
+}
+
+/// New password refused by the directory, reported on the request field it concerns.
+/// A password which can not be changed at all is a 403.
+pub fn password_rejected_response(error: &PasswordChangeError) -> HttpResponse {
+    let field = match error {
+        PasswordChangeError::InvalidCredentials
+        | PasswordChangeError::Policy(PasswordPolicyError::MustSupplyOldPassword) => {
+            "current_password"
+        }
+        PasswordChangeError::ConstraintViolation(_)
+        | PasswordChangeError::Policy(
+              PasswordPolicyError::InsufficientPasswordQuality
+              | PasswordPolicyError::PasswordTooShort
+              | PasswordPolicyError::PasswordTooYoung
+              | PasswordPolicyError::PasswordInHistory,
+          ) => "new_password",
+        PasswordChangeError::Policy(_) | PasswordChangeError::Refused(_) => {
+            return HttpResponse::Forbidden().json(
+                ErrorResponse::new_error(Some(error.to_string()))
+            );
+        }
+    };
+    return HttpResponse::BadRequest().json(
+        ErrorResponse::new_field_errors(
+            Some(HashMap::from([(String::from(field), vec![error.to_string()])]))
+        )
+    );
+}
+
+impl ResponseError for LdapClientError {
+    fn status_code(&self) -> StatusCode {
+        if self.is_unavailable() {
+            return StatusCode::SERVICE_UNAVAILABLE;
+        }
+        return StatusCode::BAD_REQUEST;
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let mut response = HttpResponse::build(self.status_code());
+        if let LdapClientError::Unavailable(retry_after) = self {
+            response.insert_header((header::RETRY_AFTER, retry_after.as_secs().max(1)));
+        }
+        return response.json(ErrorResponse::new_error(Some(self.to_string())));
+    }
+}
@@ -0,0 +1,47 @@
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::infra::database::session_repository::Session;
+
+#[derive(Clone, Serialize)]
+pub struct SessionResponse {
+    pub uuid: Uuid,
+    pub provider: String,
+    pub current: bool,
+    pub created_at: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+    pub last_seen_at: NaiveDateTime,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ip: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_agent: Option<String>,
+}
+
+impl SessionResponse {
+    pub fn session_to_response(session: Session, current_uuid: Option<Uuid>) -> Self {
+        return SessionResponse {
+            current: current_uuid == Some(session.uuid),
+            uuid: session.uuid,
+            provider: session.provider,
+            created_at: session.created_at,
+            expires_at: session.expires_at,
+            last_seen_at: session.last_seen_at,
+            ip: session.ip,
+            user_agent: session.user_agent,
+        };
+    }
+
+    pub fn sessions_to_response(sessions: Vec<Session>, current_uuid: Option<Uuid>) -> Vec<Self> {
+        let mut response_objects: Vec<Self> = Vec::new();
+        for session in sessions {
+            response_objects.push(Self::session_to_response(session, current_uuid));
+        }
+        return response_objects;
+    }
+}
+
+#[derive(Clone, Serialize)]
+pub struct RevokedSessionsResponse {
+    pub revoked: usize,
+}
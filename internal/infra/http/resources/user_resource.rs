@@ -2,13 +2,28 @@ use std::sync::Arc;
 
 use serde::Serialize;
 
-use crate::infra::{ database::user_repository::User, domain::user::UserDTO };
+use crate::{
+    infra::{ database::user_repository::User, domain::user::UserDTO },
+    services::user_image_url,
+};
 
 #[derive(Clone, Serialize)]
 pub struct UserResponse {
     pub uid: Arc<str>,
     pub name: Arc<str>,
     pub email: Arc<str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<Arc<str>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub given_name: Option<Arc<str>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<Arc<str>>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub telephone_numbers: Vec<Arc<str>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub department: Option<Arc<str>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub avatar_url: Option<Arc<str>>,
 }
 
 impl UserResponse {
@@ -17,14 +32,26 @@ impl UserResponse {
             uid: dto.uid.clone(),
             name: dto.name.clone(),
             email: dto.email.clone(),
+            display_name: dto.display_name.clone(),
+            given_name: dto.given_name.clone(),
+            title: dto.title.clone(),
+            telephone_numbers: dto.telephone_numbers.clone(),
+            department: dto.department.clone(),
+            avatar_url: user_image_url(&dto.provider, &dto.id).map(|url| Arc::from(url.as_str())),
         };
     }
 
     pub fn user_to_response(dto: &User) -> Self {
         return UserResponse {
             uid: dto.uid.clone(),
-            name: dto.sn.clone(),
-            email: dto.cn.clone(),
+            name: dto.name.clone(),
+            email: dto.email.clone(),
+            display_name: dto.display_name.clone(),
+            given_name: dto.given_name.clone(),
+            title: dto.title.clone(),
+            telephone_numbers: dto.telephone_numbers.clone(),
+            department: dto.department.clone(),
+            avatar_url: user_image_url(&dto.provider, &dto.id).map(|url| Arc::from(url.as_str())),
         };
     }
 
@@ -36,3 +63,10 @@ impl UserResponse {
         return response_objects;
     }
 }
+
+#[derive(Clone, Serialize)]
+pub struct PermissionsResponse {
+    pub user_id: Arc<str>,
+    pub roles: Vec<Arc<str>>,
+    pub is_admin: bool,
+}
@@ -2,7 +2,8 @@ use std::sync::Arc;
 
 use actix_web::{
     dev::{ ServiceFactory, ServiceRequest, ServiceResponse },
-    middleware::from_fn,
+    http::header,
+    middleware::{ from_fn, DefaultHeaders },
     web::{ self, Data },
     HttpResponse,
     Responder,
@@ -17,13 +18,50 @@ const BASIC_PATH: &str = "/api/v1";
 
 use super::{
     controllers::{
-        auth_controller::{ login, logout, AuthController },
-        user_controller::{ find_all, find_me, UserController },
+        api_token_controller::{ create_api_token, find_my_api_tokens, revoke_api_token },
+        auth_controller::{
+            change_password,
+            login,
+            login_totp,
+            logout,
+            refresh,
+            register_local,
+            AuthController,
+        },
+        health_controller::health,
+        impersonation_controller::{ end_impersonation, impersonate },
+        jwks_controller::jwks,
+        oauth_controller::{ introspect, revoke },
+        password_reset_controller::{ forgot_password, reset_password },
+        registration_controller::{ register, verify_registration },
+        session_controller::{
+            find_my_sessions,
+            find_user_sessions,
+            logout_all,
+            revoke_my_session,
+            revoke_user_sessions,
+            SessionController,
+        },
+        totp_controller::{ disable, enable, enroll, reset, TotpController },
+        user_controller::{
+            create_user,
+            delete_avatar,
+            delete_user,
+            find_all,
+            find_me,
+            patch_user,
+            permissions,
+            update_user,
+            upload_avatar,
+            UserController,
+        },
     },
     middlewares::{
         auth_middleware::auth_middleware,
+        csrf_middleware::csrf_middleware,
         is_owner_middleware::is_owner_middleware,
         path_object_middleware::path_object_middleware,
+        role_middleware::role_middleware,
         Findable,
         Userable,
     },
@@ -32,24 +70,72 @@ use super::{
 pub fn init_routes(cfg: &mut web::ServiceConfig, container: Arc<Container>) {
     let auth_controller_data = web::Data::new(container.controllers.user_controller.clone());
     let user_controller_data = web::Data::new(container.controllers.auth_controller.clone());
+    let totp_controller_data = web::Data::new(container.controllers.totp_controller.clone());
+    let session_controller_data = web::Data::new(
+        container.controllers.session_controller.clone()
+    );
+    let jwks_controller_data = web::Data::new(container.controllers.jwks_controller.clone());
     cfg.service(
         web
             ::scope(BASIC_PATH)
-            .service(init_auth_routes(user_controller_data, Arc::clone(&container)))
-            .service(init_user_routes(auth_controller_data, Arc::clone(&container)))
+            .wrap(from_fn(csrf_middleware))
+            .service(
+                init_auth_routes(
+                    user_controller_data.clone(),
+                    totp_controller_data.clone(),
+                    session_controller_data.clone(),
+                    Arc::clone(&container)
+                )
+            )
+            .service(
+                init_user_routes(
+                    auth_controller_data.clone(),
+                    user_controller_data,
+                    Arc::clone(&container)
+                )
+            )
+            .service(
+                init_admin_routes(
+                    auth_controller_data,
+                    totp_controller_data,
+                    session_controller_data,
+                    Arc::clone(&container)
+                )
+            )
     );
+    let health_controller_data = web::Data::new(container.controllers.health_controller.clone());
     cfg.service(
-        web::scope("/api").route(
-            "",
-            web::head().to(move || async move {
-                return HttpResponse::Ok().finish().map_into_boxed_body();
-            })
-        )
+        web
+            ::scope("/api")
+            .app_data(health_controller_data)
+            .route(
+                "",
+                web::head().to(move || async move {
+                    return HttpResponse::Ok().finish().map_into_boxed_body();
+                })
+            )
+            .route("/health", web::get().to(health))
     );
     cfg.service(
-        actix_files::Files
-            ::new("/static", &CONFIGURATION.file_storage_location)
-            .show_files_listing()
+        web
+            ::scope("/.well-known")
+            .app_data(jwks_controller_data)
+            .route("/jwks.json", web::get().to(jwks))
+    );
+    let oauth_controller_data = web::Data::new(container.controllers.oauth_controller.clone());
+    cfg.service(
+        web
+            ::scope("/oauth")
+            .app_data(oauth_controller_data)
+            .route("/introspect", web::post().to(introspect))
+            .route("/revoke", web::post().to(revoke))
+    );
+    // Uploaded files are served with their detected type only, browsers must not sniff it.
+    cfg.service(
+        web
+            ::scope("/static")
+            .wrap(DefaultHeaders::new().add((header::X_CONTENT_TYPE_OPTIONS, "nosniff")))
+            .service(actix_files::Files::new("", &CONFIGURATION.file_storage_location))
     );
     cfg.default_service(web::get().to(not_found_handler));
 }
@@ -60,6 +146,8 @@ async fn not_found_handler() -> impl Responder {
 
 fn init_auth_routes(
     auth_controller: Data<AuthController>,
+    totp_controller: Data<TotpController>,
+    session_controller: Data<SessionController>,
     container: Arc<Container>
 ) -> Scope<
     impl ServiceFactory<
@@ -70,15 +158,101 @@ fn init_auth_routes(
         InitError = ()
     >
 > {
+    let api_token_controller = web::Data::new(
+        container.controllers.api_token_controller.clone()
+    );
+    let impersonation_controller = web::Data::new(
+        container.controllers.impersonation_controller.clone()
+    );
+    let password_reset_controller = web::Data::new(
+        container.controllers.password_reset_controller.clone()
+    );
+    let registration_controller = web::Data::new(
+        container.controllers.registration_controller.clone()
+    );
     return web
         ::scope("/auth")
         .app_data(auth_controller.clone())
         .route("/login", web::post().to(login))
-        .service(protected_route(container, "").route("/logout", web::post().to(logout)));
+        .route("/login/totp", web::post().to(login_totp))
+        .route("/refresh", web::post().to(refresh))
+        .route("/local/register", web::post().to(register_local))
+        .service(
+            web
+                ::scope("/password")
+                .app_data(password_reset_controller)
+                .route("/forgot", web::post().to(forgot_password))
+                .route("/reset", web::post().to(reset_password))
+        )
+        .service(
+            web
+                ::scope("/register")
+                .app_data(registration_controller)
+                .route("", web::post().to(register))
+                .route("/verify", web::post().to(verify_registration))
+        )
+        .service(
+            protected_route(Arc::clone(&container), "/totp")
+                .app_data(totp_controller)
+                .route("/enroll", web::post().to(enroll))
+                .route("/enable", web::post().to(enable))
+                .route("/disable", web::post().to(disable))
+        )
+        .service(
+            role_route(
+                Arc::clone(&container),
+                "/impersonate/{user_id}",
+                &[&CONFIGURATION.admin_role]
+            )
+                .app_data(impersonation_controller.clone())
+                .route("", web::post().to(impersonate))
+        )
+        .service(
+            protected_route(container, "")
+                .app_data(session_controller)
+                .route("/logout", web::post().to(logout))
+                .route("/logout-all", web::post().to(logout_all))
+                .route("/sessions", web::get().to(find_my_sessions))
+                .route("/sessions/{uuid}", web::delete().to(revoke_my_session))
+                .app_data(api_token_controller)
+                .route("/tokens", web::post().to(create_api_token))
+                .route("/tokens", web::get().to(find_my_api_tokens))
+                .route("/tokens/{id}", web::delete().to(revoke_api_token))
+                .app_data(impersonation_controller)
+                .route("/impersonate", web::delete().to(end_impersonation))
+        );
+}
+
+fn init_admin_routes(
+    us_controller: Data<UserController>,
+    totp_controller: Data<TotpController>,
+    session_controller: Data<SessionController>,
+    container: Arc<Container>
+) -> Scope<
+    impl ServiceFactory<
+        ServiceRequest,
+        Config = (),
+        Response = ServiceResponse,
+        Error = actix_web::Error,
+        InitError = ()
+    >
+> {
+    return role_route(container, "/admin", &[&CONFIGURATION.admin_role])
+        .app_data(totp_controller)
+        .app_data(session_controller)
+        .app_data(us_controller)
+        .route("/users", web::post().to(create_user))
+        .route("/users/{user_id}", web::put().to(update_user))
+        .route("/users/{user_id}", web::patch().to(patch_user))
+        .route("/users/{user_id}", web::delete().to(delete_user))
+        .route("/users/{user_id}/totp", web::delete().to(reset))
+        .route("/users/{user_id}/sessions", web::get().to(find_user_sessions))
+        .route("/users/{user_id}/sessions", web::delete().to(revoke_user_sessions));
 }
 
 fn init_user_routes(
     us_controller: Data<UserController>,
+    auth_controller: Data<AuthController>,
     container: Arc<Container>
 ) -> Scope<
     impl ServiceFactory<
@@ -91,7 +265,12 @@ fn init_user_routes(
 > {
     return protected_route(Arc::clone(&container), "/user")
         .app_data(us_controller)
+        .app_data(auth_controller)
         .route("/all", web::get().to(find_all))
+        .route("/password", web::put().to(change_password))
+        .route("/permissions", web::get().to(permissions))
+        .route("/avatar", web::put().to(upload_avatar))
+        .route("/avatar", web::delete().to(delete_avatar))
         .route("", web::get().to(find_me));
 }
 
@@ -119,6 +298,44 @@ fn protected_route(
     );
 }
 
+/// Scope which requires authentication and at least one of the given roles.
+fn role_route(
+    container: Arc<Container>,
+    path: &str,
+    roles: &[&str]
+) -> Scope<
+    impl ServiceFactory<
+        ServiceRequest,
+        Config = (),
+        Response = ServiceResponse,
+        Error = actix_web::Error,
+        InitError = ()
+    >
+> {
+    let required_roles: Arc<[Arc<str>]> = roles
+        .iter()
+        .map(|role| Arc::from(role.to_lowercase().as_str()))
+        .collect();
+    // Middlewares run in reverse order of registration, so authentication goes first.
+    return web
+        ::scope(path)
+        .wrap(
+            from_fn(move |req: ServiceRequest, next| {
+                return role_middleware(Arc::clone(&required_roles), req, next);
+            })
+        )
+        .wrap(
+            from_fn(move |req: ServiceRequest, next| {
+                return auth_middleware(
+                    Arc::clone(&container.services.user_service),
+                    Arc::clone(&container.services.auth_service),
+                    req,
+                    next
+                );
+            })
+        );
+}
+
 // TODO
 #[allow(dead_code)]
 fn is_owner_route<T>(
@@ -2,6 +2,7 @@ use std::sync::Arc;
 
 use actix_cors::Cors;
 use actix_web::{ middleware::Logger, web::JsonConfig, App, HttpServer };
+use config::CONFIGURATION;
 
 use crate::container::container::Container;
 
@@ -11,13 +12,20 @@ pub async fn start_server(container: Container) -> std::io::Result<()> {
     HttpServer::new(move || {
         let container_clone = Arc::new(container.clone());
         let logger = Logger::default();
-        let cors = Cors::default()
-            .allowed_origin("https://*")
-            .allowed_origin("http://*")
-            .allowed_methods(["GET", "POST", "PUT", "DELETE", "OPTIONS"])
+        let mut cors = Cors::default()
+            .allowed_methods(["GET", "POST", "PUT", "PATCH", "DELETE", "OPTIONS"])
             .allowed_headers(["Accept", "Authorization", "Content-Type", "X-CSRF-Token"])
             .expose_headers(["Link"])
             .max_age(300);
+        if CONFIGURATION.auth_cookie_enabled {
+            // Requests with cookies are only allowed from exactly listed origins.
+            for origin in CONFIGURATION.auth_cookie_allowed_origins.iter() {
+                cors = cors.allowed_origin(origin);
+            }
+            cors = cors.supports_credentials();
+        } else {
+            cors = cors.allowed_origin("https://*").allowed_origin("http://*");
+        }
         return App::new()
             .app_data(JsonConfig::default().limit(4 * 1024 * 1024))
             .wrap(logger)
@@ -0,0 +1,53 @@
+use std::{ sync::Mutex, time::{ Duration, Instant } };
+
+/// Tracks consecutive failures to reach the directory. After each failure new
+/// connection attempts are suspended for an exponentially growing delay.
+pub struct Backoff {
+    initial: Duration,
+    max: Duration,
+    state: Mutex<BackoffState>,
+}
+
+struct BackoffState {
+    failures: u32,
+    retry_at: Option<Instant>,
+}
+
+impl Backoff {
+    pub fn new(initial: Duration, max: Duration) -> Backoff {
+        return Backoff {
+            initial,
+            max,
+            state: Mutex::new(BackoffState { failures: 0, retry_at: None }),
+        };
+    }
+
+    /// Returns the time left until the next attempt is allowed.
+    pub fn retry_after(&self) -> Option<Duration> {
+        let state = self.state.lock().unwrap();
+        return state.retry_at.and_then(|retry_at| {
+            retry_at.checked_duration_since(Instant::now()).filter(|left| !left.is_zero())
+        });
+    }
+
+    pub fn record_failure(&self) -> Duration {
+        let mut state = self.state.lock().unwrap();
+        let delay = self.initial
+            .checked_mul((2u32).saturating_pow(state.failures))
+            .unwrap_or(self.max)
+            .min(self.max);
+        state.failures = state.failures.saturating_add(1);
+        state.retry_at = Some(Instant::now() + delay);
+        return delay;
+    }
+
+    /// Resets the failure counter. Returns `true` when the directory was considered
+    /// unreachable before.
+    pub fn record_success(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let was_failing = state.failures > 0;
+        state.failures = 0;
+        state.retry_at = None;
+        return was_failing;
+    }
+}
@@ -0,0 +1,85 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use config::{ log::info, CONFIGURATION };
+use ldap3::{ Ldap, LdapConnAsync, LdapConnSettings, LdapError, Scope };
+
+/// Creates connections for the service pool. Every connection is bound with the
+/// configured service account, so directory searches never run as an end user.
+pub struct LdapConnectionManager {
+    url: String,
+    settings: LdapConnSettings,
+    bind_dn: String,
+    bind_password: String,
+}
+
+impl LdapConnectionManager {
+    pub fn new(
+        url: &str,
+        settings: LdapConnSettings,
+        bind_dn: &str,
+        bind_password: &str
+    ) -> LdapConnectionManager {
+        return LdapConnectionManager {
+            url: url.to_owned(),
+            settings,
+            bind_dn: bind_dn.to_owned(),
+            bind_password: bind_password.to_owned(),
+        };
+    }
+}
+
+#[async_trait]
+impl bb8::ManageConnection for LdapConnectionManager {
+    type Connection = Ldap;
+    type Error = LdapError;
+
+    async fn connect(&self) -> Result<Ldap, LdapError> {
+        let mut ldap = open_connection(&self.url, self.settings.clone()).await?;
+        if !self.bind_dn.is_empty() {
+            ldap
+                .with_timeout(operation_timeout())
+                .simple_bind(&self.bind_dn, &self.bind_password).await?
+                .success()?;
+        }
+        info!("Opened LDAP connection to [{}]", self.url);
+        return Ok(ldap);
+    }
+
+    async fn is_valid(&self, conn: &mut Ldap) -> Result<(), LdapError> {
+        // Reading the root DSE is the cheapest request every directory answers.
+        conn
+            .with_timeout(operation_timeout())
+            .search("", Scope::Base, "(objectClass=*)", vec!["1.1"]).await?
+            .success()?;
+        return Ok(());
+    }
+
+    fn has_broken(&self, conn: &mut Ldap) -> bool {
+        return conn.is_closed();
+    }
+}
+
+pub async fn open_connection(url: &str, settings: LdapConnSettings) -> Result<Ldap, LdapError> {
+    let (conn, ldap) = LdapConnAsync::with_settings(settings, url).await?;
+    ldap3::drive!(conn);
+    return Ok(ldap);
+}
+
+pub fn operation_timeout() -> Duration {
+    return Duration::from_secs(CONFIGURATION.ldap_operation_timeout);
+}
+
+/// Errors which mean the connection or the directory itself is gone, as opposed
+/// to a regular LDAP result code returned by a healthy server.
+pub fn is_connection_error(error: &LdapError) -> bool {
+    return matches!(
+        error,
+        LdapError::Io { .. } |
+            LdapError::OpSend { .. } |
+            LdapError::ResultRecv { .. } |
+            LdapError::Timeout { .. } |
+            LdapError::EndOfStream |
+            LdapError::NativeTLS { .. }
+    );
+}
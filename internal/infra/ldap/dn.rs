@@ -0,0 +1,99 @@
+/// Returns the unescaped value of the first RDN, e.g. `admins` for
+/// `cn=admins,ou=groups,dc=example,dc=com`. Escapes follow RFC 4514.
+pub fn first_rdn_value(dn: &str) -> Option<String> {
+    let bytes = dn.as_bytes();
+    let mut value: Vec<u8> = Vec::new();
+    let mut in_value = false;
+    let mut i = 0;
+    while i < bytes.len() {
+        let c = bytes[i];
+        if c == b'\\' && i + 1 < bytes.len() {
+            let hex = dn.get(i + 1..i + 3).and_then(|h| u8::from_str_radix(h, 16).ok());
+            let (unescaped, len) = match hex {
+                Some(byte) => (byte, 3),
+                None => (bytes[i + 1], 2),
+            };
+            if in_value {
+                value.push(unescaped);
+            }
+            i += len;
+            continue;
+        }
+        if !in_value {
+            in_value = c == b'=';
+        } else if c == b',' || c == b'+' {
+            break;
+        } else {
+            value.push(c);
+        }
+        i += 1;
+    }
+    if !in_value {
+        return None;
+    }
+    return String::from_utf8(value)
+        .ok()
+        .map(|v| v.trim().to_owned());
+}
+
+/// Splits the DN into the attribute of the first RDN and the DN of the parent entry,
+/// e.g. `cn` and `ou=people,dc=example,dc=com` for `cn=john,ou=people,dc=example,dc=com`.
+pub fn split_first_rdn(dn: &str) -> Option<(&str, &str)> {
+    let (attribute, _) = dn.split_once('=')?;
+    let bytes = dn.as_bytes();
+    let mut i = attribute.len() + 1;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => {
+                i += 2;
+            }
+            b',' => {
+                return Some((attribute.trim(), dn[i + 1..].trim()));
+            }
+            _ => {
+                i += 1;
+            }
+        }
+    }
+    return None;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ first_rdn_value, split_first_rdn };
+
+    #[test]
+    fn splits_at_the_first_unescaped_comma() {
+        assert_eq!(
+            split_first_rdn("cn=john,ou=people,dc=example,dc=com"),
+            Some(("cn", "ou=people,dc=example,dc=com"))
+        );
+        assert_eq!(
+            split_first_rdn("cn=Doe\\, John,ou=people,dc=example"),
+            Some(("cn", "ou=people,dc=example"))
+        );
+        assert_eq!(
+            split_first_rdn("cn=Doe\\2c John\\\\,ou=people"),
+            Some(("cn", "ou=people"))
+        );
+    }
+
+    #[test]
+    fn has_no_parent_without_an_unescaped_comma() {
+        assert_eq!(split_first_rdn("dc=com"), None);
+        assert_eq!(split_first_rdn("cn=Doe\\, John"), None);
+        assert_eq!(split_first_rdn("cn=trailing\\"), None);
+        assert_eq!(split_first_rdn("no attribute"), None);
+    }
+
+    #[test]
+    fn unescapes_the_first_value() {
+        assert_eq!(first_rdn_value("cn=Doe\\, John,ou=people").as_deref(), Some("Doe, John"));
+        assert_eq!(
+            first_rdn_value("cn=Doe\\2c John\\2bx\\3d\\5cy,ou=people").as_deref(),
+            Some("Doe, John+x=\\y")
+        );
+        assert_eq!(first_rdn_value("cn=admins+ou=x,dc=example").as_deref(), Some("admins"));
+        assert_eq!(first_rdn_value("admins"), None);
+    }
+}
@@ -0,0 +1,150 @@
+use std::collections::HashSet;
+
+use config::CONFIGURATION;
+use ldap3::dn_escape;
+
+use super::ldap_client::LdapClientError;
+
+/// Directory entry which is about to be added.
+pub struct NewEntry {
+    pub dn: String,
+    pub attributes: Vec<(String, HashSet<String>)>,
+}
+
+/// Renders LDAP_REGISTRATION_TEMPLATE with the given placeholder values. Attributes whose
+/// value is empty are left out, the DN is built from the RDN attribute and escaped
+/// according to RFC 4514.
+pub fn registration_entry(values: &[(&str, &str)]) -> Result<NewEntry, LdapClientError> {
+    return build_entry(
+        &CONFIGURATION.ldap_registration_template,
+        &CONFIGURATION.ldap_registration_rdn_attribute,
+        &CONFIGURATION.ldap_auth_base_dn,
+        values
+    );
+}
+
+fn build_entry(
+    entry_template: &[(String, String)],
+    rdn_attribute: &str,
+    base_dn: &str,
+    values: &[(&str, &str)]
+) -> Result<NewEntry, LdapClientError> {
+    let mut attributes: Vec<(String, HashSet<String>)> = Vec::new();
+    for (attribute, template) in entry_template.iter() {
+        let value = render(template, values);
+        let value = value.trim();
+        if value.is_empty() {
+            continue;
+        }
+        match attributes.iter_mut().find(|(name, _)| name.eq_ignore_ascii_case(attribute)) {
+            Some((_, existing)) => {
+                existing.insert(value.to_owned());
+            }
+            None => attributes.push((attribute.clone(), HashSet::from([value.to_owned()]))),
+        }
+    }
+    let rdn_value = attributes
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(rdn_attribute))
+        .and_then(|(_, values)| values.iter().next())
+        .ok_or_else(|| {
+            return LdapClientError::Configuration(
+                format!("LDAP_REGISTRATION_TEMPLATE sets no value for [{}]", rdn_attribute)
+            );
+        })?;
+    return Ok(NewEntry {
+        dn: format!("{}={},{}", rdn_attribute, dn_escape(rdn_value.as_str()), base_dn),
+        attributes,
+    });
+}
+
+/// Replaces the placeholders in one pass, so user input is never rendered again.
+fn render(template: &str, values: &[(&str, &str)]) -> String {
+    let mut rendered = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        rendered.push_str(&rest[..start]);
+        let tail = &rest[start..];
+        match values.iter().find(|(placeholder, _)| tail.starts_with(placeholder)) {
+            Some((placeholder, value)) => {
+                rendered.push_str(value);
+                rest = &tail[placeholder.len()..];
+            }
+            None => {
+                rendered.push('{');
+                rest = &tail[1..];
+            }
+        }
+    }
+    rendered.push_str(rest);
+    return rendered;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ build_entry, render, NewEntry };
+
+    fn template() -> Vec<(String, String)> {
+        return [
+            ("objectClass", "inetOrgPerson"),
+            ("cn", "{name}"),
+            ("sn", "{name}"),
+            ("mail", "{email}"),
+            ("uid", "{id}"),
+            ("jpegPhoto", "{avatar}"),
+        ]
+            .iter()
+            .map(|(attribute, value)| (attribute.to_string(), value.to_string()))
+            .collect();
+    }
+
+    fn entry(values: &[(&str, &str)]) -> NewEntry {
+        return build_entry(&template(), "cn", "ou=people,dc=example,dc=com", values).unwrap();
+    }
+
+    fn value<'a>(entry: &'a NewEntry, attribute: &str) -> Option<&'a str> {
+        return entry.attributes
+            .iter()
+            .find(|(name, _)| name == attribute)
+            .and_then(|(_, values)| values.iter().next())
+            .map(|value| value.as_str());
+    }
+
+    #[test]
+    fn does_not_render_placeholders_from_values() {
+        assert_eq!(
+            render("{name} <{email}>", &[("{name}", "{email}"), ("{email}", "{name}")]),
+            "{email} <{name}>"
+        );
+        let entry = entry(&[("{id}", "{name}"), ("{name}", "{id}"), ("{email}", "a@b.c")]);
+        assert_eq!(value(&entry, "uid"), Some("{name}"));
+        assert_eq!(value(&entry, "cn"), Some("{id}"));
+    }
+
+    #[test]
+    fn keeps_unknown_placeholders_and_braces() {
+        assert_eq!(render("{other} { {name", &[("{name}", "john")]), "{other} { {name");
+    }
+
+    #[test]
+    fn escapes_special_characters_in_the_rdn() {
+        let entry = entry(&[("{id}", "john"), ("{name}", "Doe, John+x=\\y"), ("{email}", "")]);
+        assert_eq!(entry.dn, "cn=Doe\\2c John\\2bx\\3d\\5cy,ou=people,dc=example,dc=com");
+        assert_eq!(value(&entry, "cn"), Some("Doe, John+x=\\y"));
+    }
+
+    #[test]
+    fn leaves_out_empty_attributes() {
+        let entry = entry(
+            &[("{id}", "john"), ("{name}", "John"), ("{email}", " "), ("{avatar}", "")]
+        );
+        assert_eq!(value(&entry, "mail"), None);
+        assert_eq!(value(&entry, "jpegPhoto"), None);
+    }
+
+    #[test]
+    fn requires_a_value_for_the_rdn_attribute() {
+        let result = build_entry(&template(), "cn", "dc=example", &[("{name}", "")]);
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,80 @@
+use config::CONFIGURATION;
+use ldap3::ldap_escape;
+
+const ATTRIBUTE_PLACEHOLDER: &str = "{attribute}";
+const IDENTIFIER_PLACEHOLDER: &str = "{identifier}";
+const DN_PLACEHOLDER: &str = "{dn}";
+
+/// Login identifiers are matched case-insensitively, so they are always stored
+/// and compared in their normalized form.
+pub fn normalize_identifier(identifier: &str) -> String {
+    return identifier.trim().to_lowercase();
+}
+
+/// Renders the configured user filter template. The identifier is escaped
+/// according to RFC 4515, so it can never change the structure of the filter.
+pub fn user_filter(identifier: &str) -> String {
+    return render_user_filter(
+        &CONFIGURATION.ldap_user_filter,
+        &CONFIGURATION.ldap_login_attribute,
+        identifier
+    );
+}
+
+/// Renders the configured group filter for the given member DN.
+pub fn group_filter(member_dn: &str) -> String {
+    return render_group_filter(&CONFIGURATION.ldap_group_filter, member_dn);
+}
+
+fn render_user_filter(template: &str, attribute: &str, identifier: &str) -> String {
+    return template
+        .replace(ATTRIBUTE_PLACEHOLDER, attribute)
+        .replace(IDENTIFIER_PLACEHOLDER, &ldap_escape(normalize_identifier(identifier)));
+}
+
+fn render_group_filter(template: &str, member_dn: &str) -> String {
+    return template.replace(DN_PLACEHOLDER, &ldap_escape(member_dn));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ render_group_filter, render_user_filter };
+
+    const USER_FILTER: &str = "(&(objectClass=person)({attribute}={identifier}))";
+
+    #[test]
+    fn escapes_filter_metacharacters_in_identifiers() {
+        assert_eq!(
+            render_user_filter(USER_FILTER, "uid", "*)(uid=*))(|(uid=*"),
+            "(&(objectClass=person)(uid=\\2a\\29\\28uid=\\2a\\29\\29\\28|\\28uid=\\2a))"
+        );
+        assert_eq!(
+            render_user_filter(USER_FILTER, "uid", "a\\b\0c"),
+            "(&(objectClass=person)(uid=a\\5cb\\00c))"
+        );
+    }
+
+    #[test]
+    fn normalizes_identifiers() {
+        assert_eq!(
+            render_user_filter(USER_FILTER, "mail", "  John@Example.COM "),
+            "(&(objectClass=person)(mail=john@example.com))"
+        );
+    }
+
+    #[test]
+    fn does_not_render_placeholders_from_identifiers() {
+        assert_eq!(
+            render_user_filter(USER_FILTER, "uid", "{attribute}"),
+            "(&(objectClass=person)(uid={attribute}))"
+        );
+    }
+
+    #[test]
+    fn escapes_member_dns() {
+        assert_eq!(
+            render_group_filter("(member={dn})", "cn=a*\\2c(b),dc=example"),
+            "(member=cn=a\\2a\\5c2c\\28b\\29,dc=example)"
+        );
+    }
+}
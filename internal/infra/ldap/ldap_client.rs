@@ -0,0 +1,289 @@
+use std::{
+    collections::HashSet,
+    sync::{ atomic::{ AtomicUsize, Ordering }, Arc },
+    time::Duration,
+};
+
+use config::{ log::{ error, info, warn }, CONFIGURATION };
+use futures::future::BoxFuture;
+use ldap3::{ LdapError, Scope, SearchEntry };
+use thiserror::Error;
+
+use super::{
+    backoff::Backoff,
+    connection_manager::is_connection_error,
+    entry::NewEntry,
+    ldap_server::{ LdapServer, LdapServerStatus },
+    password_policy::PasswordChangeError,
+    tls::{ connection_settings, is_bind_allowed },
+};
+
+#[derive(Error, Debug)]
+pub enum LdapClientError {
+    #[error("{0}")] LdapError(LdapError),
+    #[error("LDAP directory is unavailable, retry in {} seconds", .0.as_secs().max(1))]
+    Unavailable(Duration),
+    #[error("LDAP operation timed out")] Timeout,
+    #[error("Simple bind over an unencrypted LDAP connection is not allowed")] InsecureBind,
+    #[error("{0}")] Configuration(String),
+    #[error("{0}")] PasswordChange(PasswordChangeError),
+}
+
+impl LdapClientError {
+    pub fn is_unavailable(&self) -> bool {
+        return matches!(self, LdapClientError::Unavailable(_) | LdapClientError::Timeout);
+    }
+
+    /// Errors after which the same request is retried on the next server.
+    fn is_failover(&self) -> bool {
+        return match self {
+            LdapClientError::LdapError(e) => is_connection_error(e),
+            _ => self.is_unavailable(),
+        };
+    }
+}
+
+impl From<LdapError> for LdapClientError {
+    fn from(error: LdapError) -> Self {
+        LdapClientError::LdapError(error)
+    }
+}
+
+/// Entry point to the directory. Reads are spread round-robin over the configured
+/// servers, a failing server is skipped for a cooldown period and the request is
+/// retried on the next one. When no server answers, callers get a fast
+/// `Unavailable` error until the reconnection backoff expires. Writes always go to
+/// the primary, the first server of `LDAP_URL`, and are sent once.
+pub struct LdapClient {
+    servers: Vec<LdapServer>,
+    next: AtomicUsize,
+    cooldown: Duration,
+    backoff: Backoff,
+}
+
+impl LdapClient {
+    pub async fn new() -> Result<Arc<LdapClient>, LdapClientError> {
+        let urls: Vec<&str> = CONFIGURATION.ldap_url
+            .split(',')
+            .map(|url| url.trim())
+            .filter(|url| !url.is_empty())
+            .collect();
+        if urls.is_empty() {
+            return Err(LdapClientError::Configuration("LDAP_URL has no servers".to_owned()));
+        }
+        for url in urls.iter() {
+            if is_bind_allowed(url) {
+                continue;
+            }
+            if !CONFIGURATION.ldap_bind_dn.is_empty() {
+                return Err(LdapClientError::InsecureBind);
+            }
+            error!(
+                "LDAP connection to [{}] is not encrypted, user logins will be refused. Use ldaps://, LDAP_STARTTLS or LDAP_ALLOW_INSECURE_BIND",
+                url
+            );
+        }
+        let settings = connection_settings().map_err(LdapClientError::Configuration)?;
+        let mut servers = Vec::new();
+        for url in urls {
+            servers.push(LdapServer::new(url, settings.clone()).await?);
+        }
+        return Ok(
+            Arc::new(LdapClient {
+                servers,
+                next: AtomicUsize::new(0),
+                cooldown: Duration::from_secs(CONFIGURATION.ldap_server_cooldown),
+                backoff: Backoff::new(
+                    Duration::from_secs(CONFIGURATION.ldap_reconnect_backoff_initial),
+                    Duration::from_secs(CONFIGURATION.ldap_reconnect_backoff_max)
+                ),
+            })
+        );
+    }
+
+    pub fn status(&self) -> Vec<LdapServerStatus> {
+        return self.servers
+            .iter()
+            .map(|server| server.status())
+            .collect();
+    }
+
+    /// Runs a search with a pooled service connection.
+    pub async fn search(
+        &self,
+        base: &str,
+        scope: Scope,
+        filter: &str,
+        attrs: Vec<&str>
+    ) -> Result<Vec<SearchEntry>, LdapClientError> {
+        return self.execute(|server| {
+            Box::pin(server.search(base, scope, filter, attrs.clone()))
+        }).await;
+    }
+
+    /// Runs a search on the primary, so an entry written a moment ago is read back
+    /// before replication reaches the other servers.
+    pub async fn search_primary(
+        &self,
+        base: &str,
+        scope: Scope,
+        filter: &str,
+        attrs: Vec<&str>
+    ) -> Result<Vec<SearchEntry>, LdapClientError> {
+        return self.execute_on_primary(|server| {
+            Box::pin(server.search(base, scope, filter, attrs))
+        }).await;
+    }
+
+    /// Adds an entry with a pooled service connection.
+    pub async fn add(&self, entry: &NewEntry) -> Result<(), LdapClientError> {
+        let attributes: Vec<(&str, HashSet<&str>)> = entry.attributes
+            .iter()
+            .map(|(name, values)| {
+                return (name.as_str(), values.iter().map(|value| value.as_str()).collect());
+            })
+            .collect();
+        return self.execute_on_primary(|server| Box::pin(server.add(&entry.dn, attributes))).await;
+    }
+
+    pub async fn replace(
+        &self,
+        dn: &str,
+        changes: &[(&str, &str)]
+    ) -> Result<(), LdapClientError> {
+        return self.execute_on_primary(|server| Box::pin(server.replace(dn, changes))).await;
+    }
+
+    pub async fn rename(&self, dn: &str, rdn: &str) -> Result<(), LdapClientError> {
+        return self.execute_on_primary(|server| Box::pin(server.rename(dn, rdn))).await;
+    }
+
+    pub async fn delete(&self, dn: &str) -> Result<(), LdapClientError> {
+        return self.execute_on_primary(|server| Box::pin(server.delete(dn))).await;
+    }
+
+    /// Checks user credentials on a short-lived connection that is never shared,
+    /// so the pooled connections keep their service identity.
+    pub async fn verify_credentials(
+        &self,
+        dn: &str,
+        password: &str
+    ) -> Result<bool, LdapClientError> {
+        // An empty password turns a simple bind into an unauthenticated one,
+        // which most directories accept for any DN.
+        if password.is_empty() {
+            return Ok(false);
+        }
+        return self.execute(|server| Box::pin(server.verify_credentials(dn, password))).await;
+    }
+
+    /// Changes the password of the user, the current one is verified with a bind.
+    pub async fn change_password(
+        &self,
+        dn: &str,
+        current_password: &str,
+        new_password: &str
+    ) -> Result<(), LdapClientError> {
+        if current_password.is_empty() {
+            return Err(LdapClientError::PasswordChange(PasswordChangeError::InvalidCredentials));
+        }
+        return self.execute_on_primary(|server| {
+            Box::pin(server.change_password(dn, current_password, new_password))
+        }).await;
+    }
+
+    /// Sets the password of the user with the service account, e.g. after a reset.
+    pub async fn reset_password(
+        &self,
+        dn: &str,
+        new_password: &str
+    ) -> Result<(), LdapClientError> {
+        return self.execute_on_primary(|server| {
+            Box::pin(server.reset_password(dn, new_password))
+        }).await;
+    }
+
+    async fn execute<'a, T, F>(&'a self, operation: F) -> Result<T, LdapClientError>
+        where F: Fn(&'a LdapServer) -> BoxFuture<'a, Result<T, LdapClientError>>
+    {
+        let mut last_error = None;
+        for server in self.candidates()? {
+            match operation(server).await {
+                Err(e) if e.is_failover() => {
+                    warn!(
+                        "LDAP server [{}] failed [{}], skipping it for {:?}",
+                        server.url,
+                        e,
+                        self.cooldown
+                    );
+                    server.mark_down(self.cooldown);
+                    last_error = Some(e);
+                }
+                result => {
+                    if server.mark_up() {
+                        info!("LDAP server [{}] is reachable again", server.url);
+                    }
+                    if self.backoff.record_success() {
+                        info!("LDAP directory is reachable again");
+                    }
+                    return result;
+                }
+            }
+        }
+        let delay = self.backoff.record_failure();
+        warn!("No LDAP server is reachable, next attempt in {:?}", delay);
+        if let Some(LdapClientError::Timeout) = last_error {
+            return Err(LdapClientError::Timeout);
+        }
+        return Err(LdapClientError::Unavailable(delay));
+    }
+
+    /// Sends the operation to the primary once. A write is never retried, after a
+    /// timeout it may have been applied already and another server could apply it
+    /// a second time or disagree with the primary.
+    async fn execute_on_primary<'a, T, F>(&'a self, operation: F) -> Result<T, LdapClientError>
+        where F: FnOnce(&'a LdapServer) -> BoxFuture<'a, Result<T, LdapClientError>>
+    {
+        if let Some(retry_after) = self.backoff.retry_after() {
+            return Err(LdapClientError::Unavailable(retry_after));
+        }
+        let primary = &self.servers[0];
+        let result = operation(primary).await;
+        match &result {
+            Err(e) if e.is_failover() => {
+                warn!("LDAP primary [{}] failed [{}]", primary.url, e);
+                primary.mark_down(self.cooldown);
+            }
+            _ => {
+                if primary.mark_up() {
+                    info!("LDAP server [{}] is reachable again", primary.url);
+                }
+            }
+        }
+        return result;
+    }
+
+    /// Servers to try for the next request, starting from the next one in the
+    /// round-robin order. Servers in cooldown are skipped, when all of them are
+    /// cooling down the one which comes back first is probed.
+    fn candidates(&self) -> Result<Vec<&LdapServer>, LdapClientError> {
+        if let Some(retry_after) = self.backoff.retry_after() {
+            return Err(LdapClientError::Unavailable(retry_after));
+        }
+        let start = self.next.fetch_add(1, Ordering::Relaxed);
+        let mut available = Vec::new();
+        let mut failed = Vec::new();
+        for i in 0..self.servers.len() {
+            let server = &self.servers[(start + i) % self.servers.len()];
+            match server.retry_in() {
+                None => available.push(server),
+                Some(retry_in) => failed.push((retry_in, server)),
+            }
+        }
+        if available.is_empty() {
+            failed.sort_by_key(|(retry_in, _)| *retry_in);
+            available.extend(failed.into_iter().take(1).map(|(_, server)| server));
+        }
+        return Ok(available);
+    }
+}
@@ -0,0 +1,351 @@
+use std::{
+    collections::HashSet,
+    sync::{ atomic::{ AtomicU64, Ordering }, Mutex },
+    time::{ Duration, Instant },
+};
+
+use bb8::{ PooledConnection, RunError };
+use config::CONFIGURATION;
+use ldap3::{
+    exop::PasswordModify,
+    Ldap,
+    LdapConnSettings,
+    LdapError,
+    LdapResult,
+    Mod,
+    Scope,
+    SearchEntry,
+};
+
+use super::{
+    connection_manager::{
+        is_connection_error,
+        open_connection,
+        operation_timeout,
+        LdapConnectionManager,
+    },
+    ldap_client::LdapClientError,
+    password_policy::{ password_policy_control, password_policy_error, PasswordChangeError },
+    tls::is_bind_allowed,
+    LdapPool,
+};
+
+/// Result code returned by the directory for a wrong DN or password.
+const INVALID_CREDENTIALS: u32 = 49;
+/// Result codes of servers which do not support the password modify operation.
+const PROTOCOL_ERROR: u32 = 2;
+const UNAVAILABLE_CRITICAL_EXTENSION: u32 = 12;
+/// Result codes of a new password which the directory did not accept.
+const CONSTRAINT_VIOLATION: u32 = 19;
+const INSUFFICIENT_ACCESS_RIGHTS: u32 = 50;
+const UNWILLING_TO_PERFORM: u32 = 53;
+const PASSWORD_ATTRIBUTE: &str = "userPassword";
+
+/// One directory replica with its own connection pool.
+pub struct LdapServer {
+    pub url: String,
+    pool: LdapPool,
+    settings: LdapConnSettings,
+    down_until: Mutex<Option<Instant>>,
+    requests: AtomicU64,
+}
+
+pub struct LdapServerStatus {
+    pub url: String,
+    pub available: bool,
+    pub retry_in: Option<Duration>,
+    pub connections: u32,
+    pub idle_connections: u32,
+    pub requests: u64,
+}
+
+impl LdapServer {
+    pub async fn new(url: &str, settings: LdapConnSettings) -> Result<LdapServer, LdapError> {
+        let manager = LdapConnectionManager::new(
+            url,
+            settings.clone(),
+            &CONFIGURATION.ldap_bind_dn,
+            &CONFIGURATION.ldap_bind_password
+        );
+        let min_idle = match CONFIGURATION.ldap_pool_min_idle {
+            0 => None,
+            value => Some(value),
+        };
+        let pool = bb8::Pool
+            ::builder()
+            .max_size(CONFIGURATION.ldap_pool_max_size)
+            .min_idle(min_idle)
+            .idle_timeout(Some(Duration::from_secs(CONFIGURATION.ldap_pool_idle_timeout)))
+            .connection_timeout(Duration::from_secs(CONFIGURATION.ldap_pool_connection_timeout))
+            .test_on_check_out(CONFIGURATION.ldap_pool_health_check)
+            // Failed connects are reported right away and handled by the failover.
+            .retry_connection(false)
+            .build(manager).await?;
+        return Ok(LdapServer {
+            url: url.to_owned(),
+            pool,
+            settings,
+            down_until: Mutex::new(None),
+            requests: AtomicU64::new(0),
+        });
+    }
+
+    /// Time left until a failed server is taken back into rotation.
+    pub fn retry_in(&self) -> Option<Duration> {
+        let down_until = self.down_until.lock().unwrap();
+        return down_until.and_then(|until| {
+            until.checked_duration_since(Instant::now()).filter(|left| !left.is_zero())
+        });
+    }
+
+    pub fn mark_down(&self, cooldown: Duration) {
+        *self.down_until.lock().unwrap() = Some(Instant::now() + cooldown);
+    }
+
+    /// Returns `true` when the server was marked as failed before.
+    pub fn mark_up(&self) -> bool {
+        return self.down_until.lock().unwrap().take().is_some();
+    }
+
+    pub fn status(&self) -> LdapServerStatus {
+        let state = self.pool.state();
+        let retry_in = self.retry_in();
+        return LdapServerStatus {
+            url: self.url.clone(),
+            available: retry_in.is_none(),
+            retry_in,
+            connections: state.connections,
+            idle_connections: state.idle_connections,
+            requests: self.requests.load(Ordering::Relaxed),
+        };
+    }
+
+    pub async fn search(
+        &self,
+        base: &str,
+        scope: Scope,
+        filter: &str,
+        attrs: Vec<&str>
+    ) -> Result<Vec<SearchEntry>, LdapClientError> {
+        let mut ldap = self.get_connection().await?;
+        let result = ldap.with_timeout(operation_timeout()).search(base, scope, filter, attrs).await;
+        let (entries, _) = check_result(&mut ldap, result).await?.success()?;
+        return Ok(entries.into_iter().map(SearchEntry::construct).collect());
+    }
+
+    pub async fn add(
+        &self,
+        dn: &str,
+        attributes: Vec<(&str, HashSet<&str>)>
+    ) -> Result<(), LdapClientError> {
+        let mut ldap = self.get_connection().await?;
+        let result = ldap.with_timeout(operation_timeout()).add(dn, attributes).await;
+        check_result(&mut ldap, result).await?.success()?;
+        return Ok(());
+    }
+
+    /// Replaces the values of the given attributes.
+    pub async fn replace(
+        &self,
+        dn: &str,
+        changes: &[(&str, &str)]
+    ) -> Result<(), LdapClientError> {
+        let mods = changes
+            .iter()
+            .map(|(attribute, value)| Mod::Replace(*attribute, HashSet::from([*value])))
+            .collect();
+        let mut ldap = self.get_connection().await?;
+        let result = ldap.with_timeout(operation_timeout()).modify(dn, mods).await;
+        check_result(&mut ldap, result).await?.success()?;
+        return Ok(());
+    }
+
+    /// Changes the RDN of the entry, the old RDN value is removed.
+    pub async fn rename(&self, dn: &str, rdn: &str) -> Result<(), LdapClientError> {
+        let mut ldap = self.get_connection().await?;
+        let result = ldap.with_timeout(operation_timeout()).modifydn(dn, rdn, true, None).await;
+        check_result(&mut ldap, result).await?.success()?;
+        return Ok(());
+    }
+
+    pub async fn delete(&self, dn: &str) -> Result<(), LdapClientError> {
+        let mut ldap = self.get_connection().await?;
+        let result = ldap.with_timeout(operation_timeout()).delete(dn).await;
+        check_result(&mut ldap, result).await?.success()?;
+        return Ok(());
+    }
+
+    /// Checks user credentials on a short-lived connection that is never shared,
+    /// so the pooled connections keep their service identity.
+    pub async fn verify_credentials(
+        &self,
+        dn: &str,
+        password: &str
+    ) -> Result<bool, LdapClientError> {
+        if !is_bind_allowed(&self.url) {
+            return Err(LdapClientError::InsecureBind);
+        }
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        let mut ldap = open_connection(&self.url, self.settings.clone()).await.map_err(
+            map_error
+        )?;
+        let result = ldap
+            .with_timeout(operation_timeout())
+            .simple_bind(dn, password).await
+            .map_err(map_error)?;
+        let _ = ldap.unbind().await;
+        if result.rc == INVALID_CREDENTIALS {
+            return Ok(false);
+        }
+        result.success()?;
+        return Ok(true);
+    }
+
+    /// Binds as the user and sets the new password on a short-lived connection.
+    pub async fn change_password(
+        &self,
+        dn: &str,
+        current_password: &str,
+        new_password: &str
+    ) -> Result<(), LdapClientError> {
+        if !is_bind_allowed(&self.url) {
+            return Err(LdapClientError::InsecureBind);
+        }
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        let mut ldap = open_connection(&self.url, self.settings.clone()).await.map_err(
+            map_error
+        )?;
+        let result = modify_password(&mut ldap, dn, current_password, new_password).await;
+        let _ = ldap.unbind().await;
+        return result;
+    }
+
+    /// Sets the password with a pooled service connection, the service account needs
+    /// write access to the password of the user.
+    pub async fn reset_password(
+        &self,
+        dn: &str,
+        new_password: &str
+    ) -> Result<(), LdapClientError> {
+        let mut ldap = self.get_connection().await?;
+        let result = set_password(&mut ldap, dn, None, new_password).await;
+        if let Err(LdapClientError::LdapError(e)) = &result {
+            if is_connection_error(e) {
+                let _ = ldap.unbind().await;
+            }
+        }
+        return result;
+    }
+
+    async fn get_connection(
+        &self
+    ) -> Result<PooledConnection<'_, LdapConnectionManager>, LdapClientError> {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        match self.pool.get().await {
+            Ok(conn) => {
+                return Ok(conn);
+            }
+            Err(RunError::User(e)) => {
+                return Err(map_error(e));
+            }
+            Err(RunError::TimedOut) => {
+                return Err(LdapClientError::Timeout);
+            }
+        }
+    }
+}
+
+/// Drops broken connections from the pool, closing the connection makes the
+/// pool discard it on return.
+async fn check_result<T>(
+    ldap: &mut PooledConnection<'_, LdapConnectionManager>,
+    result: Result<T, LdapError>
+) -> Result<T, LdapClientError> {
+    match result {
+        Ok(value) => {
+            return Ok(value);
+        }
+        Err(e) => {
+            if is_connection_error(&e) {
+                let _ = ldap.unbind().await;
+            }
+            return Err(map_error(e));
+        }
+    }
+}
+
+async fn modify_password(
+    ldap: &mut Ldap,
+    dn: &str,
+    current_password: &str,
+    new_password: &str
+) -> Result<(), LdapClientError> {
+    let bind = ldap
+        .with_timeout(operation_timeout())
+        .simple_bind(dn, current_password).await
+        .map_err(map_error)?;
+    if bind.rc == INVALID_CREDENTIALS {
+        return Err(LdapClientError::PasswordChange(PasswordChangeError::InvalidCredentials));
+    }
+    bind.success()?;
+    return set_password(ldap, dn, Some(current_password), new_password).await;
+}
+
+/// Password modify extended operation (RFC 3062), servers without it get the
+/// userPassword attribute replaced.
+async fn set_password(
+    ldap: &mut Ldap,
+    dn: &str,
+    current_password: Option<&str>,
+    new_password: &str
+) -> Result<(), LdapClientError> {
+    let result = ldap
+        .with_timeout(operation_timeout())
+        .with_controls(password_policy_control())
+        .extended(PasswordModify {
+            user_id: Some(dn),
+            old_pass: current_password,
+            new_pass: Some(new_password),
+        }).await
+        .map_err(map_error)?.1;
+    if result.rc != PROTOCOL_ERROR && result.rc != UNAVAILABLE_CRITICAL_EXTENSION {
+        return check_password_result(result);
+    }
+    let result = ldap
+        .with_timeout(operation_timeout())
+        .with_controls(password_policy_control())
+        .modify(dn, vec![Mod::Replace(PASSWORD_ATTRIBUTE, HashSet::from([new_password]))]).await
+        .map_err(map_error)?;
+    return check_password_result(result);
+}
+
+/// Turns the refusals of the directory into errors which can be shown to the user.
+fn check_password_result(result: LdapResult) -> Result<(), LdapClientError> {
+    if result.rc == 0 {
+        return Ok(());
+    }
+    if let Some(policy_error) = password_policy_error(&result.ctrls) {
+        return Err(LdapClientError::PasswordChange(PasswordChangeError::Policy(policy_error)));
+    }
+    let message = if result.text.is_empty() {
+        String::from("Password was rejected by the directory")
+    } else {
+        result.text.clone()
+    };
+    let error = match result.rc {
+        INVALID_CREDENTIALS => PasswordChangeError::InvalidCredentials,
+        CONSTRAINT_VIOLATION => PasswordChangeError::ConstraintViolation(message),
+        INSUFFICIENT_ACCESS_RIGHTS | UNWILLING_TO_PERFORM => PasswordChangeError::Refused(message),
+        _ => {
+            return Err(LdapClientError::LdapError(LdapError::from(result)));
+        }
+    };
+    return Err(LdapClientError::PasswordChange(error));
+}
+
+fn map_error(error: LdapError) -> LdapClientError {
+    if let LdapError::Timeout { .. } = error {
+        return LdapClientError::Timeout;
+    }
+    return LdapClientError::LdapError(error);
+}
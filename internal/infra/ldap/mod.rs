@@ -0,0 +1,11 @@
+pub mod backoff;
+pub mod connection_manager;
+pub mod dn;
+pub mod entry;
+pub mod filter;
+pub mod ldap_client;
+pub mod ldap_server;
+pub mod password_policy;
+pub mod tls;
+
+pub type LdapPool = bb8::Pool<connection_manager::LdapConnectionManager>;
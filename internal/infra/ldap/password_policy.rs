@@ -0,0 +1,126 @@
+use ldap3::{ asn1::{ parse_tag, TagClass }, controls::{ Control, RawControl } };
+use thiserror::Error;
+
+/// Password policy control (draft-behera-ldap-password-policy). OpenLDAP and 389 Directory
+/// Server use it to tell why a new password was rejected.
+pub const PASSWORD_POLICY_OID: &str = "1.3.6.1.4.1.42.2.27.8.5.1";
+/// Context tag of the error in the response value.
+const POLICY_ERROR_TAG: u64 = 1;
+
+#[derive(Error, Debug, Clone, Copy, PartialEq)]
+pub enum PasswordPolicyError {
+    #[error("Password has expired")] PasswordExpired,
+    #[error("Account is locked")] AccountLocked,
+    #[error("Password must be changed after a reset")] ChangeAfterReset,
+    #[error("Password of this account can not be changed")] PasswordModNotAllowed,
+    #[error("Current password must be supplied")] MustSupplyOldPassword,
+    #[error("Password does not meet the quality requirements")] InsufficientPasswordQuality,
+    #[error("Password is too short")] PasswordTooShort,
+    #[error("Password was changed too recently")] PasswordTooYoung,
+    #[error("Password was used before")] PasswordInHistory,
+}
+
+/// Reasons the directory refused a password change.
+#[derive(Error, Debug)]
+pub enum PasswordChangeError {
+    #[error("Current password is not valid")] InvalidCredentials,
+    #[error("{0}")] Policy(PasswordPolicyError),
+    #[error("{0}")] ConstraintViolation(String),
+    #[error("{0}")] Refused(String),
+}
+
+/// Asks the server to explain a rejected password, servers without the policy ignore it.
+pub fn password_policy_control() -> RawControl {
+    return RawControl { ctype: PASSWORD_POLICY_OID.to_owned(), crit: false, val: None };
+}
+
+/// Error from the password policy response control, if the server sent one.
+pub fn password_policy_error(controls: &[Control]) -> Option<PasswordPolicyError> {
+    let value = controls
+        .iter()
+        .find(|control| control.1.ctype == PASSWORD_POLICY_OID)?
+        .1.val.as_ref()?;
+    let (_, tag) = parse_tag(value).ok()?;
+    let error = tag
+        .expect_constructed()?
+        .into_iter()
+        .find(|tag| tag.class == TagClass::Context && tag.id == POLICY_ERROR_TAG)?
+        .expect_primitive()?;
+    let code = error.iter().fold(0u64, |code, byte| (code << 8) | (*byte as u64));
+    return match code {
+        0 => Some(PasswordPolicyError::PasswordExpired),
+        1 => Some(PasswordPolicyError::AccountLocked),
+        2 => Some(PasswordPolicyError::ChangeAfterReset),
+        3 => Some(PasswordPolicyError::PasswordModNotAllowed),
+        4 => Some(PasswordPolicyError::MustSupplyOldPassword),
+        5 => Some(PasswordPolicyError::InsufficientPasswordQuality),
+        6 => Some(PasswordPolicyError::PasswordTooShort),
+        7 => Some(PasswordPolicyError::PasswordTooYoung),
+        8 => Some(PasswordPolicyError::PasswordInHistory),
+        _ => None,
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use ldap3::controls::{ Control, RawControl };
+
+    use super::{ password_policy_error, PasswordPolicyError, PASSWORD_POLICY_OID };
+
+    fn response(oid: &str, value: &[u8]) -> Vec<Control> {
+        let control = RawControl { ctype: oid.to_owned(), crit: false, val: Some(value.to_vec()) };
+        return vec![Control(None, control)];
+    }
+
+    #[test]
+    fn decodes_every_error_code() {
+        let expected = [
+            PasswordPolicyError::PasswordExpired,
+            PasswordPolicyError::AccountLocked,
+            PasswordPolicyError::ChangeAfterReset,
+            PasswordPolicyError::PasswordModNotAllowed,
+            PasswordPolicyError::MustSupplyOldPassword,
+            PasswordPolicyError::InsufficientPasswordQuality,
+            PasswordPolicyError::PasswordTooShort,
+            PasswordPolicyError::PasswordTooYoung,
+            PasswordPolicyError::PasswordInHistory,
+        ];
+        for (code, error) in expected.into_iter().enumerate() {
+            let controls = response(PASSWORD_POLICY_OID, &[0x30, 0x03, 0x81, 0x01, code as u8]);
+            assert_eq!(password_policy_error(&controls), Some(error));
+        }
+    }
+
+    #[test]
+    fn skips_the_warning() {
+        // timeBeforeExpiration of 5 seconds followed by passwordInHistory.
+        let value = [0x30, 0x08, 0xa0, 0x03, 0x80, 0x01, 0x05, 0x81, 0x01, 0x08];
+        assert_eq!(
+            password_policy_error(&response(PASSWORD_POLICY_OID, &value)),
+            Some(PasswordPolicyError::PasswordInHistory)
+        );
+    }
+
+    #[test]
+    fn ignores_responses_without_an_error() {
+        let warning_only = [0x30, 0x05, 0xa0, 0x03, 0x80, 0x01, 0x05];
+        assert_eq!(password_policy_error(&response(PASSWORD_POLICY_OID, &warning_only)), None);
+        assert_eq!(password_policy_error(&response(PASSWORD_POLICY_OID, &[0x30, 0x00])), None);
+        assert_eq!(password_policy_error(&[]), None);
+    }
+
+    #[test]
+    fn ignores_unknown_codes_and_other_controls() {
+        let unknown = [0x30, 0x03, 0x81, 0x01, 0x09];
+        assert_eq!(password_policy_error(&response(PASSWORD_POLICY_OID, &unknown)), None);
+        let value = [0x30, 0x03, 0x81, 0x01, 0x06];
+        assert_eq!(password_policy_error(&response("1.2.840.113556.1.4.319", &value)), None);
+    }
+
+    #[test]
+    fn rejects_malformed_values() {
+        for value in [&[][..], &[0x30, 0x03, 0x81, 0x01], &[0x81, 0x01, 0x06], &[0x30]] {
+            assert_eq!(password_policy_error(&response(PASSWORD_POLICY_OID, value)), None);
+        }
+    }
+}
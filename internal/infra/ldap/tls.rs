@@ -0,0 +1,64 @@
+use std::{ fs, time::Duration };
+
+use config::CONFIGURATION;
+use ldap3::LdapConnSettings;
+use native_tls::{ Certificate, Identity, TlsConnector };
+
+const PEM_CERTIFICATE_END: &str = "-----END CERTIFICATE-----";
+
+/// Builds connection settings shared by pooled and throwaway connections:
+/// connect timeout, StartTLS and a TLS connector with the configured CA bundle
+/// and client certificate.
+pub fn connection_settings() -> Result<LdapConnSettings, String> {
+    let mut builder = TlsConnector::builder();
+    if !CONFIGURATION.ldap_ca_cert_path.is_empty() {
+        let bundle = fs
+            ::read_to_string(&CONFIGURATION.ldap_ca_cert_path)
+            .map_err(|e| format!("Error in reading LDAP CA bundle: {}", e))?;
+        // A bundle may hold several certificates, native-tls parses one at a time.
+        for pem in bundle.split_inclusive(PEM_CERTIFICATE_END) {
+            if !pem.contains(PEM_CERTIFICATE_END) {
+                continue;
+            }
+            let certificate = Certificate::from_pem(pem.trim().as_bytes()).map_err(|e|
+                format!("Error in parsing LDAP CA certificate: {}", e)
+            )?;
+            builder.add_root_certificate(certificate);
+        }
+    }
+    if !CONFIGURATION.ldap_client_cert_path.is_empty() {
+        let certificate = fs
+            ::read(&CONFIGURATION.ldap_client_cert_path)
+            .map_err(|e| format!("Error in reading LDAP client certificate: {}", e))?;
+        let key = fs
+            ::read(&CONFIGURATION.ldap_client_key_path)
+            .map_err(|e| format!("Error in reading LDAP client key: {}", e))?;
+        let identity = Identity::from_pkcs8(&certificate, &key).map_err(|e|
+            format!("Error in loading LDAP client certificate: {}", e)
+        )?;
+        builder.identity(identity);
+    }
+    let connector = builder
+        .build()
+        .map_err(|e| format!("Error in building LDAP TLS connector: {}", e))?;
+    return Ok(
+        LdapConnSettings::new()
+            .set_conn_timeout(Duration::from_secs(CONFIGURATION.ldap_connect_timeout))
+            .set_connector(connector)
+            .set_starttls(CONFIGURATION.ldap_starttls)
+    );
+}
+
+/// Whether credentials sent to the given URL travel over an encrypted channel.
+pub fn is_secure_channel(url: &str) -> bool {
+    let url = url.trim().to_lowercase();
+    return url.starts_with("ldaps://") ||
+        url.starts_with("ldapi://") ||
+        (url.starts_with("ldap://") && CONFIGURATION.ldap_starttls);
+}
+
+/// Simple binds send the password in clear text, they are only allowed over an
+/// encrypted channel unless explicitly enabled.
+pub fn is_bind_allowed(url: &str) -> bool {
+    return CONFIGURATION.ldap_allow_insecure_bind || is_secure_channel(url);
+}
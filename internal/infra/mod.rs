@@ -1,3 +1,4 @@
 pub mod database;
 pub mod domain;
 pub mod http;
+pub mod ldap;
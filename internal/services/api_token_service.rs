@@ -0,0 +1,160 @@
+use std::sync::Arc;
+
+use chrono::Utc;
+use config::{ log::warn, CONFIGURATION };
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::infra::{
+    database::api_token_repository::{ ApiToken, ApiTokenRepository, NewApiToken },
+    domain::api_token::CreatedApiTokenDTO,
+    http::requests::user_request::ApiTokenRequest,
+};
+
+use super::{
+    auth_service::{ generate_random_token, hash_token, Claims },
+    providers::{ AuthenticationProviderError, AuthenticationProviders },
+};
+
+/// Tells API tokens apart from JWTs in the Authorization header.
+pub const API_TOKEN_PREFIX: &str = "pat_";
+const API_TOKEN_LENGTH: usize = 48;
+/// Last use of a token is written at most once per this many seconds.
+const LAST_USED_PRECISION: i64 = 60;
+
+#[derive(Error, Debug)]
+pub enum ApiTokenServiceError {
+    #[error("{0}")] DieselError(#[from] diesel::result::Error),
+    #[error("{0}")] ProviderError(#[from] AuthenticationProviderError),
+    #[error("Scope [{0}] is not one of your roles")] InvalidScope(String),
+    #[error("Token lifetime must not exceed {0} days")] InvalidLifetime(u32),
+    #[error("API tokens can only be created in your own session")] NotAllowed,
+}
+
+/// Personal access tokens. They are stored as hashes, carry a subset of the roles of
+/// their owner as scopes and expire after the chosen number of days.
+pub struct ApiTokenService {
+    api_token_repository: Arc<ApiTokenRepository>,
+    providers: Arc<AuthenticationProviders>,
+}
+
+impl ApiTokenService {
+    pub fn new(
+        api_token_repository: Arc<ApiTokenRepository>,
+        providers: Arc<AuthenticationProviders>
+    ) -> Arc<ApiTokenService> {
+        return Arc::new(ApiTokenService { api_token_repository, providers });
+    }
+
+    /// Creates a token for the owner of the session, the token itself is only returned here.
+    pub fn create(
+        &self,
+        claims: &Claims,
+        request: ApiTokenRequest
+    ) -> Result<CreatedApiTokenDTO, ApiTokenServiceError> {
+        if claims.api_token_id.is_some() || claims.act.is_some() {
+            return Err(ApiTokenServiceError::NotAllowed);
+        }
+        if request.expires_in_days > CONFIGURATION.api_token_max_lifetime_days {
+            return Err(
+                ApiTokenServiceError::InvalidLifetime(CONFIGURATION.api_token_max_lifetime_days)
+            );
+        }
+        let mut scopes: Vec<String> = Vec::new();
+        for scope in request.scopes.iter() {
+            let scope = scope.trim().to_lowercase();
+            if !claims.roles.iter().any(|role| role.as_ref() == scope) {
+                return Err(ApiTokenServiceError::InvalidScope(scope));
+            }
+            if !scopes.contains(&scope) {
+                scopes.push(scope);
+            }
+        }
+        let token = format!("{}{}", API_TOKEN_PREFIX, generate_random_token(API_TOKEN_LENGTH));
+        let saved_token = self.api_token_repository.save(NewApiToken {
+            id: Uuid::new_v4(),
+            user_id: claims.user_id.to_string(),
+            provider: claims.provider.to_string(),
+            name: request.name.trim().to_string(),
+            token_hash: hash_token(&token),
+            scopes,
+            expires_at: Utc::now().naive_utc() +
+            chrono::Duration::days(request.expires_in_days as i64),
+        })?;
+        return Ok(CreatedApiTokenDTO {
+            id: saved_token.id,
+            name: Arc::from(saved_token.name.as_str()),
+            token: Arc::from(token.as_str()),
+            scopes: saved_token.scopes
+                .iter()
+                .map(|scope| Arc::from(scope.as_str()))
+                .collect(),
+            expires_at: saved_token.expires_at,
+            created_at: saved_token.created_at,
+        });
+    }
+
+    pub fn find_all(&self, user_id: &str) -> Result<Vec<ApiToken>, ApiTokenServiceError> {
+        return Ok(self.api_token_repository.find_by_user_id(user_id)?);
+    }
+
+    pub fn revoke(&self, user_id: &str, token_id: Uuid) -> Result<bool, ApiTokenServiceError> {
+        return Ok(self.api_token_repository.delete(user_id, token_id)? > 0);
+    }
+
+    /// Revokes the token itself, e.g. on behalf of an OAuth client. Returns `false` for
+    /// unknown tokens.
+    pub fn revoke_by_token(&self, token: &str) -> Result<bool, ApiTokenServiceError> {
+        return Ok(self.api_token_repository.delete_by_hash(&hash_token(token))? > 0);
+    }
+
+    /// Revokes every token of the user, e.g. after a password change, so tokens created
+    /// by someone who knew the old password stop working as well.
+    pub fn revoke_all(&self, provider: &str, user_id: &str) -> Result<usize, ApiTokenServiceError> {
+        return Ok(self.api_token_repository.delete_by_user_id(provider, user_id)?);
+    }
+
+    /// Resolves the token to claims. Scopes only grant roles the owner still has.
+    pub async fn authenticate(&self, token: &str) -> Result<Option<Claims>, ApiTokenServiceError> {
+        let api_token = match self.api_token_repository.find_by_hash(&hash_token(token))? {
+            Some(api_token) => api_token,
+            None => {
+                return Ok(None);
+            }
+        };
+        let now = Utc::now().naive_utc();
+        if api_token.expires_at <= now {
+            return Ok(None);
+        }
+        let user_id: Arc<str> = Arc::from(api_token.user_id.as_str());
+        let user = match self.providers.find_user(&api_token.provider, user_id.clone()).await? {
+            Some(user) => user,
+            None => {
+                return Ok(None);
+            }
+        };
+        let roles = self.providers.find_roles(&api_token.provider, &user).await?;
+        let is_stale = api_token.last_used_at.is_none_or(|last_used_at| {
+            return now - last_used_at >= chrono::Duration::seconds(LAST_USED_PRECISION);
+        });
+        if is_stale {
+            if let Err(e) = self.api_token_repository.touch(api_token.id, now) {
+                warn!("Failed to update last use of API token [{}]: {}", api_token.id, e);
+            }
+        }
+        return Ok(
+            Some(Claims {
+                user_id,
+                uuid: api_token.id,
+                exp: api_token.expires_at.and_utc().timestamp() as usize,
+                roles: roles
+                    .into_iter()
+                    .filter(|role| api_token.scopes.iter().any(|scope| scope == role.as_ref()))
+                    .collect(),
+                provider: Arc::from(api_token.provider.as_str()),
+                api_token_id: Some(api_token.id),
+                act: None,
+            })
+        );
+    }
+}
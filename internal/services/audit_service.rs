@@ -0,0 +1,42 @@
+use std::sync::Arc;
+
+use config::log::info;
+use uuid::Uuid;
+
+use crate::infra::{
+    database::audit_event_repository::{ AuditEventRepository, NewAuditEvent },
+    domain::audit::AuditEventDTO,
+};
+
+pub const IMPERSONATION_STARTED: &str = "impersonation_started";
+pub const IMPERSONATION_ENDED: &str = "impersonation_ended";
+
+/// Audit trail of sensitive actions, every event is also written to the log.
+pub struct AuditService {
+    audit_event_repository: Arc<AuditEventRepository>,
+}
+
+impl AuditService {
+    pub fn new(audit_event_repository: Arc<AuditEventRepository>) -> Arc<AuditService> {
+        return Arc::new(AuditService { audit_event_repository });
+    }
+
+    pub fn record(&self, event: AuditEventDTO) -> Result<(), diesel::result::Error> {
+        info!(
+            "Audit: [{}] by [{}] on [{}]",
+            event.action,
+            event.actor_id,
+            event.target_user_id.as_deref().unwrap_or("-")
+        );
+        self.audit_event_repository.save(NewAuditEvent {
+            id: Uuid::new_v4(),
+            actor_id: event.actor_id.to_string(),
+            action: event.action.to_string(),
+            target_user_id: event.target_user_id.map(|user_id| user_id.to_string()),
+            session_uuid: event.session_uuid,
+            ip: event.ip.map(|ip| ip.to_string()),
+            details: event.details,
+        })?;
+        return Ok(());
+    }
+}
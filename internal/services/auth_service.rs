@@ -1,30 +1,114 @@
 use core::error;
 use std::{ sync::Arc, time::{ Duration, SystemTime, UNIX_EPOCH } };
 
-use config::CONFIGURATION;
-use ldap3::{ Ldap, LdapError, SearchEntry };
+use chrono::{ NaiveDateTime, Utc };
+use config::{ log::warn, CONFIGURATION };
 use pwhash::bcrypt::{ self, BcryptSetup };
-use jsonwebtoken::{ EncodingKey, Header };
+use rand::{ distributions::Alphanumeric, Rng };
 use serde::{ Deserialize, Serialize };
+use sha2::{ Digest, Sha256 };
 use thiserror::Error;
 use uuid::Uuid;
 
 use crate::infra::{
-    database::{ session_repository::{ Session, SessionRepository }, user_repository::User },
-    domain::{ session::SessionDTO, user::AuthenticatedUserDTO },
-    http::{ requests::user_request::AuthRequest, resources::user_resource::UserResponse },
+    database::{
+        local_user_repository::{ LocalUserRepository, NewLocalUser },
+        login_challenge_repository::{ LoginChallengeRepository, NewLoginChallenge },
+        refresh_token_repository::{ NewRefreshToken, RefreshToken, RefreshTokenRepository },
+        session_repository::{ NewSession, Session, SessionRepository },
+        user_repository::User,
+    },
+    domain::{
+        session::{ SessionDTO, SessionMetadataDTO, TokenPairDTO },
+        totp::TotpChallengeDTO,
+        user::{ AuthenticatedUserDTO, LoginOutcomeDTO },
+    },
+    http::{
+        requests::user_request::{
+            AuthRequest,
+            PasswordChangeRequest,
+            TotpLoginRequest,
+            UserRequest,
+        },
+        resources::user_resource::UserResponse,
+    },
+    ldap::{
+        filter::normalize_identifier,
+        ldap_client::LdapClientError,
+        password_policy::PasswordChangeError,
+    },
+};
+
+use super::{
+    api_token_service::{ ApiTokenService, ApiTokenServiceError, API_TOKEN_PREFIX },
+    providers::{
+        ldap_provider::LDAP_PROVIDER,
+        local_provider::LOCAL_PROVIDER,
+        AuthenticationProviderError,
+        AuthenticationProviders,
+    },
+    jwt_key_service::JwtKeyService,
+    login_throttle_service::LoginThrottleService,
+    totp_service::{ TotpService, TotpServiceError },
 };
 
+const REFRESH_TOKEN_LENGTH: usize = 64;
+const CHALLENGE_TOKEN_LENGTH: usize = 64;
+/// Last activity of a session is written at most once per this many seconds.
+const LAST_SEEN_PRECISION: i64 = 60;
+
 #[derive(Serialize, Clone, Deserialize)]
 pub struct Claims {
     pub user_id: Arc<str>,
     pub uuid: Uuid,
     pub exp: usize,
+    #[serde(default)]
+    pub roles: Vec<Arc<str>>,
+    /// Provider which authenticated the user, tokens issued before providers existed are LDAP ones.
+    #[serde(default = "default_provider")]
+    pub provider: Arc<str>,
+    /// Set when the request was authenticated with an API token instead of a JWT.
+    #[serde(skip)]
+    pub api_token_id: Option<Uuid>,
+    /// Administrator acting as the user, only present in impersonation tokens.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub act: Option<ActorClaims>,
+}
+
+#[derive(Serialize, Clone, Deserialize)]
+pub struct ActorClaims {
+    pub user_id: Arc<str>,
+    pub provider: Arc<str>,
+}
+
+fn default_provider() -> Arc<str> {
+    return Arc::from(LDAP_PROVIDER);
+}
+
+impl Claims {
+    pub fn has_any_role(&self, roles: &[Arc<str>]) -> bool {
+        return self.roles.iter().any(|role| roles.contains(role));
+    }
+
+    /// The user who really sends the request, logs and audit records name this one.
+    pub fn actor_id(&self) -> Arc<str> {
+        return match &self.act {
+            Some(actor) => actor.user_id.clone(),
+            None => self.user_id.clone(),
+        };
+    }
 }
 
 pub struct AuthService {
-    ldap: Arc<tokio::sync::RwLock<Ldap>>,
+    providers: Arc<AuthenticationProviders>,
+    local_user_repository: Arc<LocalUserRepository>,
     session_repository: Arc<SessionRepository>,
+    refresh_token_repository: Arc<RefreshTokenRepository>,
+    login_challenge_repository: Arc<LoginChallengeRepository>,
+    totp_service: Arc<TotpService>,
+    login_throttle_service: Arc<LoginThrottleService>,
+    jwt_key_service: Arc<JwtKeyService>,
+    api_token_service: Arc<ApiTokenService>,
 }
 
 #[derive(Error, Debug)]
@@ -32,59 +116,265 @@ pub enum AuthServiceError {
     #[error("{0}")] DieselError(diesel::result::Error),
     #[error("{0}")] ArgonError(pwhash::error::Error),
     #[error("{0}")] JWTError(jsonwebtoken::errors::Error),
-    #[error("{0}")] LDAPError(LdapError),
+    #[error("{0}")] ProviderError(#[source] AuthenticationProviderError),
+    #[error("User [{0}] does not exist anymore")] UserNotFound(String),
+    #[error("{0}")] TotpError(#[source] TotpServiceError),
+    #[error("{0}")] ApiTokenError(#[source] ApiTokenServiceError),
+    #[error("Login challenge is not valid or expired")] InvalidChallenge,
+    #[error("Too many failed login attempts, try again later")] TooManyAttempts(Duration),
+    #[error("Code is not valid")] InvalidTotpCode,
+    #[error("Registration of local accounts is disabled")] RegistrationDisabled,
+    #[error("User with this email already exists")] UserAlreadyExists,
+    #[error("Current password is not valid")] InvalidCurrentPassword,
+    #[error("Password of this account can not be changed here")] PasswordChangeNotSupported,
+    #[error("{0}")] PasswordRejected(#[source] PasswordChangeError),
+    #[error("Refresh token is not valid")] InvalidRefreshToken,
+    #[error("Refresh token was already used, session was revoked")] RefreshTokenReused,
     #[error("{0}")] ServiceError(Box<dyn error::Error + Send + Sync + 'static>),
 }
 
+/// Storage used by `AuthService`, grouped to keep its constructor short.
+pub struct AuthRepositories {
+    pub local_user_repository: Arc<LocalUserRepository>,
+    pub session_repository: Arc<SessionRepository>,
+    pub refresh_token_repository: Arc<RefreshTokenRepository>,
+    pub login_challenge_repository: Arc<LoginChallengeRepository>,
+}
+
 impl AuthService {
     pub fn new(
-        ldap: Arc<tokio::sync::RwLock<Ldap>>,
-        session_repository: Arc<SessionRepository>
+        providers: Arc<AuthenticationProviders>,
+        repositories: AuthRepositories,
+        totp_service: Arc<TotpService>,
+        login_throttle_service: Arc<LoginThrottleService>,
+        jwt_key_service: Arc<JwtKeyService>,
+        api_token_service: Arc<ApiTokenService>
     ) -> Arc<AuthService> {
         return Arc::new(AuthService {
-            ldap,
-            session_repository,
+            providers,
+            local_user_repository: repositories.local_user_repository,
+            session_repository: repositories.session_repository,
+            refresh_token_repository: repositories.refresh_token_repository,
+            login_challenge_repository: repositories.login_challenge_repository,
+            totp_service,
+            login_throttle_service,
+            jwt_key_service,
+            api_token_service,
         });
     }
 
+    /// Checks the credentials. Users with two-factor authentication get a challenge token
+    /// which is exchanged for the session by `login_totp`. Failed attempts are throttled
+    /// per login and per client address, the failures are forgotten only once the user
+    /// passed every factor.
     pub async fn login(
         &self,
-        request_user: AuthRequest
+        request_user: AuthRequest,
+        metadata: SessionMetadataDTO
+    ) -> Result<LoginOutcomeDTO, AuthServiceError> {
+        let retry_after = self.login_throttle_service
+            .retry_after(&request_user.login, metadata.ip.as_deref())
+            .map_err(AuthServiceError::DieselError)?;
+        if let Some(retry_after) = retry_after {
+            return Err(AuthServiceError::TooManyAttempts(retry_after));
+        }
+        let authenticated = self.providers.authenticate(
+            &request_user.login,
+            &request_user.password
+        ).await;
+        let (provider, user) = match authenticated {
+            Ok(authenticated) => authenticated,
+            Err(e @ AuthenticationProviderError::InvalidCredentials) => {
+                self.login_throttle_service
+                    .record_failure(&request_user.login, metadata.ip.as_deref())
+                    .map_err(AuthServiceError::DieselError)?;
+                return Err(AuthServiceError::ProviderError(e));
+            }
+            Err(e) => {
+                return Err(AuthServiceError::ProviderError(e));
+            }
+        };
+        let is_totp_enabled = self.totp_service
+            .is_enabled(provider, &user.id)
+            .map_err(AuthServiceError::TotpError)?;
+        if is_totp_enabled {
+            let challenge_token = generate_random_token(CHALLENGE_TOKEN_LENGTH);
+            self.login_challenge_repository
+                .save(NewLoginChallenge {
+                    token_hash: hash_token(&challenge_token),
+                    user_id: user.id.to_string(),
+                    provider: provider.to_string(),
+                    expires_at: Utc::now().naive_utc() +
+                    chrono::Duration::seconds(CONFIGURATION.totp_challenge_ttl as i64),
+                    login: request_user.login.clone(),
+                })
+                .map_err(AuthServiceError::DieselError)?;
+            return Ok(
+                LoginOutcomeDTO::TotpRequired(TotpChallengeDTO {
+                    totp_required: true,
+                    challenge_token: Arc::from(challenge_token.as_str()),
+                    expires_in: CONFIGURATION.totp_challenge_ttl,
+                })
+            );
+        }
+        let authenticated_user = self.authenticated_user(
+            provider,
+            user,
+            &request_user.login,
+            metadata
+        ).await?;
+        return Ok(LoginOutcomeDTO::Authenticated(authenticated_user));
+    }
+
+    /// Second login step, exchanges the challenge token and a TOTP or recovery code for
+    /// the session. A challenge allows a limited number of attempts, and wrong codes
+    /// count as failed logins, so new challenges do not give an unlimited number of guesses.
+    pub async fn login_totp(
+        &self,
+        request: TotpLoginRequest,
+        metadata: SessionMetadataDTO
+    ) -> Result<AuthenticatedUserDTO, AuthServiceError> {
+        let hash = hash_token(&request.challenge_token);
+        let challenge = self.login_challenge_repository
+            .register_attempt(&hash, CONFIGURATION.totp_challenge_max_attempts)
+            .map_err(AuthServiceError::DieselError)?
+            .ok_or(AuthServiceError::InvalidChallenge)?;
+        let retry_after = self.login_throttle_service
+            .retry_after(&challenge.login, metadata.ip.as_deref())
+            .map_err(AuthServiceError::DieselError)?;
+        if let Some(retry_after) = retry_after {
+            return Err(AuthServiceError::TooManyAttempts(retry_after));
+        }
+        let is_valid = self.totp_service
+            .verify(&challenge.provider, &challenge.user_id, &request.code)
+            .map_err(AuthServiceError::TotpError)?;
+        if !is_valid {
+            self.login_throttle_service
+                .record_failure(&challenge.login, metadata.ip.as_deref())
+                .map_err(AuthServiceError::DieselError)?;
+            return Err(AuthServiceError::InvalidTotpCode);
+        }
+        self.login_challenge_repository.delete(&hash).map_err(AuthServiceError::DieselError)?;
+        let user = self.providers
+            .find_user(&challenge.provider, Arc::from(challenge.user_id.as_str())).await
+            .map_err(AuthServiceError::ProviderError)?
+            .ok_or_else(|| AuthServiceError::UserNotFound(challenge.user_id.clone()))?;
+        return self.authenticated_user(
+            &challenge.provider,
+            user,
+            &challenge.login,
+            metadata
+        ).await;
+    }
+
+    /// Creates the session of a user who passed every factor.
+    async fn authenticated_user(
+        &self,
+        provider: &str,
+        user: User,
+        login: &str,
+        metadata: SessionMetadataDTO
+    ) -> Result<AuthenticatedUserDTO, AuthServiceError> {
+        self.login_throttle_service.record_success(login).map_err(AuthServiceError::DieselError)?;
+        let roles = self.providers
+            .find_roles(provider, &user).await
+            .map_err(AuthServiceError::ProviderError)?;
+        let tokens = self.create_session(user.id.clone(), provider, roles, metadata)?;
+        return Ok(AuthenticatedUserDTO {
+            user: UserResponse::user_to_response(&user),
+            token: tokens.token,
+            refresh_token: tokens.refresh_token,
+        });
+    }
+
+    /// Creates a local account identified by the email and logs it in.
+    pub async fn register_local(
+        &self,
+        request_user: UserRequest,
+        metadata: SessionMetadataDTO
     ) -> Result<AuthenticatedUserDTO, AuthServiceError> {
-        let result = self.ldap
-            .write().await
-            .search(
-                &format!("cn={},{}", request_user.email, CONFIGURATION.ldap_auth_base_dn),
-                ldap3::Scope::Subtree,
-                "(objectClass=inetOrgPerson)",
-                vec!["dn", "cn", "sn", "uid"]
-            ).await
-            .map_err(AuthServiceError::LDAPError)?;
-        let (entries, _) = result.success().map_err(AuthServiceError::LDAPError)?;
-        if entries.is_empty() {
-            return Err(AuthServiceError::ServiceError(Box::from("There is no one user was found")));
-        }
-        if entries.len() > 1 {
-            return Err(AuthServiceError::ServiceError(Box::from("Multiply users was found")));
-        }
-        let user_dn = SearchEntry::construct(entries[0].clone());
-        let bind_result = self.ldap
-            .write().await
-            .simple_bind(&user_dn.dn, &request_user.password).await
-            .map_err(AuthServiceError::LDAPError)?;
-        if bind_result.success().is_ok() {
-            let user = User {
-                cn: Arc::from(user_dn.attrs.get("cn").unwrap().get(0).unwrap().as_str()),
-                uid: Arc::from(user_dn.attrs.get("uid").unwrap().get(0).unwrap().as_str()),
-                sn: Arc::from(user_dn.attrs.get("sn").unwrap().get(0).unwrap().to_owned()),
-            };
-            let token = self.generate_jwt(user.cn.clone())?;
-            return Ok(AuthenticatedUserDTO {
-                user: UserResponse::user_to_response(&user),
-                token: token,
-            });
-        }
-        return Err(AuthServiceError::ServiceError(Box::from("Auth error")));
+        let is_enabled =
+            CONFIGURATION.local_registration_enabled && self.providers.get(LOCAL_PROVIDER).is_ok();
+        if !is_enabled {
+            return Err(AuthServiceError::RegistrationDisabled);
+        }
+        let user_id = normalize_identifier(&request_user.email);
+        // Ids are shared between providers, a directory user must not be shadowed. The
+        // directory is checked by its login attribute as well as by the email address.
+        let is_taken = self.providers
+            .is_taken(&user_id, &request_user.email).await
+            .map_err(AuthServiceError::ProviderError)?;
+        if is_taken {
+            return Err(AuthServiceError::UserAlreadyExists);
+        }
+        let password = hash_user_password(&request_user.password).map_err(
+            AuthServiceError::ArgonError
+        )?;
+        let saved_user = self.local_user_repository
+            .save(NewLocalUser {
+                id: user_id,
+                name: request_user.name,
+                email: request_user.email,
+                password,
+            })
+            .map_err(|e| {
+                return match e {
+                    diesel::result::Error::DatabaseError(
+                        diesel::result::DatabaseErrorKind::UniqueViolation,
+                        _,
+                    ) => AuthServiceError::UserAlreadyExists,
+                    e => AuthServiceError::DieselError(e),
+                };
+            })?;
+        let user = saved_user.to_user();
+        let tokens = self.create_session(user.id.clone(), LOCAL_PROVIDER, Vec::new(), metadata)?;
+        return Ok(AuthenticatedUserDTO {
+            user: UserResponse::user_to_response(&user),
+            token: tokens.token,
+            refresh_token: tokens.refresh_token,
+        });
+    }
+
+    /// Changes the password with the provider of the session, LDAP passwords are written
+    /// back to the directory. All other sessions and all API tokens of the user are revoked.
+    pub async fn change_password(
+        &self,
+        claims: &Claims,
+        request: PasswordChangeRequest
+    ) -> Result<(), AuthServiceError> {
+        // Only the user in their own session, not an API token or an impersonating admin.
+        if claims.api_token_id.is_some() || claims.act.is_some() {
+            return Err(AuthServiceError::PasswordChangeNotSupported);
+        }
+        let user = self.providers
+            .find_user(&claims.provider, claims.user_id.clone()).await
+            .map_err(AuthServiceError::ProviderError)?
+            .ok_or_else(|| AuthServiceError::UserNotFound(claims.user_id.to_string()))?;
+        let changed = self.providers.change_password(
+            &claims.provider,
+            &user,
+            &request.current_password,
+            &request.new_password
+        ).await;
+        match changed {
+            Ok(_) => {}
+            Err(AuthenticationProviderError::InvalidCredentials) => {
+                return Err(AuthServiceError::InvalidCurrentPassword);
+            }
+            Err(AuthenticationProviderError::LDAPError(LdapClientError::PasswordChange(e))) => {
+                return Err(AuthServiceError::PasswordRejected(e));
+            }
+            Err(e) => {
+                return Err(AuthServiceError::ProviderError(e));
+            }
+        }
+        self.session_repository
+            .delete_others(SessionDTO::new(claims.user_id.clone(), claims.uuid))
+            .map_err(AuthServiceError::DieselError)?;
+        self.api_token_service
+            .revoke_all(&claims.provider, &claims.user_id)
+            .map_err(AuthServiceError::ApiTokenError)?;
+        return Ok(());
     }
 
     pub fn logout(&self, session: SessionDTO) -> Result<(), AuthServiceError> {
@@ -92,44 +382,240 @@ impl AuthService {
         return Ok(());
     }
 
+    pub fn decode_claims(&self, token: &str) -> Result<Claims, AuthServiceError> {
+        return self.jwt_key_service.decode::<Claims>(token).map_err(AuthServiceError::JWTError);
+    }
+
+    /// Resolves a bearer token, either an API token or a JWT of a live session.
+    pub async fn authenticate(&self, token: &str) -> Result<Option<Claims>, AuthServiceError> {
+        if token.starts_with(API_TOKEN_PREFIX) {
+            return self.api_token_service
+                .authenticate(token).await
+                .map_err(AuthServiceError::ApiTokenError);
+        }
+        let claims = match self.decode_claims(token) {
+            Ok(claims) => claims,
+            Err(_) => {
+                return Ok(None);
+            }
+        };
+        if !self.check(Arc::new(claims.clone())).await {
+            return Ok(None);
+        }
+        return Ok(Some(claims));
+    }
+
+    /// Checks that the session exists and is not expired, and records the activity.
     pub async fn check(&self, session: Arc<Claims>) -> bool {
-        let res = self.session_repository.exists(SessionDTO {
-            user_id: session.user_id.clone(),
-            uuid: session.uuid,
-        });
-        if res.is_ok() {
-            return res.unwrap();
+        let saved_session = match
+            self.session_repository.find(SessionDTO::new(session.user_id.clone(), session.uuid))
+        {
+            Ok(Some(saved_session)) => saved_session,
+            _ => {
+                return false;
+            }
+        };
+        let now = Utc::now().naive_utc();
+        if saved_session.expires_at <= now {
+            return false;
+        }
+        if now - saved_session.last_seen_at >= chrono::Duration::seconds(LAST_SEEN_PRECISION) {
+            let touched = self.session_repository.touch(
+                SessionDTO::new(session.user_id.clone(), session.uuid),
+                now
+            );
+            if let Err(e) = touched {
+                warn!("Failed to update last activity of session [{}]: {}", saved_session.uuid, e);
+            }
+        }
+        return true;
+    }
+
+    pub fn find_sessions(&self, user_id: &str) -> Result<Vec<Session>, AuthServiceError> {
+        return self.session_repository
+            .find_by_user_id(&normalize_identifier(user_id))
+            .map_err(AuthServiceError::DieselError);
+    }
+
+    /// Revokes one session of the user, returns `false` when the user has no such session.
+    pub fn revoke_session(&self, user_id: Arc<str>, uuid: Uuid) -> Result<bool, AuthServiceError> {
+        let deleted = self.session_repository
+            .delete(SessionDTO::new(user_id, uuid))
+            .map_err(AuthServiceError::DieselError)?;
+        return Ok(deleted > 0);
+    }
+
+    /// Ends every session of the caller, optionally except the one the request was made with.
+    pub fn logout_all(
+        &self,
+        claims: &Claims,
+        keep_current: bool
+    ) -> Result<usize, AuthServiceError> {
+        let session = SessionDTO::new(claims.user_id.clone(), claims.uuid);
+        let result = match keep_current {
+            true => self.session_repository.delete_others(session),
+            false => self.session_repository.delete_by_user_id(session.user_id.to_string()),
+        };
+        return result.map_err(AuthServiceError::DieselError);
+    }
+
+    /// Ends every session of the given user, used by administrators.
+    pub fn revoke_user_sessions(&self, user_id: &str) -> Result<usize, AuthServiceError> {
+        return self.session_repository
+            .delete_by_user_id(normalize_identifier(user_id))
+            .map_err(AuthServiceError::DieselError);
+    }
+
+    /// Exchanges a refresh token for a new token pair. Every refresh token can be used
+    /// only once, presenting an already used one revokes the whole session with all
+    /// tokens issued for it. The token is consumed only after the session and the user
+    /// were resolved, so a failing provider does not burn it and the client can retry.
+    pub async fn refresh(&self, refresh_token: &str) -> Result<TokenPairDTO, AuthServiceError> {
+        let hash = hash_token(refresh_token);
+        let token = self.refresh_token_repository
+            .find_by_hash(&hash)
+            .map_err(AuthServiceError::DieselError)?
+            .ok_or(AuthServiceError::InvalidRefreshToken)?;
+        if token.used {
+            return Err(self.revoke_reused_session(&token));
         }
-        return false;
+        if token.expires_at < Utc::now().naive_utc() {
+            return Err(AuthServiceError::InvalidRefreshToken);
+        }
+        let session = SessionDTO::new(Arc::from(token.user_id.as_str()), token.session_uuid);
+        let saved_session = self.session_repository
+            .find(SessionDTO::new(session.user_id.clone(), session.uuid))
+            .map_err(AuthServiceError::DieselError)?
+            .ok_or(AuthServiceError::InvalidRefreshToken)?;
+        if saved_session.expires_at <= Utc::now().naive_utc() {
+            return Err(AuthServiceError::InvalidRefreshToken);
+        }
+        let provider = saved_session.provider;
+        // Roles are resolved again, so membership changes apply on the next refresh.
+        let user = self.providers
+            .find_user(&provider, session.user_id.clone()).await
+            .map_err(AuthServiceError::ProviderError)?
+            .ok_or_else(|| AuthServiceError::UserNotFound(token.user_id.clone()))?;
+        let roles = self.providers
+            .find_roles(&provider, &user).await
+            .map_err(AuthServiceError::ProviderError)?;
+        let consumed = self.refresh_token_repository
+            .consume(&hash)
+            .map_err(AuthServiceError::DieselError)?;
+        if consumed.is_none() {
+            // A concurrent request used the token in the meantime.
+            return Err(self.revoke_reused_session(&token));
+        }
+        self.session_repository
+            .extend(SessionDTO::new(session.user_id.clone(), session.uuid), session_expires_at())
+            .map_err(AuthServiceError::DieselError)?;
+        return self.issue_tokens(session, &provider, roles);
+    }
+
+    /// Revokes the session of a refresh token which was presented a second time.
+    fn revoke_reused_session(&self, token: &RefreshToken) -> AuthServiceError {
+        warn!(
+            "Refresh token reuse detected for user [{}], revoking session [{}]",
+            token.user_id,
+            token.session_uuid
+        );
+        let deleted = self.session_repository.delete(SessionDTO {
+            user_id: Arc::from(token.user_id.as_str()),
+            uuid: token.session_uuid,
+        });
+        return match deleted {
+            Ok(_) => AuthServiceError::RefreshTokenReused,
+            Err(e) => AuthServiceError::DieselError(e),
+        };
     }
 
-    fn generate_jwt(&self, user_id: Arc<str>) -> Result<Arc<str>, AuthServiceError> {
-        let session = SessionDTO { user_id, uuid: Uuid::new_v4() };
+    fn create_session(
+        &self,
+        user_id: Arc<str>,
+        provider: &str,
+        roles: Vec<Arc<str>>,
+        metadata: SessionMetadataDTO
+    ) -> Result<TokenPairDTO, AuthServiceError> {
+        let now = Utc::now().naive_utc();
         let saved_session: Session = self.session_repository
-            .save(session)
+            .save(NewSession {
+                user_id: user_id.to_string(),
+                uuid: Uuid::new_v4(),
+                provider: provider.to_string(),
+                created_at: now,
+                expires_at: session_expires_at(),
+                last_seen_at: now,
+                ip: metadata.ip.map(|ip| ip.to_string()),
+                user_agent: metadata.user_agent.map(|user_agent| user_agent.to_string()),
+            })
+            .map_err(AuthServiceError::DieselError)?;
+        return self.issue_tokens(
+            SessionDTO::new(Arc::from(saved_session.user_id.as_str()), saved_session.uuid),
+            &saved_session.provider,
+            roles
+        );
+    }
+
+    fn issue_tokens(
+        &self,
+        session: SessionDTO,
+        provider: &str,
+        roles: Vec<Arc<str>>
+    ) -> Result<TokenPairDTO, AuthServiceError> {
+        let refresh_token = generate_random_token(REFRESH_TOKEN_LENGTH);
+        self.refresh_token_repository
+            .save(NewRefreshToken {
+                token_hash: hash_token(&refresh_token),
+                user_id: session.user_id.to_string(),
+                session_uuid: session.uuid,
+                expires_at: Utc::now().naive_utc() +
+                chrono::Duration::seconds(CONFIGURATION.refresh_token_ttl as i64),
+            })
             .map_err(AuthServiceError::DieselError)?;
+        let token = self.generate_jwt(session, provider, roles)?;
+        return Ok(TokenPairDTO { token, refresh_token: Arc::from(refresh_token.as_str()) });
+    }
+
+    fn generate_jwt(
+        &self,
+        session: SessionDTO,
+        provider: &str,
+        roles: Vec<Arc<str>>
+    ) -> Result<Arc<str>, AuthServiceError> {
         let claims = Claims {
-            user_id: Arc::from(saved_session.user_id.as_str()),
-            uuid: saved_session.uuid,
+            user_id: session.user_id,
+            uuid: session.uuid,
             exp: (SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as usize) +
             (Duration::from_secs(CONFIGURATION.jwt_ttl).as_secs() as usize),
+            roles,
+            provider: Arc::from(provider),
+            api_token_id: None,
+            act: None,
         };
-        let token = jsonwebtoken
-            ::encode(
-                &Header::default(),
-                &claims,
-                &EncodingKey::from_secret(CONFIGURATION.jwt_secret.as_ref())
-            )
-            .map_err(AuthServiceError::JWTError)?;
+        let token = self.jwt_key_service.encode(&claims).map_err(AuthServiceError::JWTError)?;
         return Ok(Arc::from(token.as_str()));
     }
 }
 
+/// Sessions live as long as their refresh tokens, every refresh extends them.
+fn session_expires_at() -> NaiveDateTime {
+    let ttl = chrono::Duration::seconds(CONFIGURATION.refresh_token_ttl as i64);
+    return Utc::now().naive_utc() + ttl;
+}
+
+pub fn generate_random_token(length: usize) -> String {
+    return rand::thread_rng().sample_iter(&Alphanumeric).take(length).map(char::from).collect();
+}
+
+pub fn hash_token(token: &str) -> String {
+    return hex::encode(Sha256::digest(token.as_bytes()));
+}
+
 pub fn hash_user_password(password: &str) -> Result<String, pwhash::error::Error> {
     return bcrypt::hash_with(
         BcryptSetup {
             variant: Some(bcrypt::BcryptVariant::V2b),
-            cost: Some(5),
+            cost: Some(CONFIGURATION.bcrypt_cost),
             ..Default::default()
         },
         password
@@ -0,0 +1,143 @@
+use std::sync::Arc;
+
+use chrono::Utc;
+use config::{ log::info, CONFIGURATION };
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::infra::{
+    database::session_repository::{ NewSession, SessionRepository },
+    domain::{
+        audit::AuditEventDTO,
+        session::{ SessionDTO, SessionMetadataDTO },
+        user::ImpersonationDTO,
+    },
+    http::resources::user_resource::UserResponse,
+};
+
+use super::{
+    audit_service::{ AuditService, IMPERSONATION_ENDED, IMPERSONATION_STARTED },
+    auth_service::{ ActorClaims, Claims },
+    jwt_key_service::JwtKeyService,
+    providers::{ AuthenticationProviderError, AuthenticationProviders },
+};
+
+#[derive(Error, Debug)]
+pub enum ImpersonationServiceError {
+    #[error("{0}")] DieselError(#[from] diesel::result::Error),
+    #[error("{0}")] ProviderError(#[from] AuthenticationProviderError),
+    #[error("{0}")] JWTError(#[from] jsonwebtoken::errors::Error),
+    #[error("User [{0}] does not exist")] UserNotFound(String),
+    #[error("Impersonation can only be started in your own session")] NotAllowed,
+    #[error("Users with the admin role can not be impersonated")] AdminTarget,
+    #[error("You can not impersonate yourself")] SelfTarget,
+    #[error("This session is not an impersonation")] NotImpersonating,
+}
+
+/// Lets administrators act as another user. The token carries the administrator in the
+/// `act` claim, its session expires after IMPERSONATION_TTL and is never refreshed.
+pub struct ImpersonationService {
+    providers: Arc<AuthenticationProviders>,
+    session_repository: Arc<SessionRepository>,
+    jwt_key_service: Arc<JwtKeyService>,
+    audit_service: Arc<AuditService>,
+}
+
+impl ImpersonationService {
+    pub fn new(
+        providers: Arc<AuthenticationProviders>,
+        session_repository: Arc<SessionRepository>,
+        jwt_key_service: Arc<JwtKeyService>,
+        audit_service: Arc<AuditService>
+    ) -> Arc<ImpersonationService> {
+        return Arc::new(ImpersonationService {
+            providers,
+            session_repository,
+            jwt_key_service,
+            audit_service,
+        });
+    }
+
+    pub async fn start(
+        &self,
+        actor: &Claims,
+        user_id: Arc<str>,
+        metadata: SessionMetadataDTO
+    ) -> Result<ImpersonationDTO, ImpersonationServiceError> {
+        if actor.act.is_some() || actor.api_token_id.is_some() {
+            return Err(ImpersonationServiceError::NotAllowed);
+        }
+        if actor.user_id == user_id {
+            return Err(ImpersonationServiceError::SelfTarget);
+        }
+        let (user, provider) = match
+            self.providers.find_any_user_with_provider(user_id.clone()).await?
+        {
+            Some(found) => found,
+            None => {
+                return Err(ImpersonationServiceError::UserNotFound(user_id.to_string()));
+            }
+        };
+        let roles = self.providers.find_roles(provider, &user).await?;
+        if roles.iter().any(|role| role.as_ref() == CONFIGURATION.admin_role) {
+            return Err(ImpersonationServiceError::AdminTarget);
+        }
+        let now = Utc::now().naive_utc();
+        let expires_at = now + chrono::Duration::seconds(CONFIGURATION.impersonation_ttl as i64);
+        let session_uuid = Uuid::new_v4();
+        // Nothing is issued unless the audit record was written.
+        self.audit_service.record(AuditEventDTO {
+            target_user_id: Some(user.id.clone()),
+            ip: metadata.ip.clone(),
+            details: Some(format!("session {}", session_uuid)),
+            ..AuditEventDTO::new(actor, IMPERSONATION_STARTED)
+        })?;
+        self.session_repository.save(NewSession {
+            user_id: user.id.to_string(),
+            uuid: session_uuid,
+            provider: provider.to_string(),
+            created_at: now,
+            expires_at,
+            last_seen_at: now,
+            ip: metadata.ip.map(|ip| ip.to_string()),
+            user_agent: metadata.user_agent.map(|user_agent| user_agent.to_string()),
+        })?;
+        let claims = Claims {
+            user_id: user.id.clone(),
+            uuid: session_uuid,
+            exp: expires_at.and_utc().timestamp() as usize,
+            roles,
+            provider: Arc::from(provider),
+            api_token_id: None,
+            act: Some(ActorClaims {
+                user_id: actor.user_id.clone(),
+                provider: actor.provider.clone(),
+            }),
+        };
+        let token = self.jwt_key_service.encode(&claims)?;
+        return Ok(ImpersonationDTO {
+            user: UserResponse::user_to_response(&user),
+            token: Arc::from(token.as_str()),
+            expires_at,
+        });
+    }
+
+    /// Ends the impersonation session, the token stops working right away.
+    pub fn end(
+        &self,
+        claims: &Claims,
+        metadata: SessionMetadataDTO
+    ) -> Result<(), ImpersonationServiceError> {
+        if claims.act.is_none() {
+            return Err(ImpersonationServiceError::NotImpersonating);
+        }
+        self.session_repository.delete(SessionDTO::new(claims.user_id.clone(), claims.uuid))?;
+        self.audit_service.record(AuditEventDTO {
+            target_user_id: Some(claims.user_id.clone()),
+            ip: metadata.ip,
+            ..AuditEventDTO::new(claims, IMPERSONATION_ENDED)
+        })?;
+        info!("Impersonation of [{}] by [{}] ended", claims.user_id, claims.actor_id());
+        return Ok(());
+    }
+}
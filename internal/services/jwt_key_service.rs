@@ -0,0 +1,327 @@
+use std::{ fs, str::FromStr, sync::{ Arc, RwLock }, time::Duration };
+
+use base64::{ engine::general_purpose::URL_SAFE_NO_PAD, Engine };
+use chrono::{ NaiveDateTime, Utc };
+use config::{ log::{ info, warn }, CONFIGURATION };
+use ed25519_dalek::SigningKey;
+use jsonwebtoken::{
+    decode,
+    decode_header,
+    encode,
+    errors::ErrorKind,
+    jwk::{
+        AlgorithmParameters,
+        CommonParameters,
+        EllipticCurve,
+        EllipticCurveKeyParameters,
+        EllipticCurveKeyType,
+        Jwk,
+        JwkSet,
+        OctetKeyPairParameters,
+        OctetKeyPairType,
+        PublicKeyUse,
+        RSAKeyParameters,
+        RSAKeyType,
+    },
+    Algorithm,
+    DecodingKey,
+    EncodingKey,
+    Header,
+    Validation,
+};
+use p256::{ elliptic_curve::sec1::ToEncodedPoint, pkcs8::DecodePrivateKey };
+use rsa::{ pkcs1::DecodeRsaPrivateKey, traits::PublicKeyParts, RsaPrivateKey };
+use serde::{ de::DeserializeOwned, Serialize };
+use sha2::{ Digest, Sha256 };
+use thiserror::Error;
+
+const DEFAULT_JWT_SECRET: &str = "1234567890";
+const SECRET_KEY_ID_LENGTH: usize = 16;
+/// Allowed clock skew of the `exp` claim.
+const TOKEN_LEEWAY: u64 = 60;
+
+#[derive(Error, Debug)]
+pub enum JwtKeyError {
+    #[error("Error in reading JWT signing key: {0}")] IoError(#[from] std::io::Error),
+    #[error("Error in parsing JWT signing key: {0}")] InvalidKey(String),
+    #[error("JWT_PRIVATE_KEY_PATH is required for the [{0}] algorithm")] MissingPrivateKey(String),
+    #[error("{0}")] JWTError(#[from] jsonwebtoken::errors::Error),
+}
+
+/// Key which signs and verifies access tokens, identified by the `kid` header.
+pub struct JwtKey {
+    pub kid: String,
+    pub algorithm: Algorithm,
+    encoding_key: EncodingKey,
+    decoding_key: DecodingKey,
+    /// Public part of the key for the JWKS, shared secrets are never published.
+    jwk: Option<Jwk>,
+}
+
+impl JwtKey {
+    /// HMAC key, the key id defaults to a truncated hash of the secret.
+    pub fn from_secret(algorithm: Algorithm, secret: &str, kid: &str) -> JwtKey {
+        let kid = match kid.is_empty() {
+            true => {
+                let mut kid = URL_SAFE_NO_PAD.encode(Sha256::digest(secret.as_bytes()));
+                kid.truncate(SECRET_KEY_ID_LENGTH);
+                kid
+            }
+            false => kid.to_string(),
+        };
+        return JwtKey {
+            kid,
+            algorithm,
+            encoding_key: EncodingKey::from_secret(secret.as_bytes()),
+            decoding_key: DecodingKey::from_secret(secret.as_bytes()),
+            jwk: None,
+        };
+    }
+
+    /// Asymmetric key from a PEM private key, the key id defaults to the RFC 7638 thumbprint.
+    pub fn from_pem(algorithm: Algorithm, pem: &str, kid: &str) -> Result<JwtKey, JwtKeyError> {
+        let (encoding_key, parameters) = match algorithm {
+            Algorithm::RS256 => (EncodingKey::from_rsa_pem(pem.as_bytes())?, rsa_parameters(pem)?),
+            Algorithm::ES256 => (EncodingKey::from_ec_pem(pem.as_bytes())?, ec_parameters(pem)?),
+            Algorithm::EdDSA => (EncodingKey::from_ed_pem(pem.as_bytes())?, ed_parameters(pem)?),
+            _ => {
+                return Err(JwtKeyError::InvalidKey(format!("{:?} is not supported", algorithm)));
+            }
+        };
+        let decoding_key = match &parameters {
+            AlgorithmParameters::RSA(key) => DecodingKey::from_rsa_components(&key.n, &key.e)?,
+            AlgorithmParameters::EllipticCurve(key) => DecodingKey::from_ec_components(
+                &key.x,
+                &key.y
+            )?,
+            AlgorithmParameters::OctetKeyPair(key) => DecodingKey::from_ed_components(&key.x)?,
+            AlgorithmParameters::OctetKey(_) => unreachable!("Shared secrets are not PEM keys"),
+        };
+        let kid = match kid.is_empty() {
+            true => thumbprint(&parameters),
+            false => kid.to_string(),
+        };
+        let jwk = Jwk {
+            common: CommonParameters {
+                public_key_use: Some(PublicKeyUse::Signature),
+                algorithm: Some(algorithm),
+                key_id: Some(kid.clone()),
+                ..Default::default()
+            },
+            algorithm: parameters,
+        };
+        return Ok(JwtKey { kid, algorithm, encoding_key, decoding_key, jwk: Some(jwk) });
+    }
+
+    /// Retired keys are given without an algorithm, it follows from the key type.
+    pub fn from_any_pem(pem: &str) -> Result<JwtKey, JwtKeyError> {
+        if rsa_parameters(pem).is_ok() {
+            return JwtKey::from_pem(Algorithm::RS256, pem, "");
+        }
+        if p256::SecretKey::from_pkcs8_pem(pem).is_ok() {
+            return JwtKey::from_pem(Algorithm::ES256, pem, "");
+        }
+        return JwtKey::from_pem(Algorithm::EdDSA, pem, "");
+    }
+
+    /// Loads the key selected by JWT_ALGORITHM.
+    pub fn from_configuration() -> Result<JwtKey, JwtKeyError> {
+        let algorithm = Algorithm::from_str(&CONFIGURATION.jwt_algorithm)?;
+        if algorithm == Algorithm::HS256 {
+            if CONFIGURATION.jwt_secret == DEFAULT_JWT_SECRET {
+                warn!("JWT_SECRET has the default value, tokens can be forged by anyone");
+            }
+            return Ok(
+                JwtKey::from_secret(algorithm, &CONFIGURATION.jwt_secret, &CONFIGURATION.jwt_key_id)
+            );
+        }
+        if CONFIGURATION.jwt_private_key_path.is_empty() {
+            return Err(JwtKeyError::MissingPrivateKey(CONFIGURATION.jwt_algorithm.clone()));
+        }
+        let pem = fs::read_to_string(&CONFIGURATION.jwt_private_key_path)?;
+        return JwtKey::from_pem(algorithm, &pem, &CONFIGURATION.jwt_key_id);
+    }
+}
+
+/// Key which no longer signs but still verifies the tokens it signed until they expire.
+struct RetiredKey {
+    key: Arc<JwtKey>,
+    retired_until: NaiveDateTime,
+}
+
+struct KeyRing {
+    signing_key: Arc<JwtKey>,
+    retired_keys: Vec<RetiredKey>,
+}
+
+impl KeyRing {
+    fn find(&self, kid: &str, now: NaiveDateTime) -> Option<Arc<JwtKey>> {
+        if self.signing_key.kid == kid {
+            return Some(Arc::clone(&self.signing_key));
+        }
+        return self.retired_keys
+            .iter()
+            .find(|retired| retired.key.kid == kid && retired.retired_until > now)
+            .map(|retired| Arc::clone(&retired.key));
+    }
+
+    fn has_expired_keys(&self, now: NaiveDateTime) -> bool {
+        return self.retired_keys.iter().any(|retired| retired.retired_until <= now);
+    }
+
+    fn drop_expired_keys(&mut self, now: NaiveDateTime) {
+        self.retired_keys.retain(|retired| {
+            let is_active = retired.retired_until > now;
+            if !is_active {
+                info!("Dropped retired JWT signing key [{}]", retired.key.kid);
+            }
+            return is_active;
+        });
+    }
+}
+
+/// Key ring, one key signs and retired keys keep verifying until every token they
+/// signed has expired. Keys are selected by the `kid` header.
+pub struct JwtKeyService {
+    ring: RwLock<KeyRing>,
+}
+
+impl JwtKeyService {
+    /// The configured key signs, keys from JWT_RETIRED_SECRETS and JWT_RETIRED_KEY_PATHS
+    /// verify tokens issued before the restart.
+    pub fn new() -> Result<Arc<JwtKeyService>, JwtKeyError> {
+        let signing_key = Arc::new(JwtKey::from_configuration()?);
+        let retired_until = retired_until();
+        let mut retired_keys: Vec<RetiredKey> = Vec::new();
+        for secret in CONFIGURATION.jwt_retired_secrets.iter() {
+            retired_keys.push(RetiredKey {
+                key: Arc::new(JwtKey::from_secret(Algorithm::HS256, secret, "")),
+                retired_until,
+            });
+        }
+        for path in CONFIGURATION.jwt_retired_key_paths.iter() {
+            retired_keys.push(RetiredKey {
+                key: Arc::new(JwtKey::from_any_pem(&fs::read_to_string(path)?)?),
+                retired_until,
+            });
+        }
+        retired_keys.retain(|retired| retired.key.kid != signing_key.kid);
+        let ring = KeyRing { signing_key, retired_keys };
+        return Ok(Arc::new(JwtKeyService { ring: RwLock::new(ring) }));
+    }
+
+    pub fn encode<T: Serialize>(&self, claims: &T) -> Result<String, jsonwebtoken::errors::Error> {
+        let signing_key = Arc::clone(&self.ring.read().unwrap().signing_key);
+        let mut header = Header::new(signing_key.algorithm);
+        header.kid = Some(signing_key.kid.clone());
+        return encode(&header, claims, &signing_key.encoding_key);
+    }
+
+    /// Verifies the token with the key named by its `kid`. Tokens without a `kid` were
+    /// issued before key ids existed and are checked against the signing key.
+    pub fn decode<T: DeserializeOwned>(
+        &self,
+        token: &str
+    ) -> Result<T, jsonwebtoken::errors::Error> {
+        let header = decode_header(token)?;
+        let now = Utc::now().naive_utc();
+        self.drop_expired_keys(now);
+        let key = {
+            let ring = self.ring.read().unwrap();
+            match header.kid {
+                Some(kid) => ring.find(&kid, now),
+                None => Some(Arc::clone(&ring.signing_key)),
+            }
+        };
+        let key = key.ok_or(ErrorKind::InvalidToken)?;
+        let mut validation = Validation::new(key.algorithm);
+        validation.leeway = TOKEN_LEEWAY;
+        let token_data = decode::<T>(token, &key.decoding_key, &validation)?;
+        return Ok(token_data.claims);
+    }
+
+    /// Public keys which verify the issued tokens, the signing key first.
+    pub fn jwks(&self) -> JwkSet {
+        self.drop_expired_keys(Utc::now().naive_utc());
+        let ring = self.ring.read().unwrap();
+        let keys = std::iter
+            ::once(&ring.signing_key)
+            .chain(ring.retired_keys.iter().map(|retired| &retired.key))
+            .filter_map(|key| key.jwk.clone())
+            .collect();
+        return JwkSet { keys };
+    }
+
+    fn drop_expired_keys(&self, now: NaiveDateTime) {
+        if self.ring.read().unwrap().has_expired_keys(now) {
+            self.ring.write().unwrap().drop_expired_keys(now);
+        }
+    }
+}
+
+/// A retired key verifies until the last token it signed has expired, impersonation
+/// tokens may outlive regular access tokens.
+fn retired_until() -> NaiveDateTime {
+    let ttl = CONFIGURATION.jwt_ttl.max(CONFIGURATION.impersonation_ttl);
+    let lifetime = Duration::from_secs(ttl + TOKEN_LEEWAY);
+    return Utc::now().naive_utc() + lifetime;
+}
+
+fn rsa_parameters(pem: &str) -> Result<AlgorithmParameters, JwtKeyError> {
+    let key = RsaPrivateKey::from_pkcs8_pem(pem)
+        .or_else(|_| RsaPrivateKey::from_pkcs1_pem(pem))
+        .map_err(|e| JwtKeyError::InvalidKey(e.to_string()))?;
+    return Ok(
+        AlgorithmParameters::RSA(RSAKeyParameters {
+            key_type: RSAKeyType::RSA,
+            n: URL_SAFE_NO_PAD.encode(key.n().to_bytes_be()),
+            e: URL_SAFE_NO_PAD.encode(key.e().to_bytes_be()),
+        })
+    );
+}
+
+fn ec_parameters(pem: &str) -> Result<AlgorithmParameters, JwtKeyError> {
+    let key = p256::SecretKey
+        ::from_pkcs8_pem(pem)
+        .map_err(|e| JwtKeyError::InvalidKey(e.to_string()))?;
+    let point = key.public_key().to_encoded_point(false);
+    let (x, y) = match (point.x(), point.y()) {
+        (Some(x), Some(y)) => (x, y),
+        _ => {
+            return Err(JwtKeyError::InvalidKey(String::from("EC public key is not valid")));
+        }
+    };
+    return Ok(
+        AlgorithmParameters::EllipticCurve(EllipticCurveKeyParameters {
+            key_type: EllipticCurveKeyType::EC,
+            curve: EllipticCurve::P256,
+            x: URL_SAFE_NO_PAD.encode(x),
+            y: URL_SAFE_NO_PAD.encode(y),
+        })
+    );
+}
+
+fn ed_parameters(pem: &str) -> Result<AlgorithmParameters, JwtKeyError> {
+    let key = SigningKey::from_pkcs8_pem(pem).map_err(|e| JwtKeyError::InvalidKey(e.to_string()))?;
+    return Ok(
+        AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+            key_type: OctetKeyPairType::OctetKeyPair,
+            curve: EllipticCurve::Ed25519,
+            x: URL_SAFE_NO_PAD.encode(key.verifying_key().to_bytes()),
+        })
+    );
+}
+
+/// RFC 7638 thumbprint, the required members in lexicographic order.
+fn thumbprint(parameters: &AlgorithmParameters) -> String {
+    let members = match parameters {
+        AlgorithmParameters::RSA(key) =>
+            format!(r#"{{"e":"{}","kty":"RSA","n":"{}"}}"#, key.e, key.n),
+        AlgorithmParameters::EllipticCurve(key) =>
+            format!(r#"{{"crv":"P-256","kty":"EC","x":"{}","y":"{}"}}"#, key.x, key.y),
+        AlgorithmParameters::OctetKeyPair(key) =>
+            format!(r#"{{"crv":"Ed25519","kty":"OKP","x":"{}"}}"#, key.x),
+        AlgorithmParameters::OctetKey(key) => format!(r#"{{"k":"{}","kty":"oct"}}"#, key.value),
+    };
+    return URL_SAFE_NO_PAD.encode(Sha256::digest(members.as_bytes()));
+}
@@ -0,0 +1,171 @@
+use std::{ sync::Arc, time::Duration };
+
+use chrono::{ NaiveDateTime, Utc };
+use config::{ log::warn, CONFIGURATION };
+
+use crate::infra::{
+    database::login_attempt_repository::LoginAttemptRepository,
+    ldap::filter::normalize_identifier,
+};
+
+const IDENTIFIER_KIND: &str = "identifier";
+const IP_KIND: &str = "ip";
+const RESET_IDENTIFIER_KIND: &str = "reset_identifier";
+const RESET_IP_KIND: &str = "reset_ip";
+
+/// Tracks failed logins per identifier and per client address. Counters live in
+/// Postgres, so the limits hold across several server instances.
+pub struct LoginThrottleService {
+    login_attempt_repository: Arc<LoginAttemptRepository>,
+}
+
+impl LoginThrottleService {
+    pub fn new(login_attempt_repository: Arc<LoginAttemptRepository>) -> Arc<LoginThrottleService> {
+        return Arc::new(LoginThrottleService { login_attempt_repository });
+    }
+
+    /// Returns how long the client has to wait before the next attempt is checked.
+    pub fn retry_after(
+        &self,
+        identifier: &str,
+        client_ip: Option<&str>
+    ) -> Result<Option<Duration>, diesel::result::Error> {
+        return self.locked_for(&tracked_keys(identifier, client_ip), Utc::now().naive_utc());
+    }
+
+    /// Counts the failure. Failures of an identifier delay the next attempt progressively,
+    /// reaching a limit locks the identifier or address for the lockout duration.
+    pub fn record_failure(
+        &self,
+        identifier: &str,
+        client_ip: Option<&str>
+    ) -> Result<(), diesel::result::Error> {
+        let now = Utc::now().naive_utc();
+        let window_expired_before =
+            now - chrono::Duration::seconds(CONFIGURATION.login_failure_window as i64);
+        for (kind, key) in tracked_keys(identifier, client_ip) {
+            let attempt = self.login_attempt_repository.register_failure(
+                kind,
+                &key,
+                now,
+                window_expired_before
+            )?;
+            let max_failures = match kind {
+                IDENTIFIER_KIND => CONFIGURATION.login_max_failures_per_identifier,
+                _ => CONFIGURATION.login_max_failures_per_ip,
+            };
+            if attempt.failures >= max_failures {
+                if attempt.failures == max_failures {
+                    warn!("Too many failed logins for {} [{}], locking it", kind, key);
+                }
+                self.login_attempt_repository.lock(
+                    kind,
+                    &key,
+                    add_seconds(now, CONFIGURATION.login_lockout_duration)
+                )?;
+            } else if kind == IDENTIFIER_KIND {
+                let delay = progressive_delay(attempt.failures);
+                if delay > 0 {
+                    self.login_attempt_repository.lock(kind, &key, add_seconds(now, delay))?;
+                }
+            }
+        }
+        return Ok(());
+    }
+
+    /// Forgets the failures of the identifier. Address counters are kept, otherwise one
+    /// valid account would reset the limit for guessing others.
+    pub fn record_success(&self, identifier: &str) -> Result<(), diesel::result::Error> {
+        self.login_attempt_repository.delete(IDENTIFIER_KIND, &normalize_identifier(identifier))?;
+        return Ok(());
+    }
+
+    /// Counts a password reset request per login and per client address, separately from
+    /// failed logins. Returns how long the client has to wait when either asked for too
+    /// many links, no link is sent then.
+    pub fn register_reset_request(
+        &self,
+        identifier: &str,
+        client_ip: Option<&str>
+    ) -> Result<Option<Duration>, diesel::result::Error> {
+        let now = Utc::now().naive_utc();
+        let mut keys = vec![(RESET_IDENTIFIER_KIND, normalize_identifier(identifier))];
+        if let Some(client_ip) = client_ip {
+            keys.push((RESET_IP_KIND, client_ip.to_string()));
+        }
+        keys.retain(|(kind, _)| max_reset_requests(kind) > 0);
+        let retry_after = self.locked_for(&keys, now)?;
+        if retry_after.is_some() {
+            return Ok(retry_after);
+        }
+        let window_expired_before =
+            now - chrono::Duration::seconds(CONFIGURATION.login_failure_window as i64);
+        for (kind, key) in keys {
+            let attempt = self.login_attempt_repository.register_failure(
+                kind,
+                &key,
+                now,
+                window_expired_before
+            )?;
+            if attempt.failures >= max_reset_requests(kind) {
+                self.login_attempt_repository.lock(
+                    kind,
+                    &key,
+                    add_seconds(now, CONFIGURATION.login_lockout_duration)
+                )?;
+            }
+        }
+        return Ok(None);
+    }
+
+    fn locked_for(
+        &self,
+        keys: &[(&'static str, String)],
+        now: NaiveDateTime
+    ) -> Result<Option<Duration>, diesel::result::Error> {
+        let mut retry_after: Option<Duration> = None;
+        for (kind, key) in keys {
+            let locked_until = self.login_attempt_repository
+                .find(kind, key)?
+                .and_then(|attempt| attempt.locked_until);
+            if let Some(locked_until) = locked_until {
+                if let Ok(wait) = (locked_until - now).to_std() {
+                    retry_after = retry_after.max(Some(wait));
+                }
+            }
+        }
+        return Ok(retry_after);
+    }
+}
+
+fn max_reset_requests(kind: &str) -> i32 {
+    return match kind {
+        RESET_IDENTIFIER_KIND => CONFIGURATION.password_reset_max_requests_per_identifier,
+        _ => CONFIGURATION.password_reset_max_requests_per_ip,
+    };
+}
+
+fn tracked_keys(identifier: &str, client_ip: Option<&str>) -> Vec<(&'static str, String)> {
+    let mut keys = Vec::new();
+    if CONFIGURATION.login_max_failures_per_identifier > 0 {
+        keys.push((IDENTIFIER_KIND, normalize_identifier(identifier)));
+    }
+    if let Some(client_ip) = client_ip {
+        if CONFIGURATION.login_max_failures_per_ip > 0 {
+            keys.push((IP_KIND, client_ip.to_string()));
+        }
+    }
+    return keys;
+}
+
+/// Seconds the next attempt is delayed for, doubled with every failure.
+fn progressive_delay(failures: i32) -> u64 {
+    let factor = (1u64).checked_shl((failures - 1).max(0) as u32).unwrap_or(u64::MAX);
+    return CONFIGURATION.login_delay_initial
+        .saturating_mul(factor)
+        .min(CONFIGURATION.login_delay_max);
+}
+
+fn add_seconds(time: NaiveDateTime, seconds: u64) -> NaiveDateTime {
+    return time + chrono::Duration::seconds(seconds.min(i64::MAX as u64) as i64);
+}
@@ -1,6 +1,46 @@
+use std::{ fs, path::Path, time::UNIX_EPOCH };
+
+use config::CONFIGURATION;
+use sha2::{ Digest, Sha256 };
+
+use crate::filesystem::image_storage_service::IMAGE_EXTENSIONS;
+
 pub mod user_service;
+pub mod api_token_service;
+pub mod audit_service;
 pub mod auth_service;
+pub mod impersonation_service;
+pub mod jwt_key_service;
+pub mod login_throttle_service;
+pub mod notifiers;
+pub mod oauth_service;
+pub mod password_reset_service;
+pub mod providers;
+pub mod registration_service;
+pub mod session_purge;
+pub mod totp_service;
+
+/// Files are named by a hash of the provider and the id, so every account gets its own
+/// name whatever characters the id contains.
+pub fn user_image_name(provider: &str, user_id: &str, extension: &str) -> String {
+    let digest = Sha256::digest(format!("{}:{}", provider, user_id).as_bytes());
+    return format!("users/{}.{}", hex::encode(digest), extension);
+}
 
-pub fn user_image_name(username: &str) -> String {
-    return format!("users/user_{}.png", username);
+/// Public URL of the user's image, files are served from FILE_STORAGE_LOCATION under
+/// /static. The modification time makes clients reload a replaced image.
+pub fn user_image_url(provider: &str, user_id: &str) -> Option<String> {
+    for extension in IMAGE_EXTENSIONS {
+        let image_name = user_image_name(provider, user_id, extension);
+        let path = Path::new(&CONFIGURATION.file_storage_location).join(&image_name);
+        if let Ok(metadata) = fs::metadata(path) {
+            let version = metadata
+                .modified()
+                .ok()
+                .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+                .map_or(0, |modified| modified.as_secs());
+            return Some(format!("/static/{}?v={}", image_name, version));
+        }
+    }
+    return None;
 }
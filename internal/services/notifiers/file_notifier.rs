@@ -0,0 +1,46 @@
+use std::{ fs::OpenOptions, io::Write, sync::Arc };
+
+use actix_web::web;
+use async_trait::async_trait;
+use chrono::{ NaiveDateTime, Utc };
+use serde::Serialize;
+
+use crate::infra::domain::notification::NotificationDTO;
+
+use super::{ Notifier, NotifierError };
+
+pub const FILE_NOTIFIER: &str = "file";
+
+#[derive(Serialize)]
+struct FileNotification {
+    sent_at: NaiveDateTime,
+    #[serde(flatten)]
+    notification: NotificationDTO,
+}
+
+/// Appends notifications to a file as JSON lines, so flows can be tested offline.
+pub struct FileNotifier {
+    path: Arc<str>,
+}
+
+impl FileNotifier {
+    pub fn new(path: &str) -> Arc<FileNotifier> {
+        return Arc::new(FileNotifier { path: Arc::from(path) });
+    }
+}
+
+#[async_trait]
+impl Notifier for FileNotifier {
+    async fn send(&self, notification: NotificationDTO) -> Result<(), NotifierError> {
+        let mut line = serde_json::to_string(
+            &(FileNotification { sent_at: Utc::now().naive_utc(), notification })
+        )?;
+        line.push('\n');
+        let path = self.path.clone();
+        web::block(move || {
+            let mut file = OpenOptions::new().create(true).append(true).open(path.as_ref())?;
+            return file.write_all(line.as_bytes());
+        }).await??;
+        return Ok(());
+    }
+}
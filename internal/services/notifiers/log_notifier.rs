@@ -0,0 +1,33 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use config::log::info;
+
+use crate::infra::domain::notification::NotificationDTO;
+
+use super::{ Notifier, NotifierError };
+
+pub const LOG_NOTIFIER: &str = "log";
+
+/// Writes notifications to the application log, meant for development only.
+pub struct LogNotifier;
+
+impl LogNotifier {
+    pub fn new() -> Arc<LogNotifier> {
+        return Arc::new(LogNotifier);
+    }
+}
+
+#[async_trait]
+impl Notifier for LogNotifier {
+    async fn send(&self, notification: NotificationDTO) -> Result<(), NotifierError> {
+        info!(
+            "Notification for [{}] <{}>: {}\n{}",
+            notification.user_id,
+            notification.recipient,
+            notification.subject,
+            notification.body
+        );
+        return Ok(());
+    }
+}
@@ -0,0 +1,21 @@
+use async_trait::async_trait;
+use thiserror::Error;
+
+use crate::infra::domain::notification::NotificationDTO;
+
+pub mod file_notifier;
+pub mod log_notifier;
+
+#[derive(Error, Debug)]
+pub enum NotifierError {
+    #[error("{0}")] IoError(#[from] std::io::Error),
+    #[error("{0}")] SerializationError(#[from] serde_json::Error),
+    #[error("{0}")] BlockingError(#[from] actix_web::error::BlockingError),
+}
+
+/// Delivers messages to users. The built-in notifiers work offline, an e-mail or chat
+/// notifier implements this trait and is selected by NOTIFIER in the container.
+#[async_trait]
+pub trait Notifier: Send + Sync {
+    async fn send(&self, notification: NotificationDTO) -> Result<(), NotifierError>;
+}
@@ -0,0 +1,215 @@
+use std::sync::Arc;
+
+use chrono::Utc;
+use config::CONFIGURATION;
+use thiserror::Error;
+
+use crate::infra::{
+    database::{
+        refresh_token_repository::{ RefreshToken, RefreshTokenRepository },
+        session_repository::SessionRepository,
+    },
+    domain::{ oauth::IntrospectionDTO, session::SessionDTO },
+};
+
+use super::{
+    api_token_service::{ ApiTokenService, ApiTokenServiceError, API_TOKEN_PREFIX },
+    auth_service::{ hash_token, Claims },
+    jwt_key_service::JwtKeyService,
+};
+
+const ACCESS_TOKEN_TYPE: &str = "access_token";
+const REFRESH_TOKEN_TYPE: &str = "refresh_token";
+const API_TOKEN_TYPE: &str = "api_token";
+
+#[derive(Error, Debug)]
+pub enum OAuthServiceError {
+    #[error("{0}")] DieselError(#[from] diesel::result::Error),
+    #[error("{0}")] ApiTokenError(#[from] ApiTokenServiceError),
+}
+
+enum IssuedToken {
+    Access(Claims),
+    Refresh(RefreshToken),
+}
+
+impl IssuedToken {
+    fn session(&self) -> SessionDTO {
+        return match self {
+            IssuedToken::Access(claims) => SessionDTO::new(claims.user_id.clone(), claims.uuid),
+            IssuedToken::Refresh(token) =>
+                SessionDTO::new(Arc::from(token.user_id.as_str()), token.session_uuid),
+        };
+    }
+}
+
+/// Token introspection and revocation for other services, which authenticate with the
+/// client credentials from OAUTH_CLIENTS.
+pub struct OAuthService {
+    jwt_key_service: Arc<JwtKeyService>,
+    session_repository: Arc<SessionRepository>,
+    refresh_token_repository: Arc<RefreshTokenRepository>,
+    api_token_service: Arc<ApiTokenService>,
+}
+
+impl OAuthService {
+    pub fn new(
+        jwt_key_service: Arc<JwtKeyService>,
+        session_repository: Arc<SessionRepository>,
+        refresh_token_repository: Arc<RefreshTokenRepository>,
+        api_token_service: Arc<ApiTokenService>
+    ) -> Arc<OAuthService> {
+        return Arc::new(OAuthService {
+            jwt_key_service,
+            session_repository,
+            refresh_token_repository,
+            api_token_service,
+        });
+    }
+
+    pub fn authenticate_client(&self, client_id: &str, client_secret: &str) -> bool {
+        let secret_hash = hash_token(client_secret);
+        return CONFIGURATION.oauth_clients
+            .iter()
+            .any(|(id, hash)| id == client_id && *hash == secret_hash);
+    }
+
+    /// A token is active while it is valid and its session was neither revoked nor expired.
+    /// API tokens have no session, they are active until they expire or are revoked.
+    pub async fn introspect(
+        &self,
+        token: &str,
+        token_type_hint: Option<&str>
+    ) -> Result<IntrospectionDTO, OAuthServiceError> {
+        if is_api_token(token) {
+            return match self.api_token_service.authenticate(token).await? {
+                Some(claims) => Ok(claims_introspection(claims, API_TOKEN_TYPE)),
+                None => Ok(IntrospectionDTO::default()),
+            };
+        }
+        let issued_token = match self.find_token(token, token_type_hint)? {
+            Some(issued_token) => issued_token,
+            None => {
+                return Ok(IntrospectionDTO::default());
+            }
+        };
+        let session = self.session_repository.find(issued_token.session())?;
+        let now = Utc::now().naive_utc();
+        if session.is_none_or(|session| session.expires_at <= now) {
+            return Ok(IntrospectionDTO::default());
+        }
+        return match issued_token {
+            IssuedToken::Access(claims) => Ok(claims_introspection(claims, ACCESS_TOKEN_TYPE)),
+            IssuedToken::Refresh(token) => {
+                if token.used || token.expires_at <= now {
+                    return Ok(IntrospectionDTO::default());
+                }
+                Ok(IntrospectionDTO {
+                    active: true,
+                    sub: Some(Arc::from(token.user_id.as_str())),
+                    exp: Some(token.expires_at.and_utc().timestamp()),
+                    roles: None,
+                    token_type: Some(REFRESH_TOKEN_TYPE),
+                })
+            }
+        };
+    }
+
+    /// Ends the session the token belongs to, which invalidates every token issued for it.
+    /// API tokens are deleted on their own. Unknown tokens are ignored as RFC 7009 requires.
+    pub fn revoke(
+        &self,
+        token: &str,
+        token_type_hint: Option<&str>
+    ) -> Result<(), OAuthServiceError> {
+        if is_api_token(token) {
+            self.api_token_service.revoke_by_token(token)?;
+            return Ok(());
+        }
+        if let Some(issued_token) = self.find_token(token, token_type_hint)? {
+            self.session_repository.delete(issued_token.session())?;
+        }
+        return Ok(());
+    }
+
+    /// The hint only decides which kind of token is looked up first.
+    fn find_token(
+        &self,
+        token: &str,
+        token_type_hint: Option<&str>
+    ) -> Result<Option<IssuedToken>, OAuthServiceError> {
+        if token_type_hint == Some(REFRESH_TOKEN_TYPE) {
+            if let Some(refresh_token) = self.find_refresh_token(token)? {
+                return Ok(Some(refresh_token));
+            }
+            return Ok(self.find_access_token(token));
+        }
+        if let Some(access_token) = self.find_access_token(token) {
+            return Ok(Some(access_token));
+        }
+        return self.find_refresh_token(token);
+    }
+
+    fn find_access_token(&self, token: &str) -> Option<IssuedToken> {
+        return self.jwt_key_service.decode::<Claims>(token).ok().map(IssuedToken::Access);
+    }
+
+    fn find_refresh_token(&self, token: &str) -> Result<Option<IssuedToken>, OAuthServiceError> {
+        let refresh_token = self.refresh_token_repository.find_by_hash(&hash_token(token))?;
+        return Ok(refresh_token.map(IssuedToken::Refresh));
+    }
+}
+
+/// API tokens are told apart by their prefix whatever the hint says, they are neither
+/// JWTs nor refresh tokens.
+fn is_api_token(token: &str) -> bool {
+    return token.starts_with(API_TOKEN_PREFIX);
+}
+
+fn claims_introspection(claims: Claims, token_type: &'static str) -> IntrospectionDTO {
+    return IntrospectionDTO {
+        active: true,
+        sub: Some(claims.user_id),
+        exp: Some(claims.exp as i64),
+        roles: Some(claims.roles),
+        token_type: Some(token_type),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use uuid::Uuid;
+
+    use super::{ claims_introspection, is_api_token, API_TOKEN_TYPE };
+    use crate::services::auth_service::Claims;
+
+    #[test]
+    fn routes_api_tokens_by_their_prefix() {
+        assert!(is_api_token("pat_3q2+7w=="));
+        assert!(!is_api_token("eyJhbGciOiJIUzI1NiJ9.eyJzdWIiOiJwYXRfIn0.c2ln"));
+        assert!(!is_api_token("Zm9vYmFyYmF6"));
+        assert!(!is_api_token("PAT_3q2+7w=="));
+    }
+
+    #[test]
+    fn reports_api_tokens_as_active_with_their_scopes() {
+        let token_id = Uuid::new_v4();
+        let claims = Claims {
+            user_id: Arc::from("john@example.com"),
+            uuid: token_id,
+            exp: 1_900_000_000,
+            roles: vec![Arc::from("reader")],
+            provider: Arc::from("ldap"),
+            api_token_id: Some(token_id),
+            act: None,
+        };
+        let introspection = claims_introspection(claims, API_TOKEN_TYPE);
+        assert!(introspection.active);
+        assert_eq!(introspection.sub.as_deref(), Some("john@example.com"));
+        assert_eq!(introspection.exp, Some(1_900_000_000));
+        assert_eq!(introspection.roles, Some(vec![Arc::from("reader")]));
+        assert_eq!(introspection.token_type, Some(API_TOKEN_TYPE));
+    }
+}
@@ -0,0 +1,167 @@
+use std::{ sync::Arc, time::Duration };
+
+use chrono::Utc;
+use config::{ log::info, CONFIGURATION };
+use thiserror::Error;
+
+use crate::infra::{
+    database::{
+        password_reset_token_repository::{
+            NewPasswordResetToken,
+            PasswordResetTokenRepository,
+        },
+        session_repository::SessionRepository,
+    },
+    domain::notification::NotificationDTO,
+    http::requests::user_request::{ PasswordForgotRequest, PasswordResetRequest },
+    ldap::{
+        filter::normalize_identifier,
+        ldap_client::LdapClientError,
+        password_policy::PasswordChangeError,
+    },
+};
+
+use super::{
+    api_token_service::{ ApiTokenService, ApiTokenServiceError },
+    auth_service::{ generate_random_token, hash_token },
+    login_throttle_service::LoginThrottleService,
+    notifiers::{ Notifier, NotifierError },
+    providers::{ AuthenticationProviderError, AuthenticationProviders },
+};
+
+const RESET_TOKEN_LENGTH: usize = 64;
+
+#[derive(Error, Debug)]
+pub enum PasswordResetServiceError {
+    #[error("{0}")] DieselError(#[from] diesel::result::Error),
+    #[error("{0}")] ProviderError(#[from] AuthenticationProviderError),
+    #[error("{0}")] NotifierError(#[from] NotifierError),
+    #[error("{0}")] ApiTokenError(#[from] ApiTokenServiceError),
+    #[error("Too many password reset requests, try again later")] TooManyRequests(Duration),
+    #[error("Reset token is not valid or expired")] InvalidToken,
+    #[error("{0}")] PasswordRejected(#[source] PasswordChangeError),
+}
+
+/// Forgotten passwords. Reset tokens are stored as hashes, expire after
+/// PASSWORD_RESET_TTL and can be used once.
+pub struct PasswordResetService {
+    providers: Arc<AuthenticationProviders>,
+    password_reset_token_repository: Arc<PasswordResetTokenRepository>,
+    session_repository: Arc<SessionRepository>,
+    api_token_service: Arc<ApiTokenService>,
+    login_throttle_service: Arc<LoginThrottleService>,
+    notifier: Arc<dyn Notifier>,
+}
+
+impl PasswordResetService {
+    pub fn new(
+        providers: Arc<AuthenticationProviders>,
+        password_reset_token_repository: Arc<PasswordResetTokenRepository>,
+        session_repository: Arc<SessionRepository>,
+        api_token_service: Arc<ApiTokenService>,
+        login_throttle_service: Arc<LoginThrottleService>,
+        notifier: Arc<dyn Notifier>
+    ) -> Arc<PasswordResetService> {
+        return Arc::new(PasswordResetService {
+            providers,
+            password_reset_token_repository,
+            session_repository,
+            api_token_service,
+            login_throttle_service,
+            notifier,
+        });
+    }
+
+    /// Sends a reset link to the user. Unknown logins are ignored, so the response does not
+    /// tell which accounts exist. A new link invalidates the previous ones, requests are
+    /// throttled per login and per client address so nobody can flood a user with links.
+    pub async fn forgot(
+        &self,
+        request: PasswordForgotRequest,
+        client_ip: Option<&str>
+    ) -> Result<(), PasswordResetServiceError> {
+        let login = normalize_identifier(&request.login);
+        let retry_after = self.login_throttle_service.register_reset_request(&login, client_ip)?;
+        if let Some(retry_after) = retry_after {
+            return Err(PasswordResetServiceError::TooManyRequests(retry_after));
+        }
+        let (user, provider) = match
+            self.providers.find_any_user_with_provider(Arc::from(login.as_str())).await?
+        {
+            Some(found) => found,
+            None => {
+                info!("Password reset requested for unknown login [{}]", login);
+                return Ok(());
+            }
+        };
+        let token = generate_random_token(RESET_TOKEN_LENGTH);
+        self.password_reset_token_repository.delete_by_user_id(&user.id)?;
+        self.password_reset_token_repository.save(NewPasswordResetToken {
+            token_hash: hash_token(&token),
+            user_id: user.id.to_string(),
+            provider: provider.to_string(),
+            expires_at: Utc::now().naive_utc() +
+            chrono::Duration::seconds(CONFIGURATION.password_reset_ttl as i64),
+        })?;
+        self.notifier.send(NotificationDTO {
+            user_id: user.id.clone(),
+            recipient: user.email.clone(),
+            subject: String::from("Password reset"),
+            body: reset_message(&token),
+        }).await?;
+        return Ok(());
+    }
+
+    /// Sets the new password and revokes all sessions and API tokens of the user. The token
+    /// stays usable when the password was rejected, so the user can try another one.
+    pub async fn reset(
+        &self,
+        request: PasswordResetRequest
+    ) -> Result<(), PasswordResetServiceError> {
+        let hash = hash_token(&request.token);
+        let token = match self.password_reset_token_repository.consume(&hash)? {
+            Some(token) if token.expires_at > Utc::now().naive_utc() => token,
+            _ => {
+                return Err(PasswordResetServiceError::InvalidToken);
+            }
+        };
+        let user_id: Arc<str> = Arc::from(token.user_id.as_str());
+        let user = match self.providers.find_user(&token.provider, user_id).await? {
+            Some(user) => user,
+            None => {
+                return Err(PasswordResetServiceError::InvalidToken);
+            }
+        };
+        let reset = self.providers.reset_password(
+            &token.provider,
+            &user,
+            &request.new_password
+        ).await;
+        if let Err(e) = reset {
+            self.password_reset_token_repository.release(&hash)?;
+            return match e {
+                AuthenticationProviderError::LDAPError(LdapClientError::PasswordChange(e)) =>
+                    Err(PasswordResetServiceError::PasswordRejected(e)),
+                e => Err(PasswordResetServiceError::ProviderError(e)),
+            };
+        }
+        self.password_reset_token_repository.delete_by_user_id(&user.id)?;
+        self.session_repository.delete_by_user_id(user.id.to_string())?;
+        self.api_token_service.revoke_all(&token.provider, &user.id)?;
+        info!("Password of [{}] was reset", user.id);
+        return Ok(());
+    }
+}
+
+fn reset_message(token: &str) -> String {
+    let link = match CONFIGURATION.password_reset_url.is_empty() {
+        true => token.to_string(),
+        false => CONFIGURATION.password_reset_url.replace("{token}", token),
+    };
+    return format!(
+        "Use this link to set a new password, it is valid for {} minutes:\n{}\n\n\
+         Ignore this message if you did not ask for a new password.",
+        (CONFIGURATION.password_reset_ttl / 60).max(1),
+        link
+    );
+}
@@ -0,0 +1,121 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use config::CONFIGURATION;
+
+use crate::infra::{
+    database::{
+        pending_registration_repository::PendingRegistrationRepository,
+        user_repository::{ User, UserRepository, UserRepositoryError },
+    },
+    ldap::{
+        ldap_client::{ LdapClient, LdapClientError },
+        password_policy::PasswordChangeError,
+    },
+};
+
+use super::{ AuthenticationProvider, AuthenticationProviderError };
+
+pub const LDAP_PROVIDER: &str = "ldap";
+
+/// Looks the user up in the directory and verifies the password with a bind.
+/// Self-registered users can not log in until their email address is verified.
+pub struct LdapAuthenticationProvider {
+    ldap: Arc<LdapClient>,
+    user_repository: Arc<UserRepository>,
+    pending_registration_repository: Arc<PendingRegistrationRepository>,
+}
+
+impl LdapAuthenticationProvider {
+    pub fn new(
+        ldap: Arc<LdapClient>,
+        user_repository: Arc<UserRepository>,
+        pending_registration_repository: Arc<PendingRegistrationRepository>
+    ) -> Arc<LdapAuthenticationProvider> {
+        return Arc::new(LdapAuthenticationProvider {
+            ldap,
+            user_repository,
+            pending_registration_repository,
+        });
+    }
+}
+
+#[async_trait]
+impl AuthenticationProvider for LdapAuthenticationProvider {
+    fn name(&self) -> &'static str {
+        return LDAP_PROVIDER;
+    }
+
+    async fn authenticate(
+        &self,
+        login: &str,
+        password: &str
+    ) -> Result<Option<User>, AuthenticationProviderError> {
+        let user = match self.find_user(Arc::from(login)).await? {
+            Some(user) => user,
+            None => {
+                return Ok(None);
+            }
+        };
+        if !self.ldap.verify_credentials(&user.dn, password).await? {
+            return Err(AuthenticationProviderError::InvalidCredentials);
+        }
+        let is_pending =
+            CONFIGURATION.ldap_registration_verify_email &&
+            self.pending_registration_repository.find_by_user_id(&user.id)?.is_some();
+        if is_pending {
+            return Err(AuthenticationProviderError::NotVerified);
+        }
+        return Ok(Some(user));
+    }
+
+    async fn find_user(
+        &self,
+        user_id: Arc<str>
+    ) -> Result<Option<User>, AuthenticationProviderError> {
+        return match self.user_repository.find_by_id(user_id).await {
+            Ok(user) => Ok(Some(user)),
+            Err(UserRepositoryError::NotFound) => Ok(None),
+            Err(e) => Err(e.into()),
+        };
+    }
+
+    async fn find_users(&self) -> Result<Vec<User>, AuthenticationProviderError> {
+        return Ok(self.user_repository.find_all().await?);
+    }
+
+    async fn is_taken(
+        &self,
+        user_id: &str,
+        email: &str
+    ) -> Result<bool, AuthenticationProviderError> {
+        return Ok(self.user_repository.is_taken(user_id, email).await?);
+    }
+
+    async fn find_roles(&self, user: &User) -> Result<Vec<Arc<str>>, AuthenticationProviderError> {
+        return Ok(self.user_repository.find_roles(user).await?);
+    }
+
+    async fn change_password(
+        &self,
+        user: &User,
+        current_password: &str,
+        new_password: &str
+    ) -> Result<(), AuthenticationProviderError> {
+        return match self.ldap.change_password(&user.dn, current_password, new_password).await {
+            Ok(_) => Ok(()),
+            Err(LdapClientError::PasswordChange(PasswordChangeError::InvalidCredentials)) => {
+                Err(AuthenticationProviderError::InvalidCredentials)
+            }
+            Err(e) => Err(e.into()),
+        };
+    }
+
+    async fn reset_password(
+        &self,
+        user: &User,
+        new_password: &str
+    ) -> Result<(), AuthenticationProviderError> {
+        return Ok(self.ldap.reset_password(&user.dn, new_password).await?);
+    }
+}
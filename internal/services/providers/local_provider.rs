@@ -0,0 +1,123 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use pwhash::bcrypt;
+
+use crate::infra::{
+    database::{ local_user_repository::LocalUserRepository, user_repository::User },
+    ldap::filter::normalize_identifier,
+};
+
+use super::{
+    super::auth_service::hash_user_password,
+    AuthenticationProvider,
+    AuthenticationProviderError,
+};
+
+pub const LOCAL_PROVIDER: &str = "local";
+
+/// Accounts stored in Postgres with bcrypt password hashes, e.g. break-glass accounts
+/// which keep working while the directory is down.
+pub struct LocalAuthenticationProvider {
+    local_user_repository: Arc<LocalUserRepository>,
+}
+
+impl LocalAuthenticationProvider {
+    pub fn new(
+        local_user_repository: Arc<LocalUserRepository>
+    ) -> Arc<LocalAuthenticationProvider> {
+        return Arc::new(LocalAuthenticationProvider { local_user_repository });
+    }
+}
+
+#[async_trait]
+impl AuthenticationProvider for LocalAuthenticationProvider {
+    fn name(&self) -> &'static str {
+        return LOCAL_PROVIDER;
+    }
+
+    async fn authenticate(
+        &self,
+        login: &str,
+        password: &str
+    ) -> Result<Option<User>, AuthenticationProviderError> {
+        let user = self.local_user_repository.find_by_id(&normalize_identifier(login))?;
+        return match user {
+            Some(user) if bcrypt::verify(password, &user.password) => Ok(Some(user.to_user())),
+            Some(_) => Err(AuthenticationProviderError::InvalidCredentials),
+            None => Ok(None),
+        };
+    }
+
+    async fn find_user(
+        &self,
+        user_id: Arc<str>
+    ) -> Result<Option<User>, AuthenticationProviderError> {
+        let user = self.local_user_repository.find_by_id(&normalize_identifier(&user_id))?;
+        return Ok(user.map(|user| user.to_user()));
+    }
+
+    async fn find_users(&self) -> Result<Vec<User>, AuthenticationProviderError> {
+        let users = self.local_user_repository.find_all()?;
+        return Ok(
+            users
+                .iter()
+                .map(|user| user.to_user())
+                .collect()
+        );
+    }
+
+    async fn is_taken(
+        &self,
+        user_id: &str,
+        email: &str
+    ) -> Result<bool, AuthenticationProviderError> {
+        let is_taken = self.local_user_repository.exists(
+            &normalize_identifier(user_id),
+            email.trim()
+        )?;
+        return Ok(is_taken);
+    }
+
+    async fn find_roles(&self, user: &User) -> Result<Vec<Arc<str>>, AuthenticationProviderError> {
+        let local_user = self.local_user_repository.find_by_id(&user.id)?;
+        let mut roles: Vec<Arc<str>> = local_user
+            .map(|local_user| local_user.roles)
+            .unwrap_or_default()
+            .iter()
+            .map(|role| Arc::from(role.to_lowercase().as_str()))
+            .collect();
+        roles.sort_unstable();
+        roles.dedup();
+        return Ok(roles);
+    }
+
+    async fn change_password(
+        &self,
+        user: &User,
+        current_password: &str,
+        new_password: &str
+    ) -> Result<(), AuthenticationProviderError> {
+        let local_user = match self.local_user_repository.find_by_id(&user.id)? {
+            Some(local_user) if bcrypt::verify(current_password, &local_user.password) => {
+                local_user
+            }
+            _ => {
+                return Err(AuthenticationProviderError::InvalidCredentials);
+            }
+        };
+        let password = hash_user_password(new_password)?;
+        self.local_user_repository.update_password(&local_user.id, &password)?;
+        return Ok(());
+    }
+
+    async fn reset_password(
+        &self,
+        user: &User,
+        new_password: &str
+    ) -> Result<(), AuthenticationProviderError> {
+        let password = hash_user_password(new_password)?;
+        self.local_user_repository.update_password(&user.id, &password)?;
+        return Ok(());
+    }
+}
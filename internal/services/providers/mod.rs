@@ -0,0 +1,230 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use config::log::warn;
+use thiserror::Error;
+
+use crate::infra::{
+    database::user_repository::{ User, UserRepositoryError },
+    ldap::ldap_client::LdapClientError,
+};
+
+pub mod ldap_provider;
+pub mod local_provider;
+
+#[derive(Error, Debug)]
+pub enum AuthenticationProviderError {
+    #[error("Invalid login or password")] InvalidCredentials,
+    #[error("Email address of this account is not verified yet")] NotVerified,
+    #[error("Unknown authentication provider [{0}]")] UnknownProvider(String),
+    #[error("{0}")] LDAPError(#[from] LdapClientError),
+    #[error("{0}")] UserError(#[from] UserRepositoryError),
+    #[error("{0}")] DieselError(#[from] diesel::result::Error),
+    #[error("{0}")] ArgonError(#[from] pwhash::error::Error),
+}
+
+impl AuthenticationProviderError {
+    /// Errors caused by an unreachable backend, the next provider is tried on them.
+    pub fn is_unavailable(&self) -> bool {
+        return match self {
+            AuthenticationProviderError::LDAPError(e) => e.is_unavailable(),
+            AuthenticationProviderError::UserError(UserRepositoryError::LdapError(e)) =>
+                e.is_unavailable(),
+            _ => false,
+        };
+    }
+}
+
+/// Source of user accounts which is able to check credentials and resolve roles.
+#[async_trait]
+pub trait AuthenticationProvider: Send + Sync {
+    /// Name stored with the session, later requests resolve the user with the same provider.
+    fn name(&self) -> &'static str;
+
+    /// Returns `None` when the provider does not know the login, so the next one can be tried.
+    async fn authenticate(
+        &self,
+        login: &str,
+        password: &str
+    ) -> Result<Option<User>, AuthenticationProviderError>;
+
+    async fn find_user(
+        &self,
+        user_id: Arc<str>
+    ) -> Result<Option<User>, AuthenticationProviderError>;
+
+    async fn find_users(&self) -> Result<Vec<User>, AuthenticationProviderError>;
+
+    /// Whether an account already uses the login identifier or the email address.
+    async fn is_taken(
+        &self,
+        user_id: &str,
+        email: &str
+    ) -> Result<bool, AuthenticationProviderError>;
+
+    async fn find_roles(&self, user: &User) -> Result<Vec<Arc<str>>, AuthenticationProviderError>;
+
+    /// Fails with `InvalidCredentials` when the current password is wrong.
+    async fn change_password(
+        &self,
+        user: &User,
+        current_password: &str,
+        new_password: &str
+    ) -> Result<(), AuthenticationProviderError>;
+
+    /// Sets the password without the current one, the caller has verified the user.
+    async fn reset_password(
+        &self,
+        user: &User,
+        new_password: &str
+    ) -> Result<(), AuthenticationProviderError>;
+}
+
+/// Configured providers in the order they are tried on login.
+pub struct AuthenticationProviders {
+    providers: Vec<Arc<dyn AuthenticationProvider>>,
+}
+
+impl AuthenticationProviders {
+    pub fn new(providers: Vec<Arc<dyn AuthenticationProvider>>) -> Arc<AuthenticationProviders> {
+        return Arc::new(AuthenticationProviders { providers });
+    }
+
+    pub fn get(
+        &self,
+        name: &str
+    ) -> Result<&Arc<dyn AuthenticationProvider>, AuthenticationProviderError> {
+        return self.providers
+            .iter()
+            .find(|provider| provider.name() == name)
+            .ok_or_else(|| AuthenticationProviderError::UnknownProvider(name.to_string()));
+    }
+
+    /// Tries the providers in order. A provider which does not know the login or is
+    /// unavailable passes it on to the next one, a wrong password stops the chain.
+    pub async fn authenticate(
+        &self,
+        login: &str,
+        password: &str
+    ) -> Result<(&'static str, User), AuthenticationProviderError> {
+        let mut last_error: Option<AuthenticationProviderError> = None;
+        for provider in self.providers.iter() {
+            match provider.authenticate(login, password).await {
+                Ok(Some(user)) => {
+                    return Ok((provider.name(), user));
+                }
+                Ok(None) => {
+                    continue;
+                }
+                Err(e) if e.is_unavailable() => {
+                    warn!("Authentication provider [{}] is unavailable: {}", provider.name(), e);
+                    last_error = Some(e);
+                }
+                Err(e) => {
+                    return Err(e);
+                }
+            }
+        }
+        return Err(last_error.unwrap_or(AuthenticationProviderError::InvalidCredentials));
+    }
+
+    pub async fn find_user(
+        &self,
+        name: &str,
+        user_id: Arc<str>
+    ) -> Result<Option<User>, AuthenticationProviderError> {
+        return self.get(name)?.find_user(user_id).await;
+    }
+
+    /// Checks the id and the email with every provider. An unavailable provider fails the
+    /// check, a new account must not shadow one which can not be looked up right now.
+    pub async fn is_taken(
+        &self,
+        user_id: &str,
+        email: &str
+    ) -> Result<bool, AuthenticationProviderError> {
+        for provider in self.providers.iter() {
+            if provider.is_taken(user_id, email).await? {
+                return Ok(true);
+            }
+        }
+        return Ok(false);
+    }
+
+    /// Finds the user with the first provider which knows the id.
+    pub async fn find_any_user(
+        &self,
+        user_id: Arc<str>
+    ) -> Result<Option<User>, AuthenticationProviderError> {
+        let user = self.find_any_user_with_provider(user_id).await?;
+        return Ok(user.map(|(user, _)| user));
+    }
+
+    /// Like `find_any_user`, also returns the name of the provider which knows the user.
+    pub async fn find_any_user_with_provider(
+        &self,
+        user_id: Arc<str>
+    ) -> Result<Option<(User, &'static str)>, AuthenticationProviderError> {
+        let mut last_error: Option<AuthenticationProviderError> = None;
+        for provider in self.providers.iter() {
+            match provider.find_user(user_id.clone()).await {
+                Ok(Some(user)) => {
+                    return Ok(Some((user, provider.name())));
+                }
+                Ok(None) => {
+                    continue;
+                }
+                Err(e) if e.is_unavailable() => {
+                    last_error = Some(e);
+                }
+                Err(e) => {
+                    return Err(e);
+                }
+            }
+        }
+        return match last_error {
+            Some(e) => Err(e),
+            None => Ok(None),
+        };
+    }
+
+    /// Users of all providers, an id known to several providers belongs to the first one.
+    pub async fn find_all_users(&self) -> Result<Vec<User>, AuthenticationProviderError> {
+        let mut users: Vec<User> = Vec::new();
+        for provider in self.providers.iter() {
+            for user in provider.find_users().await? {
+                if !users.iter().any(|existing| existing.id == user.id) {
+                    users.push(user);
+                }
+            }
+        }
+        return Ok(users);
+    }
+
+    pub async fn find_roles(
+        &self,
+        name: &str,
+        user: &User
+    ) -> Result<Vec<Arc<str>>, AuthenticationProviderError> {
+        return self.get(name)?.find_roles(user).await;
+    }
+
+    pub async fn change_password(
+        &self,
+        name: &str,
+        user: &User,
+        current_password: &str,
+        new_password: &str
+    ) -> Result<(), AuthenticationProviderError> {
+        return self.get(name)?.change_password(user, current_password, new_password).await;
+    }
+
+    pub async fn reset_password(
+        &self,
+        name: &str,
+        user: &User,
+        new_password: &str
+    ) -> Result<(), AuthenticationProviderError> {
+        return self.get(name)?.reset_password(user, new_password).await;
+    }
+}
@@ -0,0 +1,243 @@
+use std::sync::Arc;
+
+use chrono::Utc;
+use config::{ log::{ error, info }, CONFIGURATION };
+use thiserror::Error;
+
+use crate::infra::{
+    database::{
+        pending_registration_repository::{
+            NewPendingRegistration,
+            PendingRegistrationRepository,
+        },
+        user_repository::{ User, UserRepository, UserRepositoryError },
+    },
+    domain::notification::NotificationDTO,
+    http::{
+        requests::user_request::{ RegistrationVerifyRequest, UserRequest },
+        resources::user_resource::UserResponse,
+    },
+    ldap::{
+        entry::{ registration_entry, NewEntry },
+        filter::normalize_identifier,
+        ldap_client::LdapClientError,
+        password_policy::PasswordChangeError,
+    },
+};
+
+use super::{
+    auth_service::{ generate_random_token, hash_token },
+    notifiers::{ Notifier, NotifierError },
+    providers::{
+        ldap_provider::LDAP_PROVIDER,
+        AuthenticationProviderError,
+        AuthenticationProviders,
+    },
+};
+
+const VERIFICATION_TOKEN_LENGTH: usize = 64;
+
+#[derive(Error, Debug)]
+pub enum RegistrationServiceError {
+    #[error("{0}")] DieselError(#[from] diesel::result::Error),
+    #[error("{0}")] ProviderError(#[from] AuthenticationProviderError),
+    #[error("{0}")] UserError(#[from] UserRepositoryError),
+    #[error("{0}")] LdapError(#[from] LdapClientError),
+    #[error("{0}")] NotifierError(#[from] NotifierError),
+    #[error("Registration is disabled")] RegistrationDisabled,
+    #[error("User with this email already exists")] UserAlreadyExists,
+    #[error("Verification token is not valid or expired")] InvalidToken,
+    #[error("{0}")] PasswordRejected(#[source] PasswordChangeError),
+}
+
+/// Directory accounts created by self-registration or by administrators. Entries are added
+/// with the service account, which needs write access to LDAP_AUTH_BASE_DN. With
+/// LDAP_REGISTRATION_VERIFY_EMAIL self-registered accounts stay pending until the link sent
+/// to their email address was opened.
+pub struct RegistrationService {
+    providers: Arc<AuthenticationProviders>,
+    user_repository: Arc<UserRepository>,
+    pending_registration_repository: Arc<PendingRegistrationRepository>,
+    notifier: Arc<dyn Notifier>,
+}
+
+impl RegistrationService {
+    pub fn new(
+        providers: Arc<AuthenticationProviders>,
+        user_repository: Arc<UserRepository>,
+        pending_registration_repository: Arc<PendingRegistrationRepository>,
+        notifier: Arc<dyn Notifier>
+    ) -> Arc<RegistrationService> {
+        return Arc::new(RegistrationService {
+            providers,
+            user_repository,
+            pending_registration_repository,
+            notifier,
+        });
+    }
+
+    pub async fn register(
+        &self,
+        request: UserRequest
+    ) -> Result<UserResponse, RegistrationServiceError> {
+        let is_enabled =
+            CONFIGURATION.ldap_registration_enabled && self.providers.get(LDAP_PROVIDER).is_ok();
+        if !is_enabled {
+            return Err(RegistrationServiceError::RegistrationDisabled);
+        }
+        return self.provision(request, CONFIGURATION.ldap_registration_verify_email).await;
+    }
+
+    /// Account created by an administrator, it is active right away and does not depend
+    /// on LDAP_REGISTRATION_ENABLED.
+    pub async fn create(
+        &self,
+        request: UserRequest
+    ) -> Result<UserResponse, RegistrationServiceError> {
+        self.providers.get(LDAP_PROVIDER)?;
+        return self.provision(request, false).await;
+    }
+
+    /// Creates the directory entry and sets its password. An entry which is left behind by
+    /// an expired pending registration is replaced.
+    async fn provision(
+        &self,
+        request: UserRequest,
+        verify_email: bool
+    ) -> Result<UserResponse, RegistrationServiceError> {
+        let user_id = normalize_identifier(&request.email);
+        // Ids are shared between providers, a local user must not be shadowed either.
+        let existing_user = self.providers.find_any_user_with_provider(
+            Arc::from(user_id.as_str())
+        ).await?;
+        if let Some((user, provider)) = existing_user {
+            if provider != LDAP_PROVIDER || !self.remove_expired_registration(&user).await? {
+                return Err(RegistrationServiceError::UserAlreadyExists);
+            }
+        }
+        if self.user_repository.is_taken(&user_id, &request.email).await? {
+            return Err(RegistrationServiceError::UserAlreadyExists);
+        }
+        let entry = registration_entry(
+            &[
+                ("{id}", &user_id),
+                ("{name}", request.name.trim()),
+                ("{email}", request.email.trim()),
+                ("{avatar}", request.avatar.as_deref().unwrap_or_default().trim()),
+            ]
+        )?;
+        match self.user_repository.create(&entry).await {
+            Ok(_) => {}
+            Err(UserRepositoryError::AlreadyExists(_)) => {
+                return Err(RegistrationServiceError::UserAlreadyExists);
+            }
+            Err(e) => {
+                return Err(e.into());
+            }
+        }
+        let activated = self.activate(&entry, &user_id, &request.password, verify_email).await;
+        let user = match activated {
+            Ok(user) => user,
+            Err(e) => {
+                if let Err(delete_error) = self.user_repository.delete(&entry.dn).await {
+                    error!("Failed to remove incomplete entry [{}]: {}", entry.dn, delete_error);
+                }
+                self.pending_registration_repository.delete_by_user_id(&user_id)?;
+                return Err(e);
+            }
+        };
+        info!("Registered [{}] as [{}]", user.id, user.dn);
+        return Ok(UserResponse::user_to_response(&user));
+    }
+
+    /// Ends the pending state of the account, the token can be used once.
+    pub fn verify(
+        &self,
+        request: RegistrationVerifyRequest
+    ) -> Result<(), RegistrationServiceError> {
+        let registration = self.pending_registration_repository
+            .consume(&hash_token(&request.token), Utc::now().naive_utc())?
+            .ok_or(RegistrationServiceError::InvalidToken)?;
+        info!("Email address of [{}] was verified", registration.user_id);
+        return Ok(());
+    }
+
+    /// Sets the password of the new entry with the service account, so the directory
+    /// hashes it and applies its password policy, then starts the verification.
+    async fn activate(
+        &self,
+        entry: &NewEntry,
+        user_id: &str,
+        password: &str,
+        verify_email: bool
+    ) -> Result<User, RegistrationServiceError> {
+        let user = match self.user_repository.find_by_id_on_primary(Arc::from(user_id)).await {
+            Ok(user) => user,
+            Err(UserRepositoryError::NotFound) => {
+                // The template does not set LDAP_LOGIN_ATTRIBUTE to {id}.
+                let message = format!("Entry [{}] is not found by its login", entry.dn);
+                return Err(LdapClientError::Configuration(message).into());
+            }
+            Err(e) => {
+                return Err(e.into());
+            }
+        };
+        let reset = self.providers.reset_password(LDAP_PROVIDER, &user, password).await;
+        match reset {
+            Ok(_) => {}
+            Err(AuthenticationProviderError::LDAPError(LdapClientError::PasswordChange(e))) => {
+                return Err(RegistrationServiceError::PasswordRejected(e));
+            }
+            Err(e) => {
+                return Err(e.into());
+            }
+        }
+        if verify_email {
+            let token = generate_random_token(VERIFICATION_TOKEN_LENGTH);
+            self.pending_registration_repository.save(NewPendingRegistration {
+                user_id: user.id.to_string(),
+                token_hash: hash_token(&token),
+                expires_at: Utc::now().naive_utc() +
+                chrono::Duration::seconds(CONFIGURATION.ldap_registration_verify_ttl as i64),
+            })?;
+            self.notifier.send(NotificationDTO {
+                user_id: user.id.clone(),
+                recipient: user.email.clone(),
+                subject: String::from("Email verification"),
+                body: verification_message(&token),
+            }).await?;
+        }
+        return Ok(user);
+    }
+
+    /// Removes the entry of a registration which was not verified in time.
+    async fn remove_expired_registration(
+        &self,
+        user: &User
+    ) -> Result<bool, RegistrationServiceError> {
+        let registration = self.pending_registration_repository.find_by_user_id(&user.id)?;
+        let is_expired = registration.is_some_and(|registration| {
+            return registration.expires_at <= Utc::now().naive_utc();
+        });
+        if !is_expired {
+            return Ok(false);
+        }
+        self.user_repository.delete(&user.dn).await?;
+        self.pending_registration_repository.delete_by_user_id(&user.id)?;
+        info!("Removed unverified registration of [{}]", user.id);
+        return Ok(true);
+    }
+}
+
+fn verification_message(token: &str) -> String {
+    let link = match CONFIGURATION.ldap_registration_verify_url.is_empty() {
+        true => token.to_string(),
+        false => CONFIGURATION.ldap_registration_verify_url.replace("{token}", token),
+    };
+    return format!(
+        "Use this link to verify your email address, it is valid for {} hours:\n{}\n\n\
+         Ignore this message if you did not create an account.",
+        (CONFIGURATION.ldap_registration_verify_ttl / 3600).max(1),
+        link
+    );
+}
@@ -0,0 +1,29 @@
+use std::{ sync::Arc, time::Duration };
+
+use actix_web::{ rt, web };
+use chrono::Utc;
+use config::{ log::{ error, info }, CONFIGURATION };
+
+use crate::infra::database::session_repository::SessionRepository;
+
+/// Periodically deletes expired sessions together with their refresh tokens.
+pub fn spawn_session_purge(session_repository: Arc<SessionRepository>) {
+    if CONFIGURATION.session_purge_interval == 0 {
+        return;
+    }
+    rt::spawn(async move {
+        let mut interval = rt::time::interval(
+            Duration::from_secs(CONFIGURATION.session_purge_interval)
+        );
+        loop {
+            interval.tick().await;
+            let repository = Arc::clone(&session_repository);
+            match web::block(move || repository.delete_expired(Utc::now().naive_utc())).await {
+                Ok(Ok(deleted)) if deleted > 0 => info!("Purged {} expired sessions", deleted),
+                Ok(Ok(_)) => {}
+                Ok(Err(e)) => error!("Failed to purge expired sessions: {}", e),
+                Err(e) => error!("Failed to purge expired sessions: {}", e),
+            }
+        }
+    });
+}
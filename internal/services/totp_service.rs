@@ -0,0 +1,285 @@
+use std::{ sync::Arc, time::{ SystemTime, UNIX_EPOCH } };
+
+use aes_gcm::{ aead::{ Aead, KeyInit, Payload }, Aes256Gcm, Nonce };
+use config::CONFIGURATION;
+use rand::Rng;
+use thiserror::Error;
+use totp_rs::{ Algorithm, Secret, TotpUrlError, TOTP };
+
+use crate::infra::{
+    database::totp_repository::{ NewRecoveryCode, NewUserTotp, TotpRepository, UserTotp },
+    domain::totp::{ RecoveryCodesDTO, TotpEnrollmentDTO },
+    ldap::filter::normalize_identifier,
+};
+
+use super::{
+    auth_service::{ hash_token, Claims },
+    providers::{ AuthenticationProviderError, AuthenticationProviders },
+};
+
+const TOTP_DIGITS: usize = 6;
+const TOTP_STEP: u64 = 30;
+/// Accepted clock drift in steps in both directions.
+const TOTP_SKEW: i64 = 1;
+const NONCE_LENGTH: usize = 12;
+const RECOVERY_CODE_ALPHABET: &[u8] = b"abcdefghijkmnpqrstuvwxyz23456789";
+const RECOVERY_CODE_GROUP_LENGTH: usize = 5;
+
+pub struct TotpService {
+    totp_repository: Arc<TotpRepository>,
+    providers: Arc<AuthenticationProviders>,
+}
+
+#[derive(Error, Debug)]
+pub enum TotpServiceError {
+    #[error("{0}")] DieselError(#[from] diesel::result::Error),
+    #[error("{0}")] UrlError(#[from] TotpUrlError),
+    #[error("{0}")] ProviderError(#[from] AuthenticationProviderError),
+    #[error("Two-factor authentication is not configured")] NotConfigured,
+    #[error("Two-factor authentication is already enabled")] AlreadyEnabled,
+    #[error("Two-factor authentication is not enabled")] NotEnabled,
+    #[error("Two-factor enrollment was not started")] NotEnrolled,
+    #[error("Two-factor authentication can only be changed in your own session")] NotAllowed,
+    #[error("Code is not valid")] InvalidCode,
+    #[error("Secret can not be encrypted or decrypted")] EncryptionError,
+}
+
+impl TotpService {
+    pub fn new(
+        totp_repository: Arc<TotpRepository>,
+        providers: Arc<AuthenticationProviders>
+    ) -> Arc<TotpService> {
+        return Arc::new(TotpService { totp_repository, providers });
+    }
+
+    pub fn is_enabled(&self, provider: &str, user_id: &str) -> Result<bool, TotpServiceError> {
+        let totp = self.totp_repository.find(provider, user_id)?;
+        return Ok(totp.is_some_and(|totp| totp.enabled));
+    }
+
+    /// Generates a new secret which stays pending until a code from it is verified.
+    pub fn enroll(
+        &self,
+        claims: &Claims,
+        account_name: &str
+    ) -> Result<TotpEnrollmentDTO, TotpServiceError> {
+        check_own_session(claims)?;
+        let (provider, user_id) = (claims.provider.as_ref(), claims.user_id.as_ref());
+        if self.is_enabled(provider, user_id)? {
+            return Err(TotpServiceError::AlreadyEnabled);
+        }
+        let secret = Secret::generate_secret()
+            .to_bytes()
+            .map_err(|_| TotpServiceError::EncryptionError)?;
+        let totp = build_totp(secret.clone(), account_name)?;
+        self.totp_repository.save_pending(NewUserTotp {
+            provider: provider.to_string(),
+            user_id: user_id.to_string(),
+            secret: encrypt_secret(provider, user_id, &secret)?,
+        })?;
+        return Ok(TotpEnrollmentDTO {
+            secret: Arc::from(totp.get_secret_base32().as_str()),
+            provisioning_uri: Arc::from(totp.get_url().as_str()),
+        });
+    }
+
+    /// Enables two-factor authentication with a code from the pending secret and issues
+    /// new recovery codes. The codes are returned only once.
+    pub fn enable(
+        &self,
+        claims: &Claims,
+        code: &str
+    ) -> Result<RecoveryCodesDTO, TotpServiceError> {
+        check_own_session(claims)?;
+        let (provider, user_id) = (claims.provider.as_ref(), claims.user_id.as_ref());
+        let totp = self.totp_repository
+            .find(provider, user_id)?
+            .ok_or(TotpServiceError::NotEnrolled)?;
+        if totp.enabled {
+            return Err(TotpServiceError::AlreadyEnabled);
+        }
+        let step = find_step(&totp, code)?.ok_or(TotpServiceError::InvalidCode)?;
+        let recovery_codes = generate_recovery_codes();
+        self.totp_repository.enable(
+            provider,
+            user_id,
+            step,
+            recovery_codes
+                .iter()
+                .map(|code| NewRecoveryCode {
+                    code_hash: hash_token(&normalize_recovery_code(code)),
+                    provider: provider.to_string(),
+                    user_id: user_id.to_string(),
+                })
+                .collect()
+        )?;
+        return Ok(RecoveryCodesDTO {
+            recovery_codes: recovery_codes
+                .iter()
+                .map(|code| Arc::from(code.as_str()))
+                .collect(),
+        });
+    }
+
+    /// Checks a TOTP code or an unused recovery code, both can be used only once.
+    pub fn verify(
+        &self,
+        provider: &str,
+        user_id: &str,
+        code: &str
+    ) -> Result<bool, TotpServiceError> {
+        let totp = match self.totp_repository.find(provider, user_id)? {
+            Some(totp) if totp.enabled => totp,
+            _ => {
+                return Err(TotpServiceError::NotEnabled);
+            }
+        };
+        let code = code.trim();
+        if code.len() == TOTP_DIGITS && code.chars().all(|c| c.is_ascii_digit()) {
+            return match find_step(&totp, code)? {
+                Some(step) => Ok(self.totp_repository.use_step(provider, user_id, step)?),
+                None => Ok(false),
+            };
+        }
+        return Ok(
+            self.totp_repository.use_recovery_code(
+                provider,
+                user_id,
+                &hash_token(&normalize_recovery_code(code))
+            )?
+        );
+    }
+
+    /// Turns two-factor authentication off after the user confirmed it with a code.
+    pub fn disable(&self, claims: &Claims, code: &str) -> Result<(), TotpServiceError> {
+        check_own_session(claims)?;
+        if !self.verify(&claims.provider, &claims.user_id, code)? {
+            return Err(TotpServiceError::InvalidCode);
+        }
+        self.totp_repository.delete(&claims.provider, &claims.user_id)?;
+        return Ok(());
+    }
+
+    /// Removes the secret and recovery codes without a code, used by administrators.
+    /// The account is resolved like the other admin routes do, with the first provider
+    /// which knows the id.
+    pub async fn reset(&self, user_id: &str) -> Result<bool, TotpServiceError> {
+        let user_id = normalize_identifier(user_id);
+        let user = self.providers.find_any_user_with_provider(Arc::from(user_id.as_str())).await?;
+        return match user {
+            Some((_, provider)) => Ok(self.totp_repository.delete(provider, &user_id)? > 0),
+            None => Ok(false),
+        };
+    }
+}
+
+/// Only the user in their own session, not an API token or an impersonating admin who
+/// could lock the user out.
+fn check_own_session(claims: &Claims) -> Result<(), TotpServiceError> {
+    if claims.api_token_id.is_some() || claims.act.is_some() {
+        return Err(TotpServiceError::NotAllowed);
+    }
+    return Ok(());
+}
+
+fn build_totp(secret: Vec<u8>, account_name: &str) -> Result<TOTP, TotpServiceError> {
+    let totp = TOTP::new(
+        Algorithm::SHA1,
+        TOTP_DIGITS,
+        0,
+        TOTP_STEP,
+        secret,
+        Some(CONFIGURATION.totp_issuer.clone()),
+        account_name.replace(':', "")
+    )?;
+    return Ok(totp);
+}
+
+/// Returns the time step the code belongs to, steps older than the last used one are
+/// not checked.
+fn find_step(totp: &UserTotp, code: &str) -> Result<Option<i64>, TotpServiceError> {
+    let secret = decrypt_secret(&totp.provider, &totp.user_id, &totp.secret)?;
+    let generator = build_totp(secret, &totp.user_id)?;
+    let current_step = (SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() /
+        TOTP_STEP) as i64;
+    for step in current_step - TOTP_SKEW..=current_step + TOTP_SKEW {
+        if totp.last_used_step.is_some_and(|last_used_step| step <= last_used_step) {
+            continue;
+        }
+        if generator.check(code, (step as u64) * TOTP_STEP) {
+            return Ok(Some(step));
+        }
+    }
+    return Ok(None);
+}
+
+fn cipher() -> Result<Aes256Gcm, TotpServiceError> {
+    if CONFIGURATION.totp_encryption_key.is_empty() {
+        return Err(TotpServiceError::NotConfigured);
+    }
+    let key = hex
+        ::decode(&CONFIGURATION.totp_encryption_key)
+        .map_err(|_| TotpServiceError::NotConfigured)?;
+    return Aes256Gcm::new_from_slice(&key).map_err(|_| TotpServiceError::NotConfigured);
+}
+
+/// Encrypts the secret with AES-256-GCM, the provider and the user id are bound as
+/// associated data so a secret can not be moved to another account. The nonce is
+/// prepended to the ciphertext.
+fn encrypt_secret(
+    provider: &str,
+    user_id: &str,
+    secret: &[u8]
+) -> Result<String, TotpServiceError> {
+    let nonce: [u8; NONCE_LENGTH] = rand::random();
+    let aad = associated_data(provider, user_id);
+    let ciphertext = cipher()?
+        .encrypt(Nonce::from_slice(&nonce), Payload { msg: secret, aad: aad.as_bytes() })
+        .map_err(|_| TotpServiceError::EncryptionError)?;
+    return Ok(hex::encode([nonce.as_slice(), ciphertext.as_slice()].concat()));
+}
+
+fn decrypt_secret(
+    provider: &str,
+    user_id: &str,
+    encrypted: &str
+) -> Result<Vec<u8>, TotpServiceError> {
+    let encrypted = hex::decode(encrypted).map_err(|_| TotpServiceError::EncryptionError)?;
+    if encrypted.len() <= NONCE_LENGTH {
+        return Err(TotpServiceError::EncryptionError);
+    }
+    let (nonce, ciphertext) = encrypted.split_at(NONCE_LENGTH);
+    let aad = associated_data(provider, user_id);
+    return cipher()?
+        .decrypt(Nonce::from_slice(nonce), Payload { msg: ciphertext, aad: aad.as_bytes() })
+        .map_err(|_| TotpServiceError::EncryptionError);
+}
+
+fn associated_data(provider: &str, user_id: &str) -> String {
+    return format!("{}:{}", provider, user_id);
+}
+
+fn generate_recovery_codes() -> Vec<String> {
+    let mut rng = rand::thread_rng();
+    return (0..CONFIGURATION.totp_recovery_codes)
+        .map(|_| {
+            let mut code: String = (0..RECOVERY_CODE_GROUP_LENGTH * 2)
+                .map(|_| {
+                    return RECOVERY_CODE_ALPHABET[
+                        rng.gen_range(0..RECOVERY_CODE_ALPHABET.len())
+                    ] as char;
+                })
+                .collect();
+            code.insert(RECOVERY_CODE_GROUP_LENGTH, '-');
+            return code;
+        })
+        .collect();
+}
+
+fn normalize_recovery_code(code: &str) -> String {
+    return code
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+}
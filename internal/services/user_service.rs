@@ -1,23 +1,62 @@
 use core::error;
 use std::sync::Arc;
 use async_trait::async_trait;
-use config::log::error;
+use config::{ log::{ error, info }, CONFIGURATION };
 use thiserror::Error;
 
-use crate::infra::{
-    database::user_repository::UserRepository,
-    domain::user::UserDTO,
-    http::middlewares::Findable,
+use crate::{
+    filesystem::image_storage_service::{ image_extension, ImageStorageService, IMAGE_EXTENSIONS },
+    infra::{
+        database::{
+            api_token_repository::ApiTokenRepository,
+            password_reset_token_repository::PasswordResetTokenRepository,
+            pending_registration_repository::PendingRegistrationRepository,
+            session_repository::SessionRepository,
+            totp_repository::TotpRepository,
+            user_repository::{ User, UserRepository, UserRepositoryError },
+        },
+        domain::user::UserDTO,
+        http::{ middlewares::Findable, requests::user_request::UserUpdateRequest },
+        ldap::filter::normalize_identifier,
+    },
+};
+
+use super::{
+    providers::{
+        ldap_provider::LDAP_PROVIDER,
+        AuthenticationProviderError,
+        AuthenticationProviders,
+    },
+    user_image_name,
 };
 
 pub struct UserService {
+    providers: Arc<AuthenticationProviders>,
     user_repository: Arc<UserRepository>,
+    user_data_repositories: UserDataRepositories,
+    image_storage_service: ImageStorageService,
+}
+
+/// Storage keyed by the login of a directory user, grouped to keep the constructor of
+/// `UserService` short.
+pub struct UserDataRepositories {
+    pub session_repository: Arc<SessionRepository>,
+    pub api_token_repository: Arc<ApiTokenRepository>,
+    pub totp_repository: Arc<TotpRepository>,
+    pub password_reset_token_repository: Arc<PasswordResetTokenRepository>,
+    pub pending_registration_repository: Arc<PendingRegistrationRepository>,
 }
 
 #[derive(Error, Debug)]
 pub enum UserServiceError {
     #[error("Database error: {0}")] DieselError(diesel::result::Error),
+    #[error("User was not found")] NotFound,
     #[error("{0}")] ServiceError(Box<dyn error::Error + Send + Sync + 'static>),
+    #[error("{0}")] ProviderError(#[from] AuthenticationProviderError),
+    #[error("{0}")] UserError(#[from] UserRepositoryError),
+    #[error("User with this email already exists")] UserAlreadyExists,
+    #[error("Administrators can not delete their own account")] DeleteSelf,
+    #[error("Image must be a PNG, JPEG, GIF or WebP file")] UnsupportedImage,
 }
 
 #[async_trait]
@@ -32,16 +71,24 @@ impl Findable<UserDTO> for UserService {
 }
 
 impl UserService {
-    pub fn new(user_repository: Arc<UserRepository>) -> Arc<UserService> {
+    pub fn new(
+        providers: Arc<AuthenticationProviders>,
+        user_repository: Arc<UserRepository>,
+        user_data_repositories: UserDataRepositories,
+        image_storage_service: ImageStorageService
+    ) -> Arc<UserService> {
         return Arc::from(UserService {
+            providers,
             user_repository,
+            user_data_repositories,
+            image_storage_service,
         });
     }
 
     pub async fn find_all(
         &self
     ) -> Result<Vec<UserDTO>, Box<dyn error::Error + Send + Sync + 'static>> {
-        let users = self.user_repository.find_all().await?;
+        let users = self.providers.find_all_users().await?;
         return Ok(UserDTO::models_to_dto(users));
     }
 
@@ -49,7 +96,308 @@ impl UserService {
         &self,
         user_id: Arc<str>
     ) -> Result<UserDTO, Box<dyn error::Error + Send + Sync + 'static>> {
-        let user = self.user_repository.find_by_id(user_id).await?;
+        let user = self.providers.find_any_user(user_id).await?.ok_or(UserServiceError::NotFound)?;
+        return Ok(UserDTO::model_to_dto(user));
+    }
+
+    /// Finds the user with the provider which authenticated the session.
+    pub async fn find_provider_user(
+        &self,
+        provider: &str,
+        user_id: Arc<str>
+    ) -> Result<UserDTO, Box<dyn error::Error + Send + Sync + 'static>> {
+        let user = self.providers
+            .find_user(provider, user_id).await?
+            .ok_or(UserServiceError::NotFound)?;
         return Ok(UserDTO::model_to_dto(user));
     }
+
+    /// Changes name and email of a directory user, fields which are `None` are kept.
+    /// A changed RDN attribute renames the entry. Sessions, API tokens, TOTP and reset
+    /// tokens are removed when the login of the user changed, they are keyed by it. A
+    /// pending registration and the avatar move to the new login.
+    pub async fn update(
+        &self,
+        user_id: &str,
+        request: UserUpdateRequest
+    ) -> Result<UserDTO, UserServiceError> {
+        let user = self.find_directory_user(user_id).await?;
+        let is_login = CONFIGURATION.ldap_attr_email.eq_ignore_ascii_case(
+            &CONFIGURATION.ldap_login_attribute
+        );
+        // The login attribute holds normalized identifiers which are unique across providers.
+        let email = match request.email.as_deref() {
+            Some(email) if is_login => Some(normalize_identifier(email)),
+            Some(email) => Some(email.trim().to_string()),
+            None => None,
+        };
+        if let Some(email) = email.as_deref().filter(|email| is_login && *email != &*user.id) {
+            let existing_user = self.providers.find_any_user(Arc::from(email)).await?;
+            if existing_user.is_some_and(|existing_user| existing_user.dn != user.dn) {
+                return Err(UserServiceError::UserAlreadyExists);
+            }
+        }
+        let mut changes: Vec<(&str, &str)> = Vec::new();
+        if let Some(name) = request.name.as_deref() {
+            changes.push((&CONFIGURATION.ldap_attr_name, name.trim()));
+        }
+        if let Some(email) = email.as_deref() {
+            changes.push((&CONFIGURATION.ldap_attr_email, email));
+        }
+        let updated_user = match self.user_repository.update(&user, &changes).await {
+            Ok(updated_user) => updated_user,
+            Err(UserRepositoryError::AlreadyExists(_)) => {
+                return Err(UserServiceError::UserAlreadyExists);
+            }
+            Err(e) => {
+                return Err(e.into());
+            }
+        };
+        if updated_user.id != user.id {
+            self.user_data_repositories
+                .rename(&user.id, &updated_user.id)
+                .map_err(UserServiceError::DieselError)?;
+            // The entry is renamed already, a lost avatar is not worth failing for.
+            let moved = move_avatars(
+                &self.image_storage_service,
+                LDAP_PROVIDER,
+                &user.id,
+                &updated_user.id
+            );
+            if let Err(e) = moved {
+                error!("Failed to move files of [{}]: {}", user.id, e);
+            }
+        }
+        info!("Updated directory user [{}]", updated_user.dn);
+        return Ok(UserDTO::model_to_dto(updated_user));
+    }
+
+    /// Deletes the directory entry together with the credentials and files of the user.
+    pub async fn delete(&self, actor_id: &str, user_id: &str) -> Result<(), UserServiceError> {
+        let user = self.find_directory_user(user_id).await?;
+        if *user.id == *actor_id {
+            return Err(UserServiceError::DeleteSelf);
+        }
+        self.user_repository.delete(&user.dn).await?;
+        self.user_data_repositories.delete(&user.id).map_err(UserServiceError::DieselError)?;
+        // The entry is gone already, a file left behind is not worth failing for.
+        if let Err(e) = self.remove_avatars(LDAP_PROVIDER, &user.id, None) {
+            error!("Failed to remove files of [{}]: {}", user.id, e);
+        }
+        info!("Deleted directory user [{}]", user.dn);
+        return Ok(());
+    }
+
+    /// Stores the avatar of the user in place of the previous one. Blocks on file I/O.
+    pub fn save_avatar(
+        &self,
+        provider: &str,
+        user_id: &str,
+        content: &[u8]
+    ) -> Result<(), UserServiceError> {
+        let extension = image_extension(content).ok_or(UserServiceError::UnsupportedImage)?;
+        self.image_storage_service
+            .save_image(&user_image_name(provider, user_id, extension), content)
+            .map_err(UserServiceError::ServiceError)?;
+        // An avatar of another type would otherwise shadow the new one.
+        self.remove_avatars(provider, user_id, Some(extension))?;
+        return Ok(());
+    }
+
+    /// Returns `false` when the user had no avatar. Blocks on file I/O.
+    pub fn delete_avatar(&self, provider: &str, user_id: &str) -> Result<bool, UserServiceError> {
+        return self.remove_avatars(provider, user_id, None);
+    }
+
+    fn remove_avatars(
+        &self,
+        provider: &str,
+        user_id: &str,
+        keep: Option<&str>
+    ) -> Result<bool, UserServiceError> {
+        let mut removed = false;
+        for extension in IMAGE_EXTENSIONS.iter().filter(|extension| Some(**extension) != keep) {
+            let image_name = user_image_name(provider, user_id, extension);
+            if self.image_storage_service.exists(&image_name) {
+                self.image_storage_service
+                    .remove_file_image(&image_name)
+                    .map_err(UserServiceError::ServiceError)?;
+                removed = true;
+            }
+        }
+        return Ok(removed);
+    }
+
+    async fn find_directory_user(&self, user_id: &str) -> Result<User, UserServiceError> {
+        let user_id: Arc<str> = Arc::from(normalize_identifier(user_id).as_str());
+        return self.providers
+            .find_user(LDAP_PROVIDER, user_id).await?
+            .ok_or(UserServiceError::NotFound);
+    }
+}
+
+/// Renames the avatar files of a user whose login changed. Files under the new login
+/// belong to no account and are removed first, so they can not shadow the moved ones.
+fn move_avatars(
+    image_storage_service: &ImageStorageService,
+    provider: &str,
+    user_id: &str,
+    new_user_id: &str
+) -> Result<(), UserServiceError> {
+    for extension in IMAGE_EXTENSIONS {
+        let new_image_name = user_image_name(provider, new_user_id, extension);
+        if image_storage_service.exists(&new_image_name) {
+            image_storage_service
+                .remove_file_image(&new_image_name)
+                .map_err(UserServiceError::ServiceError)?;
+        }
+    }
+    for extension in IMAGE_EXTENSIONS {
+        let image_name = user_image_name(provider, user_id, extension);
+        if image_storage_service.exists(&image_name) {
+            image_storage_service
+                .rename_file_image(&image_name, &user_image_name(provider, new_user_id, extension))
+                .map_err(UserServiceError::ServiceError)?;
+        }
+    }
+    return Ok(());
+}
+
+impl UserDataRepositories {
+    /// Removes everything stored under the login of a directory user, so nothing is
+    /// inherited by a later account with the same login.
+    pub fn delete(&self, user_id: &str) -> Result<(), diesel::result::Error> {
+        self.session_repository.delete_by_user_id(user_id.to_string())?;
+        self.api_token_repository.delete_by_user_id(LDAP_PROVIDER, user_id)?;
+        // Recovery codes are removed by the foreign key cascade.
+        self.totp_repository.delete(LDAP_PROVIDER, user_id)?;
+        self.password_reset_token_repository.delete_by_user_id(user_id)?;
+        self.pending_registration_repository.delete_by_user_id(user_id)?;
+        return Ok(());
+    }
+
+    /// Removes the credentials of the previous login of a renamed user. An unverified
+    /// account keeps its pending registration under the new login, it can not sign in
+    /// before the address is confirmed either way.
+    pub fn rename(&self, user_id: &str, new_user_id: &str) -> Result<(), diesel::result::Error> {
+        // No entry has the new login, a registration left under it is stale.
+        self.pending_registration_repository.delete_by_user_id(new_user_id)?;
+        self.pending_registration_repository.update_user_id(user_id, new_user_id)?;
+        return self.delete(user_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{ path::Path, sync::{ Arc, OnceLock, RwLock } };
+    use chrono::{ Duration, Utc };
+    use diesel::{ r2d2::{ ConnectionManager, Pool }, PgConnection };
+    use diesel_migrations::{ FileBasedMigrations, MigrationHarness };
+    use uuid::Uuid;
+
+    use crate::{
+        filesystem::image_storage_service::ImageStorageService,
+        infra::database::{
+            api_token_repository::ApiTokenRepository,
+            password_reset_token_repository::PasswordResetTokenRepository,
+            pending_registration_repository::{
+                NewPendingRegistration,
+                PendingRegistrationRepository,
+            },
+            session_repository::SessionRepository,
+            totp_repository::TotpRepository,
+        },
+        services::{ providers::ldap_provider::LDAP_PROVIDER, user_image_name },
+    };
+    use super::{ move_avatars, UserDataRepositories };
+
+    type DatabasePool = Arc<RwLock<Pool<ConnectionManager<PgConnection>>>>;
+
+    /// Connects to `TEST_DATABASE_URL` once and migrates it, `None` skips the test.
+    fn database_pool() -> Option<DatabasePool> {
+        static POOL: OnceLock<Option<DatabasePool>> = OnceLock::new();
+        return POOL.get_or_init(|| {
+            let url = std::env::var("TEST_DATABASE_URL").ok()?;
+            let manager = ConnectionManager::<PgConnection>::new(url);
+            let pool = Pool::builder().max_size(2).build(manager).unwrap();
+            let migrations = FileBasedMigrations::find_migrations_directory_in_path(
+                Path::new(env!("CARGO_MANIFEST_DIR")).join("infra/database/migrations")
+            ).unwrap();
+            pool.get().unwrap().run_pending_migrations(migrations).unwrap();
+            return Some(Arc::new(RwLock::new(pool)));
+        }).clone();
+    }
+
+    fn user_data_repositories(pool: &DatabasePool) -> UserDataRepositories {
+        return UserDataRepositories {
+            session_repository: SessionRepository::new(Arc::clone(pool)),
+            api_token_repository: ApiTokenRepository::new(Arc::clone(pool)),
+            totp_repository: TotpRepository::new(Arc::clone(pool)),
+            password_reset_token_repository: PasswordResetTokenRepository::new(Arc::clone(pool)),
+            pending_registration_repository: PendingRegistrationRepository::new(Arc::clone(pool)),
+        };
+    }
+
+    fn pending_registration(user_id: &str) -> NewPendingRegistration {
+        return NewPendingRegistration {
+            user_id: user_id.to_string(),
+            token_hash: Uuid::new_v4().to_string(),
+            expires_at: Utc::now().naive_utc() + Duration::hours(1),
+        };
+    }
+
+    #[test]
+    fn deleted_unverified_user_can_register_again() {
+        let Some(pool) = database_pool() else {
+            return;
+        };
+        let repositories = user_data_repositories(&pool);
+        let registrations = &repositories.pending_registration_repository;
+        let user_id = format!("{}@example.com", Uuid::new_v4());
+        registrations.save(pending_registration(&user_id)).unwrap();
+
+        repositories.delete(&user_id).unwrap();
+
+        assert!(registrations.find_by_user_id(&user_id).unwrap().is_none());
+        registrations.save(pending_registration(&user_id)).unwrap();
+        assert!(registrations.find_by_user_id(&user_id).unwrap().is_some());
+        registrations.delete_by_user_id(&user_id).unwrap();
+    }
+
+    #[test]
+    fn renamed_unverified_user_stays_unverified() {
+        let Some(pool) = database_pool() else {
+            return;
+        };
+        let repositories = user_data_repositories(&pool);
+        let registrations = &repositories.pending_registration_repository;
+        let user_id = format!("{}@example.com", Uuid::new_v4());
+        let new_user_id = format!("{}@example.com", Uuid::new_v4());
+        registrations.save(pending_registration(&user_id)).unwrap();
+        // A stale registration under the new login must not block the rename.
+        registrations.save(pending_registration(&new_user_id)).unwrap();
+
+        repositories.rename(&user_id, &new_user_id).unwrap();
+
+        assert!(registrations.find_by_user_id(&user_id).unwrap().is_none());
+        assert!(registrations.find_by_user_id(&new_user_id).unwrap().is_some());
+        registrations.delete_by_user_id(&new_user_id).unwrap();
+    }
+
+    #[test]
+    fn moves_avatars_to_the_new_login() {
+        let location = std::env::temp_dir().join(format!("avatars-{}", Uuid::new_v4()));
+        let storage = ImageStorageService::new(location.to_str().unwrap());
+        let avatar = user_image_name(LDAP_PROVIDER, "old@example.com", "png");
+        let stale_avatar = user_image_name(LDAP_PROVIDER, "new@example.com", "jpg");
+        storage.save_image(&avatar, b"\x89PNG\r\n\x1a\n").unwrap();
+        storage.save_image(&stale_avatar, b"\xff\xd8\xff").unwrap();
+
+        move_avatars(&storage, LDAP_PROVIDER, "old@example.com", "new@example.com").unwrap();
+
+        assert!(!storage.exists(&avatar));
+        assert!(!storage.exists(&stale_avatar));
+        assert!(storage.exists(&user_image_name(LDAP_PROVIDER, "new@example.com", "png")));
+        std::fs::remove_dir_all(location).unwrap();
+    }
 }